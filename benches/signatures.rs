@@ -0,0 +1,96 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::Rng;
+use secp256k1::{PublicKey, Secp256k1, SecretKey, Signature};
+use std::collections::HashSet;
+
+use blockchain::block::Block;
+use blockchain::transaction::{Transaction, TransactionOutput};
+use blockchain::utxo::{OutputType, Utxo, UtxoData, UtxoId};
+use blockchain::utxo_pool::UtxoPool;
+use blockchain::Hash;
+
+const TRANSACTIONS: usize = 256;
+const INPUTS_PER_TRANSACTION: usize = 2;
+
+fn random_key() -> (PublicKey, SecretKey) {
+    let mut rng = rand::thread_rng();
+    let secret_key = SecretKey::from_slice(&rng.gen::<[u8; 32]>()).unwrap();
+    let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+    (public_key, secret_key)
+}
+
+/// Block of transactions spending utxos of an output type, along with the pool holding them
+fn signed_block(output_type: OutputType) -> (Block, UtxoPool) {
+    let mut rng = rand::thread_rng();
+    let mut utxos = HashSet::new();
+    let transactions = (0..TRANSACTIONS)
+        .map(|_| {
+            let keys: Vec<_> = (0..INPUTS_PER_TRANSACTION).map(|_| random_key()).collect();
+            let spent: Vec<_> = keys
+                .iter()
+                .map(|(pk, _)| {
+                    let utxo_id = UtxoId::new(Hash::from(rng.gen::<[u8; 32]>()), 0);
+                    Utxo::new(utxo_id, UtxoData::with_type(1, *pk, output_type))
+                })
+                .collect();
+            utxos.extend(spent.iter().copied());
+            let outputs = vec![TransactionOutput::new(1, random_key().0)];
+            let secret_keys: Vec<_> = keys.iter().map(|(_, sk)| *sk).collect();
+            Transaction::sign_utxos(&spent, outputs, &secret_keys)
+        })
+        .collect();
+    let block = Block::new(&Block::genesis(), transactions).unwrap();
+    (block, UtxoPool::from(utxos))
+}
+
+/// Block validation as it was before the shared context: ECDSA signatures checked transaction
+/// after transaction, each with a context of its own
+fn authenticate_each(block: &Block, utxo_pool: &UtxoPool) {
+    for transaction in block.transactions() {
+        let secp = Secp256k1::verification_only();
+        let message = transaction.message();
+        for input in transaction.inputs() {
+            let utxo_data = &utxo_pool.utxos()[input.utxo_id()];
+            assert!(utxo_data.pays(input.public_key()));
+            let sig = Signature::from_compact(input.sig()).unwrap();
+            secp.verify(&message, &sig, input.public_key()).unwrap();
+        }
+    }
+}
+
+/// Block validation on a single thread against the whole thread pool, and for ECDSA against
+/// a context created per transaction
+fn check_signatures_of(c: &mut Criterion) {
+    let single_thread = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("check_signatures_of");
+    group.sample_size(10);
+    for output_type in [OutputType::Ecdsa, OutputType::Schnorr].iter() {
+        let (block, utxo_pool) = signed_block(*output_type);
+        if *output_type == OutputType::Ecdsa {
+            group.bench_with_input(
+                BenchmarkId::new("context_per_transaction", output_type),
+                &block,
+                |b, block| b.iter(|| authenticate_each(block, &utxo_pool)),
+            );
+        }
+        group.bench_with_input(
+            BenchmarkId::new("serial", output_type),
+            &block,
+            |b, block| {
+                b.iter(|| single_thread.install(|| utxo_pool.check_signatures_of(block).unwrap()))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("parallel", output_type),
+            &block,
+            |b, block| b.iter(|| utxo_pool.check_signatures_of(block).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, check_signatures_of);
+criterion_main!(benches);
@@ -0,0 +1,179 @@
+use secp256k1::PublicKey;
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::constants::{ADDRESS_PREFIX, KEY_HASH_BYTES};
+use crate::error::address::AddressError;
+use crate::utxo::{key_hash, KeyHash, OutputType};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_CHARS: usize = 6;
+const CHECKSUM_CONST: u32 = 0x2bc8_30a3;
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// Hash of a public key encoded for humans in Bech32m (BIP350), under the network prefix
+///
+/// As in a segwit v0 address, the program is the 20-byte key hash outputs pay, after the output
+/// type the recipient spends with as the version. The prefix is `ADDRESS_PREFIX`, as the
+/// parameters of the chain are its constants. The checksum detects any error in up to 4
+/// characters.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Address {
+    key_hash: KeyHash,
+    output_type: OutputType,
+}
+
+impl Address {
+    pub fn new(public_key: PublicKey) -> Self {
+        Self::with_type(public_key, OutputType::Ecdsa)
+    }
+
+    pub fn with_type(public_key: PublicKey, output_type: OutputType) -> Self {
+        Self::paying(key_hash(&public_key), output_type)
+    }
+
+    pub fn paying(key_hash: KeyHash, output_type: OutputType) -> Self {
+        Self {
+            key_hash,
+            output_type,
+        }
+    }
+
+    pub fn key_hash(&self) -> &KeyHash {
+        &self.key_hash
+    }
+
+    pub fn output_type(&self) -> OutputType {
+        self.output_type
+    }
+}
+
+/// Remainder of the BCH code over the values (BIP173)
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Prefix spread over 5-bit values so that the checksum covers it
+fn expand(prefix: &str) -> impl Iterator<Item = u8> + '_ {
+    let bytes = prefix.bytes();
+    bytes
+        .clone()
+        .map(|byte| byte >> 5)
+        .chain(Some(0))
+        .chain(bytes.map(|byte| byte & 31))
+}
+
+/// Regroups bits from groups of `from` bits to groups of `to`, padding the last group with
+/// zeros when `pad`, else failing if bits are left over
+pub fn regroup(values: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let (mut acc, mut bits) = (0u32, 0);
+    let mut groups = Vec::new();
+    for value in values {
+        acc = ((acc << from) | *value as u32) & ((1 << (from + to - 1)) - 1);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            groups.push(((acc >> bits) & ((1 << to) - 1)) as u8);
+        }
+    }
+    if pad && bits > 0 {
+        groups.push(((acc << (to - bits)) & ((1 << to) - 1)) as u8);
+    } else if !pad && (bits >= from || acc & ((1 << bits) - 1) != 0) {
+        return None;
+    }
+    Some(groups)
+}
+
+/// Encodes 5-bit values under a prefix in Bech32m, followed by their checksum
+pub fn encode(prefix: &str, values: &[u8]) -> String {
+    let checked = expand(prefix)
+        .chain(values.iter().copied())
+        .chain([0; CHECKSUM_CHARS].iter().copied());
+    let checksum = polymod(checked) ^ CHECKSUM_CONST;
+    let checksum = (0..CHECKSUM_CHARS).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8);
+    let chars: String = values
+        .iter()
+        .copied()
+        .chain(checksum)
+        .map(|value| CHARSET[value as usize] as char)
+        .collect();
+    format!("{}1{}", prefix, chars)
+}
+
+/// Decodes a Bech32m string into its lowercase prefix and 5-bit values, checksum removed
+pub fn decode(string: &str) -> Result<(String, Vec<u8>), AddressError> {
+    if string.chars().any(|c| c.is_ascii_lowercase())
+        && string.chars().any(|c| c.is_ascii_uppercase())
+    {
+        return Err(AddressError::MixedCase);
+    }
+    let string = string.to_ascii_lowercase();
+    let (prefix, chars) = string.split_at(string.rfind('1').ok_or(AddressError::NoPrefix)?);
+    let mut values = chars[1..]
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|&value| value == c)
+                .map(|v| v as u8)
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(AddressError::InvalidCharacter)?;
+    if values.len() < CHECKSUM_CHARS
+        || polymod(expand(prefix).chain(values.iter().copied())) != CHECKSUM_CONST
+    {
+        return Err(AddressError::WrongChecksum);
+    }
+    values.truncate(values.len() - CHECKSUM_CHARS);
+    Ok((prefix.to_string(), values))
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut values = vec![self.output_type as u8];
+        values.extend(regroup(&self.key_hash, 8, 5, true).unwrap());
+        write!(f, "{}", encode(ADDRESS_PREFIX, &values))
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let (prefix, values) = decode(address)?;
+        if prefix != ADDRESS_PREFIX {
+            return Err(AddressError::WrongNetwork(prefix));
+        }
+        let output_type = values
+            .first()
+            .and_then(|value| OutputType::from_byte(*value))
+            .ok_or(AddressError::UnknownOutputType)?;
+        regroup(&values[1..], 5, 8, false)
+            .filter(|bytes| bytes.len() == KEY_HASH_BYTES)
+            .map(|bytes| Self::paying(bytes[..].try_into().unwrap(), output_type))
+            .ok_or(AddressError::InvalidProgram)
+    }
+}
+
+impl From<PublicKey> for Address {
+    fn from(public_key: PublicKey) -> Self {
+        Self::new(public_key)
+    }
+}
@@ -1,7 +1,8 @@
+use sha2::{Digest, Sha256};
 use std::convert::TryInto;
 
 use self::target::Target;
-use crate::constants::TARGET;
+use crate::constants::{HEADER_BYTES, TARGET};
 use crate::Hash;
 
 #[derive(Clone, Debug)]
@@ -10,6 +11,7 @@ pub struct BlockHeader {
     hash_merkle_root: Hash,
     target: Target,
     nonce: u32,
+    extra_nonce: u32,
 }
 
 impl BlockHeader {
@@ -19,6 +21,7 @@ impl BlockHeader {
             hash_merkle_root,
             target: TARGET.into(),
             nonce: 0,
+            extra_nonce: 0,
         }
     }
 
@@ -28,18 +31,66 @@ impl BlockHeader {
             .chain(self.hash_merkle_root.into_iter())
             .chain(self.target.serialize())
             .chain(self.nonce.to_be_bytes().to_vec())
+            .chain(self.extra_nonce.to_be_bytes().to_vec())
             .collect()
     }
 
-    pub fn deserialize<B>(bytes: B) -> Self
+    /// Deserializes bytes that may be malformed, e.g. sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
     where
         B: AsRef<[u8]>,
     {
-        Self::from(bytes)
+        let bytes = bytes.as_ref();
+        if bytes.len() != HEADER_BYTES {
+            return None;
+        }
+        let mut i = 0;
+        let hash_prev_block = *Hash::from_slice(&bytes[i..i + 32]);
+        i += 32;
+        let hash_merkle_root = *Hash::from_slice(&bytes[i..i + 32]);
+        i += 32;
+        let target = Target::deserialize(&bytes[i..i + 4])?;
+        i += 4;
+        let nonce = u32::from_be_bytes(bytes[i..i + 4].try_into().unwrap());
+        i += 4;
+        let extra_nonce = u32::from_be_bytes(bytes[i..i + 4].try_into().unwrap());
+        Some(Self {
+            hash_prev_block,
+            hash_merkle_root,
+            target,
+            nonce,
+            extra_nonce,
+        })
+    }
+
+    pub fn hash(&self) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.input(self.serialize());
+        let hash = hasher.result_reset();
+        hasher.input(hash);
+        hasher.result()
     }
 
+    /// Checks that the hash of the header is below the target
+    pub fn has_proof_of_work(&self) -> bool {
+        self.hash() < self.target.hash()
+    }
+
+    /// Moves on to the next nonce, carrying over to the extra nonce once all nonces were tried
     pub fn inc_nonce(&mut self) {
-        self.nonce += 1;
+        let (nonce, overflow) = self.nonce.overflowing_add(1);
+        self.nonce = nonce;
+        if overflow {
+            self.extra_nonce = self.extra_nonce.wrapping_add(1);
+        }
+    }
+
+    pub fn set_nonce(&mut self, nonce: u32) {
+        self.nonce = nonce;
+    }
+
+    pub fn set_extra_nonce(&mut self, extra_nonce: u32) {
+        self.extra_nonce = extra_nonce;
     }
 
     pub fn hash_prev_block(&self) -> &Hash {
@@ -53,28 +104,21 @@ impl BlockHeader {
     pub fn target(&self) -> Target {
         self.target
     }
+
+    pub fn nonce(&self) -> u32 {
+        self.nonce
+    }
+
+    pub fn extra_nonce(&self) -> u32 {
+        self.extra_nonce
+    }
 }
 
-impl<B> From<B> for BlockHeader
-where
-    B: AsRef<[u8]>,
-{
-    fn from(bytes: B) -> Self {
-        let bytes = bytes.as_ref();
-        let mut i = 0;
-        let hash_prev_block = *Hash::from_slice(&bytes[i..i + 32]);
-        i += 32;
-        let hash_merkle_root = *Hash::from_slice(&bytes[i..i + 32]);
-        i += 32;
-        let target = Target::deserialize(&bytes[i..i + 4]);
-        i += 4;
-        let nonce = u32::from_be_bytes(bytes[i..i + 4].try_into().unwrap());
-        Self {
-            hash_prev_block,
-            hash_merkle_root,
-            target,
-            nonce,
-        }
+impl Eq for BlockHeader {}
+
+impl PartialEq for BlockHeader {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash() == other.hash()
     }
 }
 
@@ -41,11 +41,15 @@ impl Target {
         vec
     }
 
-    pub fn deserialize<B>(bytes: B) -> Self
+    /// Deserializes bytes that may be malformed, e.g. sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
     where
         B: AsRef<[u8]>,
     {
-        Self::from(bytes)
+        match *bytes.as_ref() {
+            [exponent, c0, c1, c2] => Self::new(exponent, [c0, c1, c2]).ok(),
+            _ => None,
+        }
     }
 }
 
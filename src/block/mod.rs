@@ -1,4 +1,3 @@
-use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::convert::TryInto;
 use std::fmt;
@@ -17,6 +16,7 @@ pub struct Block {
     height: usize,
     header: BlockHeader,
     transactions: Vec<Transaction>,
+    seal: Vec<u8>,
 }
 
 impl Block {
@@ -30,6 +30,7 @@ impl Block {
             height: 0,
             header,
             transactions,
+            seal: Vec::new(),
         }
     }
 
@@ -42,6 +43,7 @@ impl Block {
             height: 1 + parent.height(),
             header,
             transactions,
+            seal: Vec::new(),
         })
     }
 
@@ -57,11 +59,7 @@ impl Block {
     }
 
     pub fn hash(&self) -> Hash {
-        let mut hasher = Sha256::new();
-        hasher.input(self.header.serialize());
-        let hash = hasher.result_reset();
-        hasher.input(hash);
-        hasher.result()
+        self.header.hash()
     }
 
     pub fn target(&self) -> Target {
@@ -72,20 +70,67 @@ impl Block {
         self.header.inc_nonce()
     }
 
+    pub fn set_nonce(&mut self, nonce: u32) {
+        self.header.set_nonce(nonce)
+    }
+
+    pub fn set_extra_nonce(&mut self, extra_nonce: u32) {
+        self.header.set_extra_nonce(extra_nonce)
+    }
+
+    pub fn nonce(&self) -> u32 {
+        self.header.nonce()
+    }
+
+    pub fn extra_nonce(&self) -> u32 {
+        self.header.extra_nonce()
+    }
+
     pub fn serialize(&self) -> Vec<u8> {
         iter::once(b'b')
             .chain(self.height.to_be_bytes().iter().copied())
             .chain(self.transactions.len().to_be_bytes().iter().copied())
             .chain(self.header.serialize())
             .chain(self.transactions.iter().flat_map(|tx| tx.serialize()))
+            .chain(self.seal.len().to_be_bytes().iter().copied())
+            .chain(self.seal.iter().copied())
             .collect()
     }
 
-    pub fn deserialize<B>(bytes: B) -> Self
+    /// Deserializes bytes that may be malformed, e.g. sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
     where
         B: AsRef<[u8]>,
     {
-        Self::from(bytes)
+        let bytes = bytes.as_ref();
+        let len = |i: usize| {
+            let bytes = bytes.get(i..i.checked_add(8)?)?;
+            Some(usize::from_be_bytes(bytes.try_into().unwrap()))
+        };
+        let mut i = 1;
+        let height = len(i)?;
+        i += 8;
+        let transactions_len = len(i)?;
+        i += 8;
+        let header = BlockHeader::deserialize(bytes.get(i..i + HEADER_BYTES)?)?;
+        i += HEADER_BYTES;
+        let mut transactions = Vec::new();
+        for _j in 0..transactions_len {
+            let (transaction, size) = Transaction::deserialize(&bytes[i..])?;
+            transactions.push(transaction);
+            i += size;
+        }
+        let seal_len = len(i)?;
+        i += 8;
+        if bytes.len() - i != seal_len {
+            return None;
+        }
+        Some(Self {
+            height,
+            header,
+            transactions,
+            seal: bytes[i..].to_vec(),
+        })
     }
 
     pub fn check_transaction_count_is_power_of_two(&self) -> Result<(), BlockError> {
@@ -96,6 +141,14 @@ impl Block {
         }
     }
 
+    pub fn check_proof_of_work(&self) -> Result<(), BlockError> {
+        if self.header.has_proof_of_work() {
+            Ok(())
+        } else {
+            Err(BlockError::InsufficientProofOfWork)
+        }
+    }
+
     pub fn check_double_spending(&self) -> Result<(), BlockError> {
         let mut input_count = 0;
         let mut input_utxos = HashSet::new();
@@ -132,6 +185,19 @@ impl Block {
         self.height
     }
 
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+
+    /// Proof that the block was sealed according to the consensus engine, besides the header
+    pub fn seal(&self) -> &Vec<u8> {
+        &self.seal
+    }
+
+    pub fn set_seal(&mut self, seal: Vec<u8>) {
+        self.seal = seal;
+    }
+
     pub fn hash_prev_block(&self) -> &Hash {
         self.header.hash_prev_block()
     }
@@ -157,33 +223,6 @@ impl PartialEq for Block {
     }
 }
 
-impl<B> From<B> for Block
-where
-    B: AsRef<[u8]>,
-{
-    fn from(bytes: B) -> Self {
-        let bytes = bytes.as_ref();
-        let mut i = 1;
-        let height = usize::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
-        i += 8;
-        let transactions_len = usize::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
-        i += 8;
-        let header = BlockHeader::deserialize(&bytes[i..i + HEADER_BYTES]);
-        i += HEADER_BYTES;
-        let mut transactions = Vec::with_capacity(transactions_len);
-        for _j in 0..transactions_len {
-            let (transaction, size) = Transaction::deserialize(&bytes[i..]);
-            transactions.push(transaction);
-            i += size;
-        }
-        Self {
-            height,
-            header,
-            transactions,
-        }
-    }
-}
-
 impl fmt::Display for Block {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
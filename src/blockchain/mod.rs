@@ -14,6 +14,7 @@ use crate::Hash as TransactionId;
 pub struct Blockchain {
     chain: HashMap<BlockHash, Block>,
     top_hash: BlockHash,
+    finalized: BlockHash,
     initial_utxos: HashMap<UtxoId, UtxoData>,
 }
 
@@ -26,24 +27,96 @@ impl Blockchain {
         Self {
             chain,
             top_hash,
+            finalized: top_hash,
             initial_utxos,
         }
     }
 
+    /// Adds a block, making it the top if it is higher than the current one and does not conflict
+    /// with the finalized block
     pub fn push(&mut self, block: Block) -> Result<(), BlockchainError> {
+        let is_top = block.height() > self.height() && self.descends_from(&block, self.finalized());
+        self.insert(block, is_top)
+    }
+
+    /// Adds a block, making it the top if `is_top` (as decided by a fork choice rule)
+    pub fn insert(&mut self, block: Block, is_top: bool) -> Result<(), BlockchainError> {
         if self.contains(block.id()) {
             return Err(BlockchainError::KnownBlock);
         }
         if self.get_parent_of(&block).is_none() {
             return Err(BlockchainError::OrphanBlock);
         }
-        if block.height() > self.height() {
+        if is_top {
+            if !self.descends_from(&block, self.finalized()) {
+                return Err(BlockchainError::FinalizedBlock);
+            }
             self.top_hash = block.hash();
         }
         self.chain.insert(block.hash(), block);
         Ok(())
     }
 
+    /// Makes a known block the top, e.g. after the fork choice rule changed
+    pub fn set_top(&mut self, block_id: BlockHash) -> Result<(), BlockchainError> {
+        let block = self
+            .chain
+            .get(&block_id)
+            .ok_or(BlockchainError::UnknownBlock)?;
+        if !self.descends_from(block, self.finalized()) {
+            return Err(BlockchainError::FinalizedBlock);
+        }
+        self.top_hash = block_id;
+        Ok(())
+    }
+
+    /// Makes a known block final: neither it nor its ancestors can be reverted anymore
+    pub fn finalize(&mut self, block_id: BlockHash) -> Result<(), BlockchainError> {
+        let block = self
+            .chain
+            .get(&block_id)
+            .ok_or(BlockchainError::UnknownBlock)?;
+        if self.descends_from(self.finalized(), block) {
+            return Ok(());
+        }
+        if !self.descends_from(block, self.finalized()) {
+            return Err(BlockchainError::ConflictingFinality);
+        }
+        self.finalized = block_id;
+        Ok(())
+    }
+
+    /// Tells if `ancestor` is `block` or one of its ancestors
+    pub fn descends_from<'a>(&'a self, mut block: &'a Block, ancestor: &Block) -> bool {
+        while block.height() > ancestor.height() {
+            match self.get_parent_of(block) {
+                Some(parent) => block = parent,
+                None => return false,
+            }
+        }
+        block.hash() == ancestor.hash()
+    }
+
+    /// Ancestor of a block at a given height
+    pub fn ancestor_at<'a>(&'a self, mut block: &'a Block, height: usize) -> Option<&'a Block> {
+        while block.height() > height {
+            block = self.get_parent_of(block)?;
+        }
+        Some(block).filter(|block| block.height() == height)
+    }
+
+    /// Checks that reverting blocks would not revert the finalized block
+    pub fn check_reversible(&self, blocks: &[Block]) -> Result<(), BlockchainError> {
+        if blocks
+            .iter()
+            .any(|block| self.descends_from(self.finalized(), block))
+        {
+            Err(BlockchainError::FinalizedBlock)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Finds the two shortest lists of consecutive blocks joining two blocks
     ///
     /// Computes the closest common parent A of the two given blocks B and C, then returns:
@@ -167,6 +240,17 @@ impl Blockchain {
         &self.chain[&self.top_hash]
     }
 
+    /// Returns the blocks of the longest chain in decreasing height, genesis block excluded
+    pub fn main_chain(&self) -> Vec<&Block> {
+        let mut blocks = Vec::with_capacity(self.height());
+        let mut block = self.top();
+        while let Some(parent) = self.get_parent_of(block) {
+            blocks.push(block);
+            block = parent;
+        }
+        blocks
+    }
+
     pub fn genesis(&self) -> &Block {
         self.chain
             .iter()
@@ -191,6 +275,11 @@ impl Blockchain {
         &self.top_hash
     }
 
+    /// Highest finalized block (the genesis block until a checkpoint is finalized)
+    pub fn finalized(&self) -> &Block {
+        &self.chain[&self.finalized]
+    }
+
     pub fn initial_utxos(&self) -> &HashMap<UtxoId, UtxoData> {
         &self.initial_utxos
     }
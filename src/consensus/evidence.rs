@@ -0,0 +1,83 @@
+use std::convert::TryInto;
+use std::iter;
+
+use crate::block::blockheader::BlockHeader;
+use crate::block::Block;
+use crate::constants::HEADER_BYTES;
+
+/// Headers and seals of two different blocks sealed for the same slot, proving that their
+/// sealer equivocated
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Evidence {
+    height: usize,
+    headers: [BlockHeader; 2],
+    seals: [Vec<u8>; 2],
+}
+
+impl Evidence {
+    pub fn new(block1: &Block, block2: &Block) -> Self {
+        assert_eq!(block1.height(), block2.height());
+        Self {
+            height: block1.height(),
+            headers: [block1.header().clone(), block2.header().clone()],
+            seals: [block1.seal().clone(), block2.seal().clone()],
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = iter::once(b'e')
+            .chain(self.height.to_be_bytes().iter().copied())
+            .collect();
+        for (header, seal) in self.headers.iter().zip(self.seals.iter()) {
+            bytes.extend(header.serialize());
+            bytes.extend(seal.len().to_be_bytes().iter());
+            bytes.extend(seal);
+        }
+        bytes
+    }
+
+    /// Deserializes bytes that may be malformed, e.g. sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        let len = |i: usize| {
+            let bytes = bytes.get(i..i.checked_add(8)?)?;
+            Some(usize::from_be_bytes(bytes.try_into().unwrap()))
+        };
+        let mut i = 1;
+        let height = len(i)?;
+        i += 8;
+        let mut headers = Vec::with_capacity(2);
+        let mut seals = Vec::with_capacity(2);
+        for _j in 0..2 {
+            headers.push(BlockHeader::deserialize(bytes.get(i..i + HEADER_BYTES)?)?);
+            i += HEADER_BYTES;
+            let seal_len = len(i)?;
+            i += 8;
+            seals.push(bytes.get(i..i.checked_add(seal_len)?)?.to_vec());
+            i += seal_len;
+        }
+        if i != bytes.len() {
+            return None;
+        }
+        Some(Self {
+            height,
+            headers: headers.try_into().unwrap(),
+            seals: seals.try_into().unwrap(),
+        })
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn headers(&self) -> &[BlockHeader; 2] {
+        &self.headers
+    }
+
+    pub fn seals(&self) -> &[Vec<u8>; 2] {
+        &self.seals
+    }
+}
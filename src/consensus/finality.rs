@@ -0,0 +1,129 @@
+use secp256k1::{All, PublicKey, Secp256k1};
+use std::collections::{HashMap, HashSet};
+
+use super::vote::Vote;
+use crate::error::finality::FinalityError;
+use crate::Hash as BlockHash;
+
+/// Checkpoint voting making blocks final, in the style of Tendermint precommits
+///
+/// Every `interval` blocks is a checkpoint, and each validator votes once for the block it has at
+/// the checkpoint height. A block gets finalized when more than two thirds of the validators
+/// vote for it, so two conflicting blocks cannot both be finalized unless a third of the
+/// validators vote twice. Validators caught voting twice for the same height no longer count.
+///
+/// A validator only votes for checkpoints extending its last vote, unless a later checkpoint was
+/// finalized since or its last vote can no longer gather a quorum, which keeps finality safe
+/// across heights without stalling after a split vote.
+pub struct FinalityGadget {
+    interval: usize,
+    validators: HashSet<PublicKey>,
+    votes: HashMap<usize, HashMap<PublicKey, Vote>>,
+    equivocators: HashSet<PublicKey>,
+    secp: Secp256k1<All>,
+}
+
+impl FinalityGadget {
+    pub fn new(interval: usize, validators: Vec<PublicKey>) -> Self {
+        assert!(interval > 0);
+        Self {
+            interval,
+            validators: validators.into_iter().collect(),
+            votes: HashMap::new(),
+            equivocators: HashSet::new(),
+            secp: Secp256k1::new(),
+        }
+    }
+
+    pub fn is_checkpoint(&self, height: usize) -> bool {
+        height > 0 && height % self.interval == 0
+    }
+
+    /// Number of votes finalizing a block
+    pub fn quorum(&self) -> usize {
+        self.validators.len() * 2 / 3 + 1
+    }
+
+    /// Counts a vote, telling if it is new and should be relayed
+    ///
+    /// The first vote of a validator for another block at the same height is relayed as well
+    /// so that every node learns that the validator equivocated.
+    pub fn add(&mut self, vote: &Vote) -> Result<bool, FinalityError> {
+        if !self.validators.contains(vote.public_key()) {
+            return Err(FinalityError::UnknownValidator);
+        }
+        if !self.is_checkpoint(vote.height()) {
+            return Err(FinalityError::NotACheckpoint);
+        }
+        let message = Vote::message(vote.height(), vote.hash());
+        self.secp
+            .verify(&message, vote.signature(), vote.public_key())
+            .map_err(|_| FinalityError::InvalidSignature)?;
+        let votes = self.votes.entry(vote.height()).or_default();
+        match votes.get(vote.public_key()) {
+            None => {
+                votes.insert(*vote.public_key(), vote.clone());
+                Ok(true)
+            }
+            Some(first) if first.hash() == vote.hash() => Ok(false),
+            Some(_) => Ok(self.equivocators.insert(*vote.public_key())),
+        }
+    }
+
+    /// Block voted for by a quorum of honest validators at a checkpoint height, if any
+    pub fn checkpoint(&self, height: usize) -> Option<BlockHash> {
+        let mut counts = HashMap::new();
+        for vote in self.votes.get(&height)?.values() {
+            if !self.equivocators.contains(vote.public_key()) {
+                *counts.entry(*vote.hash()).or_insert(0) += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .find(|&(_, count)| count >= self.quorum())
+            .map(|(hash, _)| hash)
+    }
+
+    /// Tells if a block may still gather a quorum at a checkpoint height, i.e. if not too many
+    /// validators voted for other blocks or equivocated
+    pub fn can_finalize(&self, height: usize, hash: &BlockHash) -> bool {
+        let against = self.votes.get(&height).map_or(0, |votes| {
+            votes
+                .values()
+                .filter(|vote| vote.hash() != hash || self.equivocators.contains(vote.public_key()))
+                .count()
+        });
+        self.validators.len() - against >= self.quorum()
+    }
+
+    /// Checkpoints voted for by a quorum, highest first
+    pub fn checkpoints(&self) -> Vec<(usize, BlockHash)> {
+        let mut heights: Vec<_> = self.votes.keys().copied().collect();
+        heights.sort_unstable_by(|h1, h2| h2.cmp(h1));
+        heights
+            .into_iter()
+            .filter_map(|height| self.checkpoint(height).map(|hash| (height, hash)))
+            .collect()
+    }
+
+    /// Highest vote of a validator
+    pub fn last_vote_of(&self, public_key: &PublicKey) -> Option<&Vote> {
+        self.votes
+            .iter()
+            .filter_map(|(height, votes)| votes.get(public_key).map(|vote| (height, vote)))
+            .max_by_key(|&(height, _)| height)
+            .map(|(_, vote)| vote)
+    }
+
+    pub fn is_equivocator(&self, public_key: &PublicKey) -> bool {
+        self.equivocators.contains(public_key)
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    pub fn validators(&self) -> &HashSet<PublicKey> {
+        &self.validators
+    }
+}
@@ -0,0 +1,73 @@
+use secp256k1::PublicKey;
+
+use self::evidence::Evidence;
+use crate::block::Block;
+use crate::error::block::BlockError;
+use crate::utxo_pool::UtxoPool;
+
+/// Rules deciding who may extend the blockchain and which branch nodes follow
+///
+/// The miner hands the engine a block to seal whenever its template changes and polls it until
+/// the block comes out sealed.
+pub trait ConsensusEngine: Send {
+    /// Starts sealing a block extending `parent`, of utxo pool `utxo_pool`, dropping the one
+    /// being sealed if any
+    fn start(&mut self, parent: &Block, block: &Block, utxo_pool: &UtxoPool);
+
+    /// Returns the block being sealed once it is
+    fn seal(&mut self) -> Option<Block>;
+
+    /// Stops sealing the current block
+    fn discard(&mut self);
+
+    /// Checks that a block extending `parent`, of utxo pool `utxo_pool`, was sealed according to
+    /// the engine rules
+    fn verify_seal(
+        &self,
+        parent: &Block,
+        block: &Block,
+        utxo_pool: &UtxoPool,
+    ) -> Result<(), BlockError>;
+
+    /// Tells if a new block should replace the top of the blockchain
+    fn fork_choice(&self, top: &Block, block: &Block) -> bool {
+        block.height() > top.height()
+    }
+
+    /// Records the seal of a block and returns evidence if its sealer already sealed another
+    /// block for the same slot
+    fn check_equivocation(&mut self, _block: &Block) -> Option<Evidence> {
+        None
+    }
+
+    /// Checks evidence of equivocation and slashes the sealer, telling if it was not already
+    fn punish(&mut self, _evidence: &Evidence) -> Result<bool, BlockError> {
+        Ok(false)
+    }
+
+    /// Tells if a sealer got slashed for equivocating
+    fn is_slashed(&self, _public_key: &PublicKey) -> bool {
+        false
+    }
+
+    /// Number of hashes computed so far to seal blocks
+    fn hashes(&self) -> u64 {
+        0
+    }
+
+    /// Limits the hashes computed per second to seal blocks (`None` for no limit)
+    fn throttle(&mut self, _hashrate: Option<f64>) {}
+
+    /// Limit of the hashes computed per second to seal blocks
+    fn hashrate_limit(&self) -> Option<f64> {
+        None
+    }
+}
+
+pub mod evidence;
+pub mod finality;
+pub mod proof_of_authority;
+pub mod proof_of_stake;
+pub mod proof_of_work;
+pub mod vote;
+pub mod vrf;
@@ -0,0 +1,73 @@
+use secp256k1::{All, Message as MessageToSign, PublicKey, Secp256k1, SecretKey, Signature};
+
+use super::ConsensusEngine;
+use crate::block::Block;
+use crate::error::block::BlockError;
+use crate::utxo_pool::UtxoPool;
+
+/// Blocks are signed in turn by a fixed list of authorities
+///
+/// The block at height `h` must be signed by the authority `h % n` of the `n` authorities, so a
+/// network of honest authorities never forks.
+pub struct ProofOfAuthority {
+    authorities: Vec<PublicKey>,
+    public_key: PublicKey,
+    secret_key: SecretKey,
+    secp: Secp256k1<All>,
+    block: Option<Block>,
+}
+
+impl ProofOfAuthority {
+    pub fn new(authorities: Vec<PublicKey>, public_key: PublicKey, secret_key: SecretKey) -> Self {
+        assert!(!authorities.is_empty());
+        Self {
+            authorities,
+            public_key,
+            secret_key,
+            secp: Secp256k1::new(),
+            block: None,
+        }
+    }
+
+    /// Authority expected to sign the block at the given height
+    pub fn authority_at(&self, height: usize) -> &PublicKey {
+        &self.authorities[height % self.authorities.len()]
+    }
+
+    pub fn authorities(&self) -> &Vec<PublicKey> {
+        &self.authorities
+    }
+}
+
+impl ConsensusEngine for ProofOfAuthority {
+    fn start(&mut self, _parent: &Block, block: &Block, _utxo_pool: &UtxoPool) {
+        self.block =
+            Some(block.clone()).filter(|b| self.authority_at(b.height()) == &self.public_key);
+    }
+
+    fn seal(&mut self) -> Option<Block> {
+        let mut block = self.block.take()?;
+        let message = MessageToSign::from_slice(&block.hash()).unwrap();
+        let signature = self.secp.sign(&message, &self.secret_key);
+        block.set_seal(signature.serialize_compact().to_vec());
+        Some(block)
+    }
+
+    fn discard(&mut self) {
+        self.block = None;
+    }
+
+    fn verify_seal(
+        &self,
+        _parent: &Block,
+        block: &Block,
+        _utxo_pool: &UtxoPool,
+    ) -> Result<(), BlockError> {
+        let message = MessageToSign::from_slice(&block.hash()).unwrap();
+        let signature =
+            Signature::from_compact(block.seal()).map_err(|_| BlockError::InvalidSeal)?;
+        self.secp
+            .verify(&message, &signature, self.authority_at(block.height()))
+            .map_err(|_| BlockError::InvalidSeal)
+    }
+}
@@ -0,0 +1,219 @@
+use secp256k1::{All, Message as MessageToSign, PublicKey, Secp256k1, SecretKey, Signature};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+
+use super::evidence::Evidence;
+use super::vrf;
+use super::ConsensusEngine;
+use crate::block::Block;
+use crate::constants::{STAKE_SEAL_BYTES, VRF_PROOF_BYTES};
+use crate::error::block::BlockError;
+use crate::utxo_pool::UtxoPool;
+use crate::Hash;
+
+/// Blocks are sealed by validators holding coins, the leader of each slot being drawn with a
+/// probability proportional to its stake
+///
+/// The slot of a block is its height. The draw of a validator for a slot is the output of its
+/// verifiable random function on the draw sealing the parent block, so that anyone can check it
+/// with the public key of the validator and no validator can grind it: it only changes with the
+/// parent. Unlike a hash of the public key, it stays unknown to the other validators until
+/// revealed. The signature of the seal authenticates the block. Every validator seals a block for
+/// every slot and, between blocks of the same height, the fork choice rule favours the lowest draw
+/// scaled by the stake of the sealer, an exponential race won with probability proportional to the
+/// stake.
+///
+/// The nonce of a block, of no use without proof of work, is derived from the key of its sealer so
+/// that validators sealing the same transactions seal blocks of different hashes, each parent
+/// having a single draw.
+///
+/// The stake of a validator is the coins it holds in the utxo pool at the parent block, which the
+/// seal carries so that blocks of different parents can be compared. A validator caught sealing
+/// two blocks for the same slot is slashed: its stake no longer counts and its blocks lose every
+/// race.
+pub struct ProofOfStake {
+    public_key: PublicKey,
+    secret_key: SecretKey,
+    secp: Secp256k1<All>,
+    slashed: HashSet<PublicKey>,
+    sealed: HashMap<(PublicKey, usize), Block>,
+    block: Option<(u64, [u8; VRF_PROOF_BYTES], Block)>,
+}
+
+impl ProofOfStake {
+    pub fn new(public_key: PublicKey, secret_key: SecretKey) -> Self {
+        Self {
+            public_key,
+            secret_key,
+            secp: Secp256k1::new(),
+            slashed: HashSet::new(),
+            sealed: HashMap::new(),
+            block: None,
+        }
+    }
+
+    /// Stake of a validator in a utxo pool, which is 0 once it is slashed
+    pub fn stake_of(&self, public_key: &PublicKey, utxo_pool: &UtxoPool) -> u64 {
+        if self.slashed.contains(public_key) {
+            return 0;
+        }
+        utxo_pool.balance_of(public_key)
+    }
+
+    /// Draw of the sealer of a block scaled by its stake, the lowest winning the slot
+    pub fn score(&self, block: &Block) -> f64 {
+        let (public_key, stake, proof, _) = match Self::open(block.seal()) {
+            Some(seal) => seal,
+            None => return f64::INFINITY,
+        };
+        if stake == 0 || self.slashed.contains(&public_key) {
+            return f64::INFINITY;
+        }
+        let draw = vrf::output(&proof);
+        let draw = u64::from_be_bytes(draw[..8].try_into().unwrap());
+        let uniform = (draw as f64 + 1.0) / 2f64.powi(64);
+        -uniform.ln() / stake as f64
+    }
+
+    /// Nonce of the blocks sealed by a validator
+    pub fn nonce_of(public_key: &PublicKey) -> u32 {
+        let mut hasher = Sha256::new();
+        hasher.input(b"nonce");
+        hasher.input(&public_key.serialize()[..]);
+        u32::from_be_bytes(hasher.result()[..4].try_into().unwrap())
+    }
+
+    /// Draw sealing a block, or its hash if it is not sealed by proof of stake, e.g. the genesis
+    pub fn draw_of(block: &Block) -> [u8; 32] {
+        match Self::open(block.seal()) {
+            Some((_, _, proof, _)) => vrf::output(&proof),
+            None => block.hash().into(),
+        }
+    }
+
+    fn block_message(hash: &Hash, height: usize) -> MessageToSign {
+        let mut hasher = Sha256::new();
+        hasher.input(hash);
+        hasher.input(height.to_be_bytes());
+        MessageToSign::from_slice(&hasher.result()).unwrap()
+    }
+
+    /// Splits a seal into the public key of the validator, its stake, the proof of its draw and
+    /// its signature
+    fn open(seal: &[u8]) -> Option<(PublicKey, u64, [u8; VRF_PROOF_BYTES], Signature)> {
+        if seal.len() != STAKE_SEAL_BYTES {
+            return None;
+        }
+        let (public_key, seal) = seal.split_at(33);
+        let (stake, seal) = seal.split_at(8);
+        let (proof, signature) = seal.split_at(VRF_PROOF_BYTES);
+        let public_key = PublicKey::from_slice(public_key).ok()?;
+        let stake = u64::from_be_bytes(stake.try_into().unwrap());
+        let signature = Signature::from_compact(signature).ok()?;
+        Some((public_key, stake, proof.try_into().unwrap(), signature))
+    }
+}
+
+impl ConsensusEngine for ProofOfStake {
+    fn start(&mut self, parent: &Block, block: &Block, utxo_pool: &UtxoPool) {
+        let stake = self.stake_of(&self.public_key, utxo_pool);
+        if stake == 0 {
+            self.block = None;
+            return;
+        }
+        let mut block = block.clone();
+        block.set_nonce(Self::nonce_of(&self.public_key));
+        let proof = vrf::prove(&Self::draw_of(parent), &self.secret_key);
+        self.block = Some((stake, proof, block));
+    }
+
+    fn seal(&mut self) -> Option<Block> {
+        let (stake, proof, mut block) = self.block.take()?;
+        let signature = self.secp.sign(
+            &Self::block_message(&block.hash(), block.height()),
+            &self.secret_key,
+        );
+        let seal = self
+            .public_key
+            .serialize()
+            .iter()
+            .copied()
+            .chain(stake.to_be_bytes().iter().copied())
+            .chain(proof.iter().copied())
+            .chain(signature.serialize_compact().iter().copied())
+            .collect();
+        block.set_seal(seal);
+        Some(block)
+    }
+
+    fn discard(&mut self) {
+        self.block = None;
+    }
+
+    fn verify_seal(
+        &self,
+        parent: &Block,
+        block: &Block,
+        utxo_pool: &UtxoPool,
+    ) -> Result<(), BlockError> {
+        let (public_key, stake, proof, signature) =
+            Self::open(block.seal()).ok_or(BlockError::InvalidSeal)?;
+        if stake == 0
+            || stake != utxo_pool.balance_of(&public_key)
+            || block.nonce() != Self::nonce_of(&public_key)
+            || vrf::verify(&Self::draw_of(parent), &proof, &public_key).is_none()
+        {
+            return Err(BlockError::InvalidSeal);
+        }
+        let message = Self::block_message(&block.hash(), block.height());
+        self.secp
+            .verify(&message, &signature, &public_key)
+            .map_err(|_| BlockError::InvalidSeal)
+    }
+
+    fn fork_choice(&self, top: &Block, block: &Block) -> bool {
+        if block.height() != top.height() {
+            return block.height() > top.height();
+        }
+        let (score, top_score) = (self.score(block), self.score(top));
+        score < top_score || (score == top_score && block.hash() < top.hash())
+    }
+
+    fn is_slashed(&self, public_key: &PublicKey) -> bool {
+        self.slashed.contains(public_key)
+    }
+
+    fn check_equivocation(&mut self, block: &Block) -> Option<Evidence> {
+        let (public_key, _, _, _) = Self::open(block.seal())?;
+        let sealed = self
+            .sealed
+            .entry((public_key, block.height()))
+            .or_insert_with(|| block.clone());
+        Some(Evidence::new(sealed, block)).filter(|_| sealed.hash() != block.hash())
+    }
+
+    fn punish(&mut self, evidence: &Evidence) -> Result<bool, BlockError> {
+        let [header1, header2] = evidence.headers();
+        if header1 == header2 {
+            return Err(BlockError::InvalidEvidence);
+        }
+        let mut sealers =
+            evidence
+                .headers()
+                .iter()
+                .zip(evidence.seals().iter())
+                .map(|(header, seal)| {
+                    let (public_key, _, _, signature) = Self::open(seal)?;
+                    let message = Self::block_message(&header.hash(), evidence.height());
+                    self.secp.verify(&message, &signature, &public_key).ok()?;
+                    Some(public_key)
+                });
+        match (sealers.next().flatten(), sealers.next().flatten()) {
+            (Some(sealer1), Some(sealer2)) if sealer1 == sealer2 => {
+                Ok(self.slashed.insert(sealer1))
+            }
+            _ => Err(BlockError::InvalidEvidence),
+        }
+    }
+}
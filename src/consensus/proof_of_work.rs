@@ -0,0 +1,53 @@
+use super::ConsensusEngine;
+use crate::block::Block;
+use crate::error::block::BlockError;
+use crate::miner::workers::Workers;
+use crate::utxo_pool::UtxoPool;
+
+/// Blocks are sealed by finding a header whose hash is below the target
+pub struct ProofOfWork {
+    workers: Workers,
+}
+
+impl ProofOfWork {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            workers: Workers::new(threads),
+        }
+    }
+}
+
+impl ConsensusEngine for ProofOfWork {
+    fn start(&mut self, _parent: &Block, block: &Block, _utxo_pool: &UtxoPool) {
+        self.workers.submit(block.clone());
+    }
+
+    fn seal(&mut self) -> Option<Block> {
+        self.workers.found()
+    }
+
+    fn discard(&mut self) {
+        self.workers.cancel();
+    }
+
+    fn verify_seal(
+        &self,
+        _parent: &Block,
+        block: &Block,
+        _utxo_pool: &UtxoPool,
+    ) -> Result<(), BlockError> {
+        block.check_proof_of_work()
+    }
+
+    fn hashes(&self) -> u64 {
+        self.workers.hashes()
+    }
+
+    fn throttle(&mut self, hashrate: Option<f64>) {
+        self.workers.throttle(hashrate);
+    }
+
+    fn hashrate_limit(&self) -> Option<f64> {
+        self.workers.limit()
+    }
+}
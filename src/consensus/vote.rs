@@ -0,0 +1,90 @@
+use secp256k1::{Message as MessageToSign, PublicKey, Secp256k1, SecretKey, Signature};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::iter;
+
+use crate::constants::VOTE_BYTES;
+use crate::Hash as BlockHash;
+
+/// Vote of a validator for the block it saw at a checkpoint height
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Vote {
+    height: usize,
+    hash: BlockHash,
+    public_key: PublicKey,
+    signature: Signature,
+}
+
+impl Vote {
+    pub fn sign(height: usize, hash: BlockHash, secret_key: &SecretKey) -> Self {
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::from_secret_key(&secp, secret_key);
+        let signature = secp.sign(&Self::message(height, &hash), secret_key);
+        Self {
+            height,
+            hash,
+            public_key,
+            signature,
+        }
+    }
+
+    /// Message signed by the validators voting for a block at a checkpoint height
+    pub fn message(height: usize, hash: &BlockHash) -> MessageToSign {
+        let mut hasher = Sha256::new();
+        hasher.input(b"vote");
+        hasher.input(height.to_be_bytes());
+        hasher.input(hash);
+        MessageToSign::from_slice(&hasher.result()).unwrap()
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        iter::once(b'c')
+            .chain((self.height as u64).to_be_bytes().iter().copied())
+            .chain(self.hash.iter().copied())
+            .chain(self.public_key.serialize().iter().copied())
+            .chain(self.signature.serialize_compact().iter().copied())
+            .collect()
+    }
+
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        if bytes.len() != VOTE_BYTES || bytes[0] != b'c' {
+            return None;
+        }
+        let mut i = 1;
+        let height = u64::from_be_bytes(bytes[i..i + 8].try_into().unwrap())
+            .try_into()
+            .ok()?;
+        i += 8;
+        let hash = *BlockHash::from_slice(&bytes[i..i + 32]);
+        i += 32;
+        let public_key = PublicKey::from_slice(&bytes[i..i + 33]).ok()?;
+        i += 33;
+        let signature = Signature::from_compact(&bytes[i..]).ok()?;
+        Some(Self {
+            height,
+            hash,
+            public_key,
+            signature,
+        })
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn hash(&self) -> &BlockHash {
+        &self.hash
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+}
@@ -0,0 +1,124 @@
+use k256::elliptic_curve::bigint::U256;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::DecompactPoint;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, FieldBytes, ProjectivePoint, Scalar};
+use secp256k1::{PublicKey, SecretKey};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+
+use crate::constants::VRF_PROOF_BYTES;
+
+/// Proves the output of the verifiable random function of a key on a message
+///
+/// The output is the hash of `Gamma = x.H`, where `x` is the secret key and `H` a point hashed
+/// from the public key and the message. The proof holds `Gamma` along with a proof `(c, s)` that
+/// `Gamma` and the public key share the same discrete logarithm, to the bases `H` and `G`. Unlike
+/// a signature, there is a single valid output per key and message, so that it cannot be ground.
+pub fn prove(message: &[u8], secret_key: &SecretKey) -> [u8; VRF_PROOF_BYTES] {
+    let x = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(&secret_key[..])))
+        .unwrap();
+    let public_key = ProjectivePoint::GENERATOR * x;
+    let h = hash_to_curve(&encode(&public_key), message);
+    let gamma = h * x;
+    let mut hasher = Sha256::new();
+    hasher.input(b"vrf-nonce");
+    hasher.input(&secret_key[..]);
+    hasher.input(encode(&h));
+    let k = reduce(&hasher.result());
+    let c = challenge(
+        &public_key,
+        &h,
+        &gamma,
+        &(ProjectivePoint::GENERATOR * k),
+        &(h * k),
+    );
+    let s = k + c * x;
+    let mut proof = [0u8; VRF_PROOF_BYTES];
+    proof[..33].copy_from_slice(&encode(&gamma));
+    proof[33..65].copy_from_slice(&c.to_repr());
+    proof[65..].copy_from_slice(&s.to_repr());
+    proof
+}
+
+/// Checks the proof of the output of a key on a message and returns the output
+pub fn verify(
+    message: &[u8],
+    proof: &[u8; VRF_PROOF_BYTES],
+    public_key: &PublicKey,
+) -> Option<[u8; 32]> {
+    let public_key = decode(&public_key.serialize())?;
+    let gamma = decode(&proof[..33])?;
+    let c = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(&proof[33..65])))?;
+    let s = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(&proof[65..])))?;
+    let h = hash_to_curve(&encode(&public_key), message);
+    let u = ProjectivePoint::GENERATOR * s - public_key * c;
+    let v = h * s - gamma * c;
+    if challenge(&public_key, &h, &gamma, &u, &v) != c {
+        return None;
+    }
+    Some(output(proof))
+}
+
+/// Output of a proof, which is only trustworthy once the proof is verified
+pub fn output(proof: &[u8; VRF_PROOF_BYTES]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(b"vrf-output");
+    hasher.input(&proof[..33]);
+    hasher.result().into()
+}
+
+/// Point of even y-coordinate whose x-coordinate is the first hash of the public key, the
+/// message and a counter to be one
+fn hash_to_curve(public_key: &[u8], message: &[u8]) -> ProjectivePoint {
+    (0u32..)
+        .find_map(|counter| {
+            let mut hasher = Sha256::new();
+            hasher.input(b"vrf-hash-to-curve");
+            hasher.input(public_key);
+            hasher.input(message);
+            hasher.input(counter.to_be_bytes());
+            Option::<AffinePoint>::from(AffinePoint::decompact(FieldBytes::from_slice(
+                &hasher.result(),
+            )))
+        })
+        .map(ProjectivePoint::from)
+        .unwrap()
+}
+
+fn challenge(
+    public_key: &ProjectivePoint,
+    h: &ProjectivePoint,
+    gamma: &ProjectivePoint,
+    u: &ProjectivePoint,
+    v: &ProjectivePoint,
+) -> Scalar {
+    let mut hasher = Sha256::new();
+    hasher.input(b"vrf-challenge");
+    for point in &[public_key, h, gamma, u, v] {
+        hasher.input(encode(point));
+    }
+    reduce(&hasher.result())
+}
+
+fn reduce(hash: &[u8]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(FieldBytes::from_slice(hash))
+}
+
+/// Compressed encoding of a point
+fn encode(point: &ProjectivePoint) -> [u8; 33] {
+    point
+        .to_affine()
+        .to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .unwrap()
+}
+
+/// Point of a compressed encoding, other than the point at infinity
+fn decode(bytes: &[u8]) -> Option<ProjectivePoint> {
+    k256::PublicKey::from_sec1_bytes(bytes)
+        .ok()
+        .map(|public_key| public_key.to_projective())
+}
@@ -1,14 +1,48 @@
+use std::time::Duration;
+
+pub const ADDRESS_PREFIX: &str = "sim";
+pub const BAN_DURATION: Duration = Duration::from_secs(10);
+pub const BAN_SCORE: u32 = 100;
+pub const BNB_TRIES: usize = 100_000;
+pub const COUNTERFEIT_PROBA: f64 = 1.0 / 2.0;
 pub const DOUBLE_SPEND_PROBA: f64 = 1.0 / 1.0;
+pub const EXTENDED_PUBLIC_KEY_BYTES: usize = 33 + 32;
+pub const GAP_LIMIT: usize = 20;
 pub const GENESIS_BLOCK_HASH_PREV_BLOCK: [u8; 32] = [0u8; 32];
-pub const HEADER_BYTES: usize = 32 + 32 + 4 + 4;
+pub const HEADER_BYTES: usize = 32 + 32 + 4 + 4 + 4;
+pub const KDF_MAX_MEMORY_KIB: u32 = 1 << 20;
+pub const KDF_MAX_PASSES: u32 = 16;
+pub const KDF_MEMORY_KIB: u32 = 1 << 13;
+pub const KDF_PASSES: u32 = 2;
+pub const KDF_SALT_BYTES: usize = 16;
+pub const KEYCHAIN_BYTES: usize = 2 * EXTENDED_PUBLIC_KEY_BYTES + 4 * 4;
+pub const KEY_HASH_BYTES: usize = 20;
+pub const KNAPSACK_ITERATIONS: usize = 1000;
+pub const MATURITY: usize = 3;
+pub const MINER_THREADS: usize = 2;
+pub const MNEMONIC_ENTROPY_BYTES: usize = 16;
+pub const NETWORK_HASHRATE: f64 = 2048.0;
 pub const NODES: usize = 4;
+pub const ORPHAN_POOL_SIZE: usize = 64;
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const REBROADCAST_INTERVAL: Duration = Duration::from_secs(10);
+pub const SCHNORR_BATCH_SIZE: usize = 64;
+pub const SEED_ROUNDS: usize = 2048;
+pub const SERVICE_MINING: u64 = 1 << 1;
+pub const SERVICE_NETWORK: u64 = 1;
 pub const SIGNATURE_BYTES: usize = 64;
 pub const SPEND_PROBA: f64 = 1.0 / 1000.0;
+pub const STAKE_SEAL_BYTES: usize = 33 + 8 + VRF_PROOF_BYTES + SIGNATURE_BYTES;
 pub const TARGET: [u8; 4] = [0x20, 1, 0, 0];
-pub const TX_INPUT_BYTES: usize = UTXO_ID_BYTES + SIGNATURE_BYTES;
+pub const TX_INPUT_BYTES: usize = UTXO_ID_BYTES + 33 + SIGNATURE_BYTES;
 pub const TX_OUTPUT_BYTES: usize = UTXO_DATA_BYTES;
+pub const TX_POOL_BYTES: usize = 1 << 16;
+pub const TX_POOL_EXPIRY: Duration = Duration::from_secs(60);
 pub const TXS_PER_BLOCK: usize = 2;
 pub const UTXO_AMOUNT_INIT: u32 = 10;
-pub const UTXO_DATA_BYTES: usize = 4 + 33;
+pub const UTXO_DATA_BYTES: usize = 4 + KEY_HASH_BYTES + 1;
 pub const UTXO_HASH_INIT: [u8; 32] = [0u8; 32];
 pub const UTXO_ID_BYTES: usize = 32 + 8;
+pub const VERSION_BYTES: usize = 1 + 4 + 8 + 8 + 8 + 32 + 33;
+pub const VOTE_BYTES: usize = 1 + 8 + 32 + 33 + SIGNATURE_BYTES;
+pub const VRF_PROOF_BYTES: usize = 33 + 32 + 32;
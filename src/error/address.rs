@@ -0,0 +1,47 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AddressError {
+    MixedCase,
+    NoPrefix,
+    WrongNetwork(String),
+    InvalidCharacter,
+    WrongChecksum,
+    UnknownOutputType,
+    InvalidProgram,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MixedCase => write!(f, "Address: mixes lowercase and uppercase characters"),
+            Self::NoPrefix => write!(f, "Address: has no network prefix"),
+            Self::WrongNetwork(prefix) => {
+                write!(
+                    f,
+                    "Address: prefix {} is not the one of this network",
+                    prefix
+                )
+            }
+            Self::InvalidCharacter => write!(f, "Address: has a character out of the alphabet"),
+            Self::WrongChecksum => write!(f, "Address: checksum does not match, check for typos"),
+            Self::UnknownOutputType => write!(f, "Address: has an unknown output type"),
+            Self::InvalidProgram => write!(f, "Address: does not encode a key hash"),
+        }
+    }
+}
+
+impl error::Error for AddressError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::MixedCase => None,
+            Self::NoPrefix => None,
+            Self::WrongNetwork(_) => None,
+            Self::InvalidCharacter => None,
+            Self::WrongChecksum => None,
+            Self::UnknownOutputType => None,
+            Self::InvalidProgram => None,
+        }
+    }
+}
@@ -6,6 +6,10 @@ pub enum BlockError {
     WrongTransactionCount,
     InvalidExponentOfTarget(u8),
     DoubleSpending,
+    InsufficientProofOfWork,
+    UnknownTemplate,
+    InvalidSeal,
+    InvalidEvidence,
 }
 
 impl fmt::Display for BlockError {
@@ -20,6 +24,18 @@ impl fmt::Display for BlockError {
                 exponent
             ),
             Self::DoubleSpending => write!(f, "Block: double spending detected"),
+            Self::InsufficientProofOfWork => {
+                write!(f, "Block: hash of the header is not below the target")
+            }
+            Self::UnknownTemplate => write!(
+                f,
+                "Block: header does not solve any template handed out by the node"
+            ),
+            Self::InvalidSeal => write!(f, "Block: seal was not made by the expected authority"),
+            Self::InvalidEvidence => write!(
+                f,
+                "Block: evidence does not show two blocks sealed by the same validator"
+            ),
         }
     }
 }
@@ -30,6 +46,10 @@ impl error::Error for BlockError {
             Self::WrongTransactionCount => None,
             Self::InvalidExponentOfTarget(_) => None,
             Self::DoubleSpending => None,
+            Self::InsufficientProofOfWork => None,
+            Self::UnknownTemplate => None,
+            Self::InvalidSeal => None,
+            Self::InvalidEvidence => None,
         }
     }
 }
@@ -6,6 +6,9 @@ pub enum BlockchainError {
     KnownBlock,
     OrphanBlock,
     KnownTransactionId,
+    UnknownBlock,
+    FinalizedBlock,
+    ConflictingFinality,
 }
 
 impl fmt::Display for BlockchainError {
@@ -21,7 +24,19 @@ impl fmt::Display for BlockchainError {
             ),
             Self::KnownTransactionId => write!(
                 f,
-                "Blockchain: block contains a transaction whose id already belongs to the blockchain")
+                "Blockchain: block contains a transaction whose id already belongs to the blockchain"),
+            Self::UnknownBlock => write!(
+                f,
+                "Blockchain: cannot make a block the top of the blockchain that does not have it"
+            ),
+            Self::FinalizedBlock => write!(
+                f,
+                "Blockchain: cannot revert a finalized block"
+            ),
+            Self::ConflictingFinality => write!(
+                f,
+                "Blockchain: cannot finalize a block that conflicts with the finalized one"
+            ),
         }
     }
 }
@@ -32,6 +47,9 @@ impl error::Error for BlockchainError {
             Self::KnownBlock => None,
             Self::OrphanBlock => None,
             Self::KnownTransactionId => None,
+            Self::UnknownBlock => None,
+            Self::FinalizedBlock => None,
+            Self::ConflictingFinality => None,
         }
     }
 }
@@ -0,0 +1,38 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum FinalityError {
+    UnknownValidator,
+    NotACheckpoint,
+    InvalidSignature,
+}
+
+impl fmt::Display for FinalityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownValidator => write!(
+                f,
+                "Finality: cannot count vote from a key that is not one of the validators"
+            ),
+            Self::NotACheckpoint => write!(
+                f,
+                "Finality: cannot count vote for a height that is not a checkpoint"
+            ),
+            Self::InvalidSignature => write!(
+                f,
+                "Finality: cannot count vote whose signature does not match the validator key"
+            ),
+        }
+    }
+}
+
+impl error::Error for FinalityError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::UnknownValidator => None,
+            Self::NotACheckpoint => None,
+            Self::InvalidSignature => None,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum LinkError {
+    NotEstablished,
+    Unauthenticated,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotEstablished => write!(
+                f,
+                "Link: cannot open packet from a neighbour no key was agreed with"
+            ),
+            Self::Unauthenticated => write!(
+                f,
+                "Link: cannot open packet that was not sealed with the key agreed with the neighbour"
+            ),
+        }
+    }
+}
+
+impl error::Error for LinkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::NotEstablished => None,
+            Self::Unauthenticated => None,
+        }
+    }
+}
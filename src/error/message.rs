@@ -0,0 +1,50 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum MessageError {
+    Empty,
+    UnknownType(u8),
+    InvalidVersion,
+    InvalidVote,
+    InvalidTransaction,
+    InvalidBlock,
+    InvalidHeader,
+    InvalidEvidence,
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Message: cannot deserialize message from no bytes"),
+            Self::UnknownType(byte) => write!(
+                f,
+                "Message: cannot deserialize message of unknown type {:#04x}",
+                byte
+            ),
+            Self::InvalidVersion => write!(f, "Message: cannot deserialize malformed version"),
+            Self::InvalidVote => write!(f, "Message: cannot deserialize malformed vote"),
+            Self::InvalidTransaction => {
+                write!(f, "Message: cannot deserialize malformed transaction")
+            }
+            Self::InvalidBlock => write!(f, "Message: cannot deserialize malformed block"),
+            Self::InvalidHeader => write!(f, "Message: cannot deserialize malformed header"),
+            Self::InvalidEvidence => write!(f, "Message: cannot deserialize malformed evidence"),
+        }
+    }
+}
+
+impl error::Error for MessageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Empty => None,
+            Self::UnknownType(_) => None,
+            Self::InvalidVersion => None,
+            Self::InvalidVote => None,
+            Self::InvalidTransaction => None,
+            Self::InvalidBlock => None,
+            Self::InvalidHeader => None,
+            Self::InvalidEvidence => None,
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use std::error;
 use std::fmt;
 
+use self::address::AddressError;
 use self::block::BlockError;
 use self::blockchain::BlockchainError;
+use self::finality::FinalityError;
+use self::link::LinkError;
+use self::message::MessageError;
 use self::transaction::TransactionError;
 use self::transaction_pool::TransactionPoolError;
 use self::utxo_pool::UtxoPoolError;
@@ -10,19 +14,62 @@ use self::wallet::WalletError;
 
 #[derive(Debug)]
 pub enum Error {
+    Address(AddressError),
     Block(BlockError),
     Blockchain(BlockchainError),
+    Finality(FinalityError),
+    Link(LinkError),
+    Message(MessageError),
     Transaction(TransactionError),
     TransactionPool(TransactionPoolError),
     UtxoPool(UtxoPoolError),
     Wallet(WalletError),
 }
 
+impl Error {
+    /// Misbehaviour score of a neighbour that sent data failing with this error
+    ///
+    /// Errors an honest neighbour may trigger score 0, e.g. because it is on another branch or
+    /// raced a block that is now stale or whose parent has not arrived yet. So does a block
+    /// solving a template handed out before the top moved.
+    ///
+    /// A block double spending the utxos of its own branch is always invalid. A loose transaction
+    /// double spending the pool or the utxo set is not scored: honest nodes relay whichever side
+    /// of a double spend they saw first, and a transaction spending an unknown utxo may be an
+    /// orphan whose parent has not arrived yet. Only the double spender sees both sides.
+    pub fn misbehaviour_score(&self) -> u32 {
+        match self {
+            Self::Address(_) => 0,
+            Self::Block(BlockError::UnknownTemplate) => 0,
+            Self::Block(_) => 100,
+            Self::Blockchain(_) => 0,
+            Self::Finality(_) => 100,
+            Self::Link(_) => 100,
+            Self::Message(_) => 100,
+            Self::Transaction(TransactionError::NoInputs)
+            | Self::Transaction(TransactionError::NoOutputs) => 50,
+            Self::Transaction(TransactionError::DoubleSpending)
+            | Self::Transaction(TransactionError::WrongBalance) => 100,
+            Self::Transaction(_) => 0,
+            Self::TransactionPool(_) => 0,
+            Self::UtxoPool(UtxoPoolError::TransactionHasInvalidSignature(_))
+            | Self::UtxoPool(UtxoPoolError::BlockHasInvalidSignature(_, _))
+            | Self::UtxoPool(UtxoPoolError::BlockHasUnknownUtxo) => 100,
+            Self::UtxoPool(_) => 0,
+            Self::Wallet(_) => 0,
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::Address(err) => err.fmt(f),
             Self::Block(err) => err.fmt(f),
             Self::Blockchain(err) => err.fmt(f),
+            Self::Finality(err) => err.fmt(f),
+            Self::Link(err) => err.fmt(f),
+            Self::Message(err) => err.fmt(f),
             Self::Transaction(err) => err.fmt(f),
             Self::TransactionPool(err) => err.fmt(f),
             Self::UtxoPool(err) => err.fmt(f),
@@ -34,8 +81,12 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
+            Self::Address(err) => err.source(),
             Self::Block(err) => err.source(),
             Self::Blockchain(err) => err.source(),
+            Self::Finality(err) => err.source(),
+            Self::Link(err) => err.source(),
+            Self::Message(err) => err.source(),
             Self::Transaction(err) => err.source(),
             Self::TransactionPool(err) => err.source(),
             Self::UtxoPool(err) => err.source(),
@@ -44,6 +95,12 @@ impl error::Error for Error {
     }
 }
 
+impl From<AddressError> for Error {
+    fn from(err: AddressError) -> Self {
+        Self::Address(err)
+    }
+}
+
 impl From<BlockError> for Error {
     fn from(err: BlockError) -> Self {
         Self::Block(err)
@@ -56,6 +113,24 @@ impl From<BlockchainError> for Error {
     }
 }
 
+impl From<FinalityError> for Error {
+    fn from(err: FinalityError) -> Self {
+        Self::Finality(err)
+    }
+}
+
+impl From<LinkError> for Error {
+    fn from(err: LinkError) -> Self {
+        Self::Link(err)
+    }
+}
+
+impl From<MessageError> for Error {
+    fn from(err: MessageError) -> Self {
+        Self::Message(err)
+    }
+}
+
 impl From<TransactionError> for Error {
     fn from(err: TransactionError) -> Self {
         Self::Transaction(err)
@@ -80,8 +155,12 @@ impl From<WalletError> for Error {
     }
 }
 
+pub mod address;
 pub mod block;
 pub mod blockchain;
+pub mod finality;
+pub mod link;
+pub mod message;
 pub mod transaction;
 pub mod transaction_pool;
 pub mod utxo_pool;
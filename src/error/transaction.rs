@@ -7,6 +7,10 @@ pub enum TransactionError {
     NoOutputs,
     DoubleSpending,
     WrongBalance,
+    InvalidSignature,
+    MissingSignatures,
+    MismatchedPartialTransactions,
+    InvalidPartialTransaction,
 }
 
 impl fmt::Display for TransactionError {
@@ -16,6 +20,25 @@ impl fmt::Display for TransactionError {
             Self::NoOutputs => write!(f, "Transaction: no outputs"),
             Self::DoubleSpending => write!(f, "Transaction: double spending"),
             Self::WrongBalance => write!(f, "Transaction: input and output amounts differ"),
+            Self::InvalidSignature => {
+                write!(
+                    f,
+                    "Transaction: signature does not match the key of the input"
+                )
+            }
+            Self::MissingSignatures => {
+                write!(
+                    f,
+                    "Transaction: cannot finalize a transaction with unsigned inputs"
+                )
+            }
+            Self::MismatchedPartialTransactions => write!(
+                f,
+                "Transaction: cannot combine partial transactions with different inputs or outputs"
+            ),
+            Self::InvalidPartialTransaction => {
+                write!(f, "Transaction: partial transaction is malformed")
+            }
         }
     }
 }
@@ -27,6 +50,10 @@ impl error::Error for TransactionError {
             Self::NoOutputs => None,
             Self::DoubleSpending => None,
             Self::WrongBalance => None,
+            Self::InvalidSignature => None,
+            Self::MissingSignatures => None,
+            Self::MismatchedPartialTransactions => None,
+            Self::InvalidPartialTransaction => None,
         }
     }
 }
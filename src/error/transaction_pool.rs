@@ -8,6 +8,8 @@ pub enum TransactionPoolError {
     KnownTransaction,
     UnknownTransaction,
     UnknownUtxo(TransactionId),
+    Full,
+    InsufficientFee(TransactionId),
 }
 
 impl fmt::Display for TransactionPoolError {
@@ -26,6 +28,15 @@ impl fmt::Display for TransactionPoolError {
                 "Transaction pool: transaction {:x} has unknown utxo",
                 txid
             ),
+            Self::Full => write!(
+                f,
+                "Transaction pool: cannot add transaction paying a lower fee rate than those of the full pool"
+            ),
+            Self::InsufficientFee(txid) => write!(
+                f,
+                "Transaction pool: cannot replace transaction {:x} without paying a higher fee and fee rate",
+                txid
+            ),
         }
     }
 }
@@ -36,6 +47,8 @@ impl error::Error for TransactionPoolError {
             Self::KnownTransaction => None,
             Self::UnknownTransaction => None,
             Self::UnknownUtxo(_) => None,
+            Self::Full => None,
+            Self::InsufficientFee(_) => None,
         }
     }
 }
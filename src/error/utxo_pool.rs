@@ -6,7 +6,12 @@ pub enum UtxoPoolError {
     KnownUtxo,
     UnknownUtxo,
     TransactionHasUnknownUtxo,
+    /// Block transaction spending a utxo unknown to the branch of the block, e.g. already spent
+    BlockHasUnknownUtxo,
     TransactionHasInvalidSignature(secp256k1::Error),
+    /// Input of a block transaction with an invalid signature, the first one in block order, as
+    /// indexes of the transaction and of the input
+    BlockHasInvalidSignature(usize, usize),
 }
 
 impl fmt::Display for UtxoPoolError {
@@ -21,10 +26,19 @@ impl fmt::Display for UtxoPoolError {
                 "Utxo pool: cannot remove utxo from the pool that does not have it"
             ),
             Self::TransactionHasUnknownUtxo => write!(f, "Utxo pool: transaction has unknown utxo"),
+            Self::BlockHasUnknownUtxo => write!(
+                f,
+                "Utxo pool: block has transaction spending unknown or spent utxo"
+            ),
             Self::TransactionHasInvalidSignature(err) => {
                 write!(f, "Utxo pool: ")?;
                 err.fmt(f)
             }
+            Self::BlockHasInvalidSignature(transaction, input) => write!(
+                f,
+                "Utxo pool: input {} of transaction {} of block has invalid signature",
+                input, transaction
+            ),
         }
     }
 }
@@ -35,7 +49,9 @@ impl error::Error for UtxoPoolError {
             Self::KnownUtxo => None,
             Self::UnknownUtxo => None,
             Self::TransactionHasUnknownUtxo => None,
+            Self::BlockHasUnknownUtxo => None,
             Self::TransactionHasInvalidSignature(err) => err.source(),
+            Self::BlockHasInvalidSignature(_, _) => None,
         }
     }
 }
@@ -1,11 +1,21 @@
 use std::error;
 use std::fmt;
+use std::io;
 
 #[derive(Debug)]
 pub enum WalletError {
     WrongPublicKey,
     KnownUtxo,
     UnknownUtxo,
+    InvalidMnemonic,
+    Locked,
+    NotEncrypted,
+    WrongPassphrase,
+    InvalidWalletFile,
+    Io(io::Error),
+    InsufficientFunds,
+    WatchOnly,
+    ChangeTooLarge,
 }
 
 impl fmt::Display for WalletError {
@@ -23,6 +33,30 @@ impl fmt::Display for WalletError {
                 f,
                 "Wallet: cannot remove utxo from the wallet that does not have it"
             ),
+            Self::InvalidMnemonic => write!(
+                f,
+                "Wallet: mnemonic has unknown words, a wrong length or a wrong checksum"
+            ),
+            Self::Locked => write!(f, "Wallet: cannot use the secret keys of a locked wallet"),
+            Self::NotEncrypted => write!(
+                f,
+                "Wallet: cannot lock or save a wallet that is not encrypted under a passphrase"
+            ),
+            Self::WrongPassphrase => write!(
+                f,
+                "Wallet: cannot unlock the wallet with a wrong passphrase"
+            ),
+            Self::InvalidWalletFile => write!(f, "Wallet: wallet file is malformed"),
+            Self::Io(err) => write!(f, "Wallet: cannot access the wallet file: {}", err),
+            Self::InsufficientFunds => write!(
+                f,
+                "Wallet: cannot pay the amount and the fee out of the spendable utxos"
+            ),
+            Self::WatchOnly => write!(f, "Wallet: cannot derive keys in a watch-only wallet"),
+            Self::ChangeTooLarge => write!(
+                f,
+                "Wallet: cannot hold the change of the selected utxos in a single output"
+            ),
         }
     }
 }
@@ -33,6 +67,15 @@ impl error::Error for WalletError {
             Self::WrongPublicKey => None,
             Self::KnownUtxo => None,
             Self::UnknownUtxo => None,
+            Self::InvalidMnemonic => None,
+            Self::Locked => None,
+            Self::NotEncrypted => None,
+            Self::WrongPassphrase => None,
+            Self::InvalidWalletFile => None,
+            Self::Io(err) => Some(err),
+            Self::InsufficientFunds => None,
+            Self::WatchOnly => None,
+            Self::ChangeTooLarge => None,
         }
     }
 }
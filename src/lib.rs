@@ -2,8 +2,10 @@ use generic_array::{typenum::U32, GenericArray};
 
 pub type Hash = GenericArray<u8, U32>;
 
+pub mod address;
 pub mod block;
 pub mod blockchain;
+pub mod consensus;
 pub mod constants;
 pub mod error;
 pub mod miner;
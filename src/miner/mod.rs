@@ -1,41 +1,142 @@
+use std::time::Instant;
+
 use crate::block::Block;
+use crate::consensus::proof_of_work::ProofOfWork;
+use crate::consensus::ConsensusEngine;
+use crate::constants::MINER_THREADS;
+use crate::transaction::Transaction;
 use crate::transaction_pool::TransactionPool;
+use crate::utxo_pool::UtxoPool;
+use crate::Hash as BlockHash;
 
 pub struct Miner {
     block: Option<Block>,
+    engine: Box<dyn ConsensusEngine>,
+    started: Instant,
+    blocks: Vec<BlockHash>,
 }
 
 impl Miner {
     pub fn new() -> Self {
-        Self { block: None }
+        Self::with_threads(MINER_THREADS)
     }
 
-    pub fn mine(&mut self, top: &Block, transaction_pool: &TransactionPool) -> Option<Block> {
-        self.mine_from(top, transaction_pool);
-        if let Some(block) = self.block.as_mut() {
-            if block.hash() < block.target().hash() {
-                self.block.take()
-            } else {
-                block.inc_nonce();
-                None
-            }
-        } else {
-            None
+    /// Miner sealing blocks by proof of work on the given number of threads
+    pub fn with_threads(threads: usize) -> Self {
+        Self::with_engine(Box::new(ProofOfWork::new(threads)))
+    }
+
+    pub fn with_engine(engine: Box<dyn ConsensusEngine>) -> Self {
+        Self {
+            block: None,
+            engine,
+            started: Instant::now(),
+            blocks: Vec::new(),
         }
     }
 
-    pub fn mine_from(&mut self, top: &Block, transaction_pool: &TransactionPool) {
+    /// Keeps the consensus engine busy on a block extending `top`, of utxo pool `utxo_pool`, and
+    /// returns the block once sealed
+    pub fn mine(
+        &mut self,
+        top: &Block,
+        utxo_pool: &UtxoPool,
+        transaction_pool: &TransactionPool,
+    ) -> Option<Block> {
+        self.mine_with(top, utxo_pool, || transaction_pool.select())
+    }
+
+    /// Same as `mine`, but on the transactions returned by `transactions` when starting a block
+    pub fn mine_with<F>(
+        &mut self,
+        top: &Block,
+        utxo_pool: &UtxoPool,
+        transactions: F,
+    ) -> Option<Block>
+    where
+        F: FnOnce() -> Option<Vec<Transaction>>,
+    {
+        self.start(top, utxo_pool, transactions);
+        self.block.as_ref()?;
+        let block = self.engine.seal()?;
+        self.discard_block();
+        self.blocks.push(block.hash());
+        Some(block)
+    }
+
+    /// Starts mining a block extending `top` with the best paying packages of the pool, unless
+    /// already mining on it
+    pub fn mine_from(
+        &mut self,
+        top: &Block,
+        utxo_pool: &UtxoPool,
+        transaction_pool: &TransactionPool,
+    ) {
+        self.start(top, utxo_pool, || transaction_pool.select());
+    }
+
+    fn start<F>(&mut self, top: &Block, utxo_pool: &UtxoPool, transactions: F)
+    where
+        F: FnOnce() -> Option<Vec<Transaction>>,
+    {
         if let Some(block) = self.block.as_ref() {
             if block.hash_prev_block() == &top.hash() {
                 return;
             }
         }
-        self.block = transaction_pool
-            .select()
-            .map(|transactions| Block::new(top, transactions).unwrap());
+        self.block = transactions().map(|transactions| Block::new(top, transactions).unwrap());
+        match self.block.as_ref() {
+            Some(block) => self.engine.start(top, block, utxo_pool),
+            None => self.engine.discard(),
+        }
     }
 
+    /// Stops mining the current block, e.g. because a new tip arrived
     pub fn discard_block(&mut self) {
         self.block = None;
+        self.engine.discard();
+    }
+
+    /// Replaces the consensus engine, dropping the block being mined
+    pub fn set_engine(&mut self, engine: Box<dyn ConsensusEngine>) {
+        self.discard_block();
+        self.engine = engine;
+    }
+
+    pub fn engine(&self) -> &dyn ConsensusEngine {
+        self.engine.as_ref()
+    }
+
+    pub fn engine_mut(&mut self) -> &mut dyn ConsensusEngine {
+        self.engine.as_mut()
+    }
+
+    /// Limits the hashes computed per second (`None` for no limit)
+    pub fn throttle(&mut self, hashrate: Option<f64>) {
+        self.engine.throttle(hashrate);
+    }
+
+    /// Limit of the hashes computed per second
+    pub fn hashrate_limit(&self) -> Option<f64> {
+        self.engine.hashrate_limit()
+    }
+
+    /// Number of hashes computed so far
+    pub fn hashes(&self) -> u64 {
+        self.engine.hashes()
+    }
+
+    /// Average number of hashes computed per second since the miner started
+    pub fn hashrate(&self) -> f64 {
+        self.hashes() as f64 / self.started.elapsed().as_secs_f64()
+    }
+
+    /// Hashes of the blocks found so far
+    pub fn blocks(&self) -> &Vec<BlockHash> {
+        &self.blocks
     }
 }
+
+pub mod selfish;
+pub mod template;
+pub mod workers;
@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+use crate::block::Block;
+
+/// Block withholding strategy of Eyal and Sirer ("Majority is not Enough", 2013)
+///
+/// The selfish miner keeps the blocks it finds on a private branch and only publishes them
+/// when doing so invalidates the work of the honest miners.
+#[derive(Debug, Default)]
+pub struct SelfishMining {
+    withheld: VecDeque<Block>,
+    private_branch_len: usize,
+    public_height: usize,
+}
+
+impl SelfishMining {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Withholds a block found by the selfish miner and returns the blocks to publish
+    ///
+    /// `private_height` is the height of the private chain before the block is appended.
+    pub fn on_own_block(&mut self, block: Block, private_height: usize) -> Vec<Block> {
+        let delta_prev = private_height.saturating_sub(self.public_height);
+        self.withheld.push_back(block);
+        self.private_branch_len += 1;
+        if delta_prev == 0 && self.private_branch_len == 2 {
+            self.private_branch_len = 0;
+            self.publish_all()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Reacts to a block found by the other miners and returns the blocks to publish
+    ///
+    /// `private_height` is the height of the private chain before the block is received.
+    pub fn on_public_block(&mut self, height: usize, private_height: usize) -> Vec<Block> {
+        if height <= self.public_height {
+            return vec![];
+        }
+        let delta_prev = private_height.saturating_sub(self.public_height);
+        self.public_height = height;
+        match delta_prev {
+            0 => {
+                self.withheld.clear();
+                self.private_branch_len = 0;
+                vec![]
+            }
+            1 => self.publish_all(),
+            2 => {
+                self.private_branch_len = 0;
+                self.publish_all()
+            }
+            _ => self.publish_first(),
+        }
+    }
+
+    fn publish_all(&mut self) -> Vec<Block> {
+        if let Some(block) = self.withheld.back() {
+            self.public_height = self.public_height.max(block.height());
+        }
+        self.withheld.drain(..).collect()
+    }
+
+    fn publish_first(&mut self) -> Vec<Block> {
+        match self.withheld.pop_front() {
+            Some(block) => {
+                self.public_height = self.public_height.max(block.height());
+                vec![block]
+            }
+            None => vec![],
+        }
+    }
+
+    pub fn withheld(&self) -> &VecDeque<Block> {
+        &self.withheld
+    }
+
+    pub fn public_height(&self) -> usize {
+        self.public_height
+    }
+}
@@ -0,0 +1,72 @@
+use std::iter;
+
+use crate::block::blockheader::BlockHeader;
+use crate::block::Block;
+use crate::consensus::ConsensusEngine;
+use crate::error::block::BlockError;
+use crate::transaction::Transaction;
+use crate::utxo_pool::UtxoPool;
+
+/// Unsolved block handed out to an external miner
+///
+/// The miner searches for a header of the template with a hash below the target and submits
+/// it back to the node, which then rebuilds the block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockTemplate {
+    block: Block,
+}
+
+impl BlockTemplate {
+    pub fn new(block: Block) -> Self {
+        Self { block }
+    }
+
+    /// Turns the template into a block with the given header, sealed according to the engine
+    /// rules on top of `parent`, of utxo pool `utxo_pool`
+    pub fn solve(
+        &self,
+        header: &BlockHeader,
+        parent: &Block,
+        utxo_pool: &UtxoPool,
+        engine: &dyn ConsensusEngine,
+    ) -> Result<Block, BlockError> {
+        let template = self.header();
+        if header.hash_prev_block() != template.hash_prev_block()
+            || header.hash_merkle_root() != template.hash_merkle_root()
+            || header.target().serialize() != template.target().serialize()
+        {
+            return Err(BlockError::UnknownTemplate);
+        }
+        let mut block = self.block.clone();
+        block.set_nonce(header.nonce());
+        block.set_extra_nonce(header.extra_nonce());
+        engine.verify_seal(parent, &block, utxo_pool)?;
+        Ok(block)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        iter::once(b'm')
+            .chain(self.block.serialize().into_iter().skip(1))
+            .collect()
+    }
+
+    /// Deserializes bytes that may be malformed, e.g. sent by the node to an external miner
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        Block::deserialize(bytes).map(|block| Self { block })
+    }
+
+    pub fn height(&self) -> usize {
+        self.block.height()
+    }
+
+    pub fn header(&self) -> &BlockHeader {
+        self.block.header()
+    }
+
+    pub fn transactions(&self) -> &Vec<Transaction> {
+        self.block.transactions()
+    }
+}
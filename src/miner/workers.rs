@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::block::Block;
+
+/// Threads searching the nonces of a block template in parallel
+///
+/// Worker #i tries the extra nonces `r + i, r + i + n, r + i + 2n, ...` of the `n` workers, each
+/// with every nonce, so no header is hashed twice. The random offset `r` keeps miners working on
+/// the same transactions from hashing the same headers. A new template or a cancellation is picked
+/// up by the workers right after their current hash.
+///
+/// The workers may be throttled to a given number of hashes per second between them.
+pub struct Workers {
+    shared: Arc<Shared>,
+    found: Receiver<(u64, Block)>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    job: Mutex<(u64, Option<Block>)>,
+    submitted: Condvar,
+    generation: AtomicU64,
+    hashes: AtomicU64,
+    limit: AtomicU64,
+    stop: AtomicBool,
+}
+
+impl Workers {
+    pub fn new(threads: usize) -> Self {
+        assert!(threads > 0);
+        let shared = Arc::new(Shared {
+            job: Mutex::new((0, None)),
+            submitted: Condvar::new(),
+            generation: AtomicU64::new(0),
+            hashes: AtomicU64::new(0),
+            limit: AtomicU64::new(0f64.to_bits()),
+            stop: AtomicBool::new(false),
+        });
+        let (sender, found) = mpsc::channel();
+        let offset: u32 = rand::random();
+        let threads = (0..threads)
+            .map(|index| {
+                let shared = Arc::clone(&shared);
+                let sender = sender.clone();
+                let extra_nonce = offset.wrapping_add(index as u32);
+                let step = threads as u32;
+                thread::spawn(move || work(&shared, &sender, extra_nonce, step))
+            })
+            .collect();
+        Self {
+            shared,
+            found,
+            threads,
+        }
+    }
+
+    /// Makes the workers drop what they are doing and search the nonces of the block
+    pub fn submit(&self, block: Block) {
+        let mut job = self.shared.job.lock().unwrap();
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *job = (generation, Some(block));
+        self.shared.submitted.notify_all();
+    }
+
+    /// Makes the workers drop what they are doing and wait for the next block
+    pub fn cancel(&self) {
+        let mut job = self.shared.job.lock().unwrap();
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *job = (generation, None);
+    }
+
+    /// Returns a block found for the block last submitted, if any
+    pub fn found(&self) -> Option<Block> {
+        let generation = self.shared.generation.load(Ordering::SeqCst);
+        self.found
+            .try_iter()
+            .find(|(g, _)| *g == generation)
+            .map(|(_, block)| block)
+    }
+
+    /// Limits the hashes computed per second by all the workers (`None` for no limit)
+    pub fn throttle(&self, limit: Option<f64>) {
+        let limit = limit.unwrap_or(0.0);
+        self.shared.limit.store(limit.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Limit of the hashes computed per second by all the workers
+    pub fn limit(&self) -> Option<f64> {
+        Some(f64::from_bits(self.shared.limit.load(Ordering::Relaxed))).filter(|&l| l > 0.0)
+    }
+
+    /// Number of hashes computed so far by all the workers
+    pub fn hashes(&self) -> u64 {
+        self.shared.hashes.load(Ordering::Relaxed)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        {
+            let _job = self.shared.job.lock().unwrap();
+            self.shared.stop.store(true, Ordering::SeqCst);
+            self.shared.submitted.notify_all();
+        }
+        for thread in self.threads.drain(..) {
+            thread.join().unwrap();
+        }
+    }
+}
+
+fn work(shared: &Shared, found: &Sender<(u64, Block)>, first_extra_nonce: u32, step: u32) {
+    let mut done = 0;
+    loop {
+        let (generation, mut block) = {
+            let mut job = shared.job.lock().unwrap();
+            loop {
+                if shared.stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                if job.0 != done {
+                    if let Some(block) = job.1.as_ref() {
+                        break (job.0, block.clone());
+                    }
+                }
+                job = shared.submitted.wait(job).unwrap();
+            }
+        };
+        done = generation;
+        let mut extra_nonce = first_extra_nonce;
+        block.set_extra_nonce(extra_nonce);
+        block.set_nonce(0);
+        let target = block.target().hash();
+        let started = Instant::now();
+        let mut hashes = 0;
+        while shared.generation.load(Ordering::Relaxed) == generation
+            && !shared.stop.load(Ordering::Relaxed)
+        {
+            let limit = f64::from_bits(shared.limit.load(Ordering::Relaxed));
+            if limit > 0.0 {
+                let due = Duration::from_secs_f64(hashes as f64 * step as f64 / limit);
+                let elapsed = started.elapsed();
+                if due > elapsed {
+                    thread::sleep(due - elapsed);
+                    continue;
+                }
+            }
+            hashes += 1;
+            shared.hashes.fetch_add(1, Ordering::Relaxed);
+            if block.hash() < target {
+                found.send((generation, block)).unwrap();
+                break;
+            }
+            if block.nonce() == u32::MAX {
+                extra_nonce = extra_nonce.wrapping_add(step);
+                block.set_extra_nonce(extra_nonce);
+                block.set_nonce(0);
+            } else {
+                block.inc_nonce();
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::node::Node;
+use crate::Hash as BlockHash;
+
+/// State shared by the Sybil identities of a single attacker
+///
+/// Only the first identity (the leader) mines: identities are cheap, hash power is not. The fake
+/// chain is only sent to the victim, so that the honest nodes keep mining their own.
+#[derive(Clone, Debug)]
+pub struct Attacker {
+    victim: usize,
+    identities: Vec<usize>,
+    blocks: Arc<Mutex<HashSet<BlockHash>>>,
+}
+
+impl Attacker {
+    pub fn new(victim: usize, identities: Vec<usize>) -> Self {
+        assert!(!identities.is_empty(), "Attacker has no identities");
+        Self {
+            victim,
+            identities,
+            blocks: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub fn add(&self, block: &Block) {
+        self.blocks.lock().unwrap().insert(block.hash());
+    }
+
+    /// Checks if the block was mined by the attacker
+    pub fn owns(&self, block: &Block) -> bool {
+        self.blocks.lock().unwrap().contains(&block.hash())
+    }
+
+    /// Checks if the chain ending at the given block contains a block mined by the attacker
+    pub fn owns_chain_of(&self, block: &Block, blockchain: &Blockchain) -> bool {
+        let blocks = self.blocks.lock().unwrap();
+        let mut block = block;
+        loop {
+            if blocks.contains(&block.hash()) {
+                return true;
+            }
+            match blockchain.get_parent_of(block) {
+                Some(parent) => block = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn is_identity(&self, id: usize) -> bool {
+        self.identities.contains(&id)
+    }
+
+    /// Checks if the identities send blocks and transactions to the node
+    pub fn targets(&self, id: usize) -> bool {
+        id == self.victim || self.is_identity(id)
+    }
+
+    pub fn victim(&self) -> usize {
+        self.victim
+    }
+
+    pub fn leader(&self) -> usize {
+        self.identities[0]
+    }
+
+    pub fn identities(&self) -> &Vec<usize> {
+        &self.identities
+    }
+
+    pub fn blocks(&self) -> HashSet<BlockHash> {
+        self.blocks.lock().unwrap().clone()
+    }
+}
+
+/// Measures of an eclipse attack once the network has shut down
+#[derive(Clone, Copy, Debug)]
+pub struct EclipseMetrics {
+    /// Time spent by the victim on the attacker's chain
+    pub victim_on_attacker_chain: Duration,
+    /// Mean time spent by the honest nodes (victim excluded) on the attacker's chain
+    pub honest_on_attacker_chain: Duration,
+    /// Duration of the simulation
+    pub duration: Duration,
+    pub victim_height: usize,
+    pub honest_height: usize,
+}
+
+impl EclipseMetrics {
+    pub fn measure(
+        victim: &Node,
+        honest_nodes: &[&Node],
+        attacker: &Attacker,
+        end: Instant,
+    ) -> Self {
+        let duration = end.duration_since(victim.tips()[0].0);
+        let victim_on_attacker_chain = time_on_attacker_chain(victim, attacker, end);
+        let honest_nodes: Vec<_> = honest_nodes
+            .iter()
+            .filter(|n| n.id() != victim.id())
+            .collect();
+        let honest_on_attacker_chain = honest_nodes
+            .iter()
+            .map(|n| time_on_attacker_chain(n, attacker, end))
+            .sum::<Duration>()
+            .checked_div(honest_nodes.len() as u32)
+            .unwrap_or_default();
+        let honest_height = honest_nodes
+            .iter()
+            .map(|n| n.blockchain().height())
+            .max()
+            .unwrap_or_default();
+        Self {
+            victim_on_attacker_chain,
+            honest_on_attacker_chain,
+            duration,
+            victim_height: victim.blockchain().height(),
+            honest_height,
+        }
+    }
+
+    /// Fraction of the simulation spent by the victim on the attacker's chain
+    pub fn victim_ratio(&self) -> f64 {
+        self.victim_on_attacker_chain.as_secs_f64() / self.duration.as_secs_f64()
+    }
+
+    /// Mean fraction of the simulation spent by the honest nodes on the attacker's chain
+    pub fn honest_ratio(&self) -> f64 {
+        self.honest_on_attacker_chain.as_secs_f64() / self.duration.as_secs_f64()
+    }
+}
+
+fn time_on_attacker_chain(node: &Node, attacker: &Attacker, end: Instant) -> Duration {
+    let blockchain = node.blockchain();
+    let tips = node.tips();
+    let mut time = Duration::default();
+    for (i, (start, tip)) in tips.iter().enumerate() {
+        let stop = tips.get(i + 1).map_or(end, |(t, _)| *t);
+        if attacker.owns_chain_of(&blockchain.chain()[tip], blockchain) {
+            time += stop.duration_since(*start);
+        }
+    }
+    time
+}
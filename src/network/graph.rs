@@ -40,6 +40,45 @@ impl Graph {
         graph
     }
 
+    /// Builds a graph where vertex 0 (the target) is only connected to sybil vertices
+    ///
+    /// Vertices `1..honest` form a random connected graph. Vertices `honest..honest + sybils` are
+    /// the sybils: they are connected to the target, to each other, and each to a random honest
+    /// vertex.
+    pub fn eclipse(honest: usize, sybils: usize) -> Graph {
+        assert!(honest > 0, "Graph has no target");
+        assert!(sybils > 0, "Graph has no sybils");
+        let vertices = honest + sybils;
+        let mut graph = Graph::with_capacity(vertices);
+        for vertex in 0..vertices {
+            graph.insert(vertex, HashSet::new());
+        }
+
+        let mut rng = rand::thread_rng();
+        for vertex in 2..honest {
+            let neighbours_len = rng.gen_range(1, vertex);
+            let neighbours = (1..vertex).choose_multiple(&mut rng, neighbours_len);
+            for neighbour in neighbours {
+                graph.connect(vertex, neighbour);
+            }
+        }
+        for sybil in honest..vertices {
+            graph.connect(sybil, 0);
+            for other in honest..sybil {
+                graph.connect(sybil, other);
+            }
+            if honest > 1 {
+                graph.connect(sybil, rng.gen_range(1, honest));
+            }
+        }
+        graph
+    }
+
+    fn connect(&mut self, vertex1: Vertex, vertex2: Vertex) {
+        self.get_mut(&vertex1).unwrap().insert(vertex2);
+        self.get_mut(&vertex2).unwrap().insert(vertex1);
+    }
+
     pub fn insert(&mut self, k: Vertex, v: Neighborhood) -> Option<Neighborhood> {
         self.0.insert(k, v)
     }
@@ -83,4 +122,24 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn eclipse_graph() {
+        let honest = 6;
+        let sybils = 4;
+        let graph = Graph::eclipse(honest, sybils);
+        println!("{:?}", graph);
+        assert_eq!(graph.size(), honest + sybils);
+        assert_eq!(graph[0], (honest..honest + sybils).collect());
+        for (vertex, neighborhood) in graph.as_ref() {
+            assert!(!neighborhood.is_empty());
+            assert!(!neighborhood.contains(vertex));
+            for &neighbour in neighborhood {
+                assert!(graph[neighbour].contains(vertex));
+            }
+        }
+        for sybil in honest..honest + sybils {
+            assert!(graph[sybil].iter().any(|&v| 0 < v && v < honest));
+        }
+    }
 }
@@ -0,0 +1,72 @@
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use secp256k1::ecdh::SharedSecret;
+use secp256k1::{PublicKey, SecretKey};
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+
+use crate::error::link::LinkError;
+
+/// Encrypted and authenticated channel with a neighbour
+///
+/// Both ends agree on the keys from an ephemeral and a static Diffie-Hellman exchange, so only
+/// the holder of the neighbour's node key can read or forge packets. Packets are sealed with
+/// ChaCha20-Poly1305 under one key per direction and a counter nonce, channels being FIFO.
+pub struct Link {
+    send_cipher: ChaCha20Poly1305,
+    receive_cipher: ChaCha20Poly1305,
+    send_nonce: Cell<u64>,
+    receive_nonce: u64,
+}
+
+impl Link {
+    pub fn new(
+        public_key: &PublicKey,
+        secret_key: &SecretKey,
+        ephemeral_key: &SecretKey,
+        peer_public_key: &PublicKey,
+        peer_ephemeral_key: &PublicKey,
+    ) -> Self {
+        let ephemeral = SharedSecret::new(peer_ephemeral_key, ephemeral_key);
+        let static_ = SharedSecret::new(peer_public_key, secret_key);
+        let derive = |from: &PublicKey, to: &PublicKey| {
+            let mut hasher = Sha256::new();
+            hasher.input(&ephemeral[..]);
+            hasher.input(&static_[..]);
+            hasher.input(&from.serialize()[..]);
+            hasher.input(&to.serialize()[..]);
+            ChaCha20Poly1305::new(Key::from_slice(hasher.result().as_slice()))
+        };
+        Self {
+            send_cipher: derive(public_key, peer_public_key),
+            receive_cipher: derive(peer_public_key, public_key),
+            send_nonce: Cell::new(0),
+            receive_nonce: 0,
+        }
+    }
+
+    pub fn seal(&self, bytes: &[u8]) -> Vec<u8> {
+        let nonce = self.send_nonce.get();
+        self.send_nonce.set(nonce + 1);
+        self.send_cipher
+            .encrypt(&Self::nonce(nonce), bytes)
+            .expect("Plaintext too long")
+    }
+
+    /// Decrypts a packet from the neighbour, failing if it was not sealed by the other end of
+    /// the link or not in order
+    pub fn open(&mut self, bytes: &[u8]) -> Result<Vec<u8>, LinkError> {
+        let bytes = self
+            .receive_cipher
+            .decrypt(&Self::nonce(self.receive_nonce), bytes)
+            .map_err(|_| LinkError::Unauthenticated)?;
+        self.receive_nonce += 1;
+        Ok(bytes)
+    }
+
+    fn nonce(counter: u64) -> Nonce {
+        let mut nonce = Nonce::default();
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        nonce
+    }
+}
@@ -5,7 +5,9 @@ use std::sync::mpsc::Sender;
 use std::sync::{mpsc, Arc, Barrier, Mutex};
 use std::thread::{self, JoinHandle};
 
+use self::eclipse::Attacker;
 use self::graph::Graph;
+use crate::consensus::finality::FinalityGadget;
 use crate::node::behaviour::Behaviour;
 use crate::node::message::Message;
 use crate::node::Node;
@@ -13,10 +15,13 @@ use crate::node::Node;
 pub use self::neighbour::Neighbour;
 pub use self::synchronizer::Synchronizer;
 
+/// Serialized message along with the id of the node that sent it (`None` for the network itself)
+pub type Packet = (Option<usize>, Arc<Vec<u8>>);
+
 pub struct Network {
     nodes: Vec<Option<Node>>,
     threads: Vec<Option<JoinHandle<Node>>>,
-    senders: Vec<Sender<Arc<Vec<u8>>>>,
+    senders: Vec<Sender<Packet>>,
 }
 
 impl Network {
@@ -34,7 +39,37 @@ impl Network {
     }
 
     pub fn random(honest: usize, malicious: usize) -> Self {
-        let nodes = honest + malicious;
+        let mut behaviours = vec![Behaviour::Honest; honest];
+        behaviours.resize(honest + malicious, Behaviour::Malicious);
+        Self::random_with(&behaviours)
+    }
+
+    /// Builds a random connected network whose node #i behaves according to `behaviours[i]`
+    pub fn random_with(behaviours: &[Behaviour]) -> Self {
+        Self::with_graph(Graph::random_connected(behaviours.len()), behaviours)
+    }
+
+    /// Builds a network where node #0 is eclipsed by the Sybil identities of a single attacker
+    ///
+    /// Nodes `0..honest` are honest and nodes `honest..honest + sybils` are the attacker's
+    /// identities. The victim's neighbours are all Sybil identities.
+    pub fn eclipse(honest: usize, sybils: usize) -> Self {
+        let mut behaviours = vec![Behaviour::Honest; honest];
+        behaviours.resize(honest + sybils, Behaviour::Sybil);
+        let mut network = Self::with_graph(Graph::eclipse(honest, sybils), &behaviours);
+        let attacker = Attacker::new(0, (honest..honest + sybils).collect());
+        for node in network.nodes.iter_mut().filter_map(|n| n.as_mut()) {
+            if node.integrity() == Behaviour::Sybil {
+                node.join(attacker.clone());
+            }
+        }
+        network
+    }
+
+    /// Builds a network on the given graph whose node #i behaves according to `behaviours[i]`
+    pub fn with_graph(graph: Graph, behaviours: &[Behaviour]) -> Self {
+        let nodes = behaviours.len();
+        assert_eq!(graph.size(), nodes);
         let secp = Secp256k1::new();
         let mut rng = rand::thread_rng();
         let mut public_keys = Vec::with_capacity(nodes);
@@ -56,7 +91,6 @@ impl Network {
             listeners.push(listener);
         }
 
-        let graph = Graph::random_connected(nodes);
         let mut network = Network::with_capacity(nodes);
         let barrier = Arc::new(Barrier::new(nodes));
         let state = Arc::new(Mutex::new(vec![true; nodes]));
@@ -72,11 +106,7 @@ impl Network {
             let barrier = Arc::clone(&barrier);
             let state = Arc::clone(&state);
             let synchronizer = Synchronizer::new(barrier, state);
-            let integrity = if id < honest {
-                Behaviour::Honest
-            } else {
-                Behaviour::Malicious
-            };
+            let integrity = behaviours[id];
             let node = Node::new(
                 id,
                 public_key,
@@ -93,6 +123,50 @@ impl Network {
         network
     }
 
+    /// Gives node #i the share `hash_powers[i]` of `NETWORK_HASHRATE`
+    ///
+    /// Nodes mine as fast as they can unless this is called, before the network runs.
+    pub fn set_hash_powers(&mut self, hash_powers: &[f64]) {
+        assert_eq!(hash_powers.len(), self.nodes.len());
+        for node in self.nodes.iter_mut() {
+            let node = node.as_mut().unwrap();
+            node.set_hash_power(hash_powers[node.id()]);
+        }
+    }
+
+    /// Makes the nodes follow a proof of authority where nodes `authorities` sign blocks in turn
+    ///
+    /// Must be called before the network runs.
+    pub fn set_proof_of_authority(&mut self, authorities: &[usize]) {
+        let mut nodes: Vec<&mut Node> = self.nodes.iter_mut().filter_map(|n| n.as_mut()).collect();
+        let public_keys: Vec<PublicKey> = authorities
+            .iter()
+            .map(|&id| *nodes.iter().find(|n| n.id() == id).unwrap().public_key())
+            .collect();
+        for node in nodes.iter_mut() {
+            node.set_proof_of_authority(public_keys.clone());
+        }
+    }
+
+    /// Makes the nodes finalize checkpoints every `interval` blocks by the votes of the holders
+    /// of `validators`
+    ///
+    /// Must be called before the network runs.
+    pub fn set_finality(&mut self, interval: usize, validators: &[PublicKey]) {
+        for node in self.nodes.iter_mut().filter_map(|n| n.as_mut()) {
+            node.set_finality(FinalityGadget::new(interval, validators.to_vec()));
+        }
+    }
+
+    /// Makes the nodes follow a proof of stake where the stakes are the coins of the utxo pool
+    ///
+    /// Must be called before the network runs.
+    pub fn set_proof_of_stake(&mut self) {
+        for node in self.nodes.iter_mut().filter_map(|n| n.as_mut()) {
+            node.set_proof_of_stake();
+        }
+    }
+
     pub fn run(&mut self) {
         while let Some(node) = self.nodes.pop() {
             let mut node = node.unwrap();
@@ -112,7 +186,7 @@ impl Network {
     pub fn broadcast(&self, message: Message) {
         let bytes = Arc::new(message.serialize());
         for sender in &self.senders {
-            sender.send(Arc::clone(&bytes)).unwrap();
+            sender.send((None, Arc::clone(&bytes))).unwrap();
         }
     }
 
@@ -126,6 +200,10 @@ impl Network {
         &self.nodes
     }
 
+    pub fn nodes_mut(&mut self) -> &mut Vec<Option<Node>> {
+        &mut self.nodes
+    }
+
     pub fn nodes_as_ref(&self) -> Vec<&Node> {
         self.nodes.iter().filter_map(|o| o.as_ref()).collect()
     }
@@ -176,6 +254,8 @@ impl fmt::Debug for Network {
     }
 }
 
+pub mod eclipse;
 pub mod graph;
+pub mod link;
 pub mod neighbour;
 pub mod synchronizer;
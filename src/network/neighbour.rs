@@ -1,20 +1,60 @@
 use secp256k1::PublicKey;
 use std::sync::mpsc::Sender;
-use std::sync::Arc;
+use std::time::Instant;
+
+use crate::constants::{BAN_DURATION, BAN_SCORE};
+use crate::error::link::LinkError;
+use crate::network::link::Link;
+use crate::network::Packet;
+use crate::node::version::Version;
 
 pub struct Neighbour {
     id: usize,
     public_key: PublicKey,
-    sender: Sender<Arc<Vec<u8>>>,
+    sender: Sender<Packet>,
+    misbehaviour: u32,
+    banned_until: Option<Instant>,
+    version: Option<Version>,
+    verack: bool,
+    refused: bool,
+    link: Option<Link>,
 }
 
 impl Neighbour {
-    pub fn new(id: usize, public_key: PublicKey, sender: Sender<Arc<Vec<u8>>>) -> Self {
+    pub fn new(id: usize, public_key: PublicKey, sender: Sender<Packet>) -> Self {
         Self {
             id,
             public_key,
             sender,
+            misbehaviour: 0,
+            banned_until: None,
+            version: None,
+            verack: false,
+            refused: false,
+            link: None,
+        }
+    }
+
+    /// Adds to the misbehaviour score of the neighbour and bans it if the score reaches
+    /// `BAN_SCORE`
+    ///
+    /// Returns true if the neighbour gets banned.
+    pub fn misbehave(&mut self, score: u32) -> bool {
+        if self.is_banned() {
+            return false;
         }
+        self.misbehaviour += score;
+        if self.misbehaviour < BAN_SCORE {
+            return false;
+        }
+        self.misbehaviour = 0;
+        self.banned_until = Some(Instant::now() + BAN_DURATION);
+        true
+    }
+
+    pub fn is_banned(&self) -> bool {
+        self.banned_until
+            .is_some_and(|banned_until| Instant::now() < banned_until)
     }
 
     pub fn id(&self) -> usize {
@@ -25,7 +65,64 @@ impl Neighbour {
         &self.public_key
     }
 
-    pub fn sender(&self) -> &Sender<Arc<Vec<u8>>> {
+    /// Records the version the neighbour introduced itself with
+    pub fn accept(&mut self, version: Version) {
+        self.version = Some(version);
+    }
+
+    /// Records that the neighbour acknowledged our version
+    pub fn acknowledge(&mut self) {
+        self.verack = true;
+    }
+
+    /// Stops talking to the neighbour for good
+    pub fn refuse(&mut self) {
+        self.refused = true;
+    }
+
+    /// Secures the packets exchanged with the neighbour from now on
+    pub fn establish(&mut self, link: Link) {
+        self.link = Some(link);
+    }
+
+    /// Encrypts bytes for the neighbour, leaving them in the clear before a link is established
+    pub fn seal(&self, bytes: &[u8]) -> Vec<u8> {
+        match self.link.as_ref() {
+            Some(link) => link.seal(bytes),
+            None => bytes.to_vec(),
+        }
+    }
+
+    /// Decrypts bytes from the neighbour
+    pub fn open(&mut self, bytes: &[u8]) -> Result<Vec<u8>, LinkError> {
+        self.link
+            .as_mut()
+            .ok_or(LinkError::NotEstablished)
+            .and_then(|link| link.open(bytes))
+    }
+
+    /// Checks if the handshake with the neighbour is complete
+    pub fn is_connected(&self) -> bool {
+        self.version.is_some() && self.verack && !self.refused
+    }
+
+    pub fn is_refused(&self) -> bool {
+        self.refused
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    pub fn link(&self) -> Option<&Link> {
+        self.link.as_ref()
+    }
+
+    pub fn misbehaviour(&self) -> u32 {
+        self.misbehaviour
+    }
+
+    pub fn sender(&self) -> &Sender<Packet> {
         &self.sender
     }
 }
@@ -4,6 +4,10 @@ use std::fmt;
 pub enum Behaviour {
     Honest,
     Malicious,
+    SelfishMiner,
+    Sybil,
+    Equivocator,
+    Counterfeiter,
 }
 
 impl fmt::Display for Behaviour {
@@ -14,6 +18,10 @@ impl fmt::Display for Behaviour {
             match self {
                 Behaviour::Honest => "honest",
                 Behaviour::Malicious => "malicious",
+                Behaviour::SelfishMiner => "selfish miner",
+                Behaviour::Sybil => "sybil",
+                Behaviour::Equivocator => "equivocator",
+                Behaviour::Counterfeiter => "counterfeiter",
             }
         )
     }
@@ -1,47 +1,85 @@
 use std::borrow::Cow;
+use std::iter;
 
+use crate::block::blockheader::BlockHeader;
 use crate::block::Block;
+use crate::consensus::evidence::Evidence;
+use crate::consensus::vote::Vote;
+use crate::error::message::MessageError;
+use crate::miner::template::BlockTemplate;
+use crate::node::version::Version;
 use crate::transaction::Transaction;
 
+const GET_BLOCK_TEMPLATE: &[u8] = b"g";
 const SHUT_DOWN: &[u8] = b"Shut down";
+const VERACK: &[u8] = b"a";
 
 #[derive(Eq, PartialEq)]
 pub enum Message<'a> {
     Transaction(Cow<'a, Transaction>),
     Block(Cow<'a, Block>),
+    Version(Version),
+    Verack,
+    GetBlockTemplate,
+    BlockTemplate(Cow<'a, BlockTemplate>),
+    SubmitBlock(BlockHeader),
+    Evidence(Evidence),
+    Vote(Vote),
     ShutDown,
 }
 
-impl<'a, B> From<B> for Message<'a>
-where
-    B: AsRef<[u8]>,
-{
-    fn from(bytes: B) -> Self {
-        let bytes = bytes.as_ref();
-        if &bytes[..] == SHUT_DOWN {
-            return Message::ShutDown;
-        }
-        match bytes[0] {
-            b't' => Message::Transaction(Cow::Owned(Transaction::deserialize(&bytes[..]).0)),
-            b'b' => Message::Block(Cow::Owned(Block::deserialize(&bytes[..]))),
-            _ => panic!("Unexpected message"),
-        }
-    }
-}
-
 impl<'a> Message<'a> {
     pub fn serialize(&self) -> Vec<u8> {
         match self {
             Message::Transaction(transaction) => transaction.serialize(),
             Message::Block(block) => block.serialize(),
+            Message::Version(version) => version.serialize(),
+            Message::Verack => VERACK.to_vec(),
+            Message::GetBlockTemplate => GET_BLOCK_TEMPLATE.to_vec(),
+            Message::BlockTemplate(template) => template.serialize(),
+            Message::SubmitBlock(header) => iter::once(b'h').chain(header.serialize()).collect(),
+            Message::Evidence(evidence) => evidence.serialize(),
+            Message::Vote(vote) => vote.serialize(),
             Message::ShutDown => SHUT_DOWN.to_vec(),
         }
     }
 
-    pub fn deserialize<T>(bytes: T) -> Self
+    /// Deserializes a message from a neighbour, failing on an unknown type or malformed bytes
+    pub fn deserialize<T>(bytes: T) -> Result<Self, MessageError>
     where
         T: AsRef<[u8]>,
     {
-        Self::from(bytes)
+        let bytes = bytes.as_ref();
+        if bytes == SHUT_DOWN {
+            return Ok(Message::ShutDown);
+        }
+        let message = match *bytes.first().ok_or(MessageError::Empty)? {
+            b't' => match Transaction::deserialize(bytes) {
+                Some((transaction, size)) if size == bytes.len() => {
+                    Message::Transaction(Cow::Owned(transaction))
+                }
+                _ => return Err(MessageError::InvalidTransaction),
+            },
+            b'b' => Message::Block(Cow::Owned(
+                Block::deserialize(bytes).ok_or(MessageError::InvalidBlock)?,
+            )),
+            b'v' => {
+                Message::Version(Version::deserialize(bytes).ok_or(MessageError::InvalidVersion)?)
+            }
+            b'a' => Message::Verack,
+            b'g' => Message::GetBlockTemplate,
+            b'm' => Message::BlockTemplate(Cow::Owned(
+                BlockTemplate::deserialize(bytes).ok_or(MessageError::InvalidBlock)?,
+            )),
+            b'h' => Message::SubmitBlock(
+                BlockHeader::deserialize(&bytes[1..]).ok_or(MessageError::InvalidHeader)?,
+            ),
+            b'e' => Message::Evidence(
+                Evidence::deserialize(bytes).ok_or(MessageError::InvalidEvidence)?,
+            ),
+            b'c' => Message::Vote(Vote::deserialize(bytes).ok_or(MessageError::InvalidVote)?),
+            byte => return Err(MessageError::UnknownType(byte)),
+        };
+        Ok(message)
     }
 }
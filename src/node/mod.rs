@@ -1,39 +1,73 @@
 use log::{info, warn};
-use rand::seq::SliceRandom;
-use secp256k1::{PublicKey, SecretKey};
+use rand::seq::IteratorRandom;
+use rand::{Rng, RngCore};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
+use std::time::Instant;
 
 use self::behaviour::Behaviour;
 use self::message::Message;
+use self::version::Version;
+use crate::block::blockheader::BlockHeader;
 use crate::block::Block;
 use crate::blockchain::Blockchain;
+use crate::consensus::evidence::Evidence;
+use crate::consensus::finality::FinalityGadget;
+use crate::consensus::proof_of_authority::ProofOfAuthority;
+use crate::consensus::proof_of_stake::ProofOfStake;
+use crate::consensus::vote::Vote;
+use crate::consensus::ConsensusEngine;
+use crate::constants::{
+    COUNTERFEIT_PROBA, NETWORK_HASHRATE, PROTOCOL_VERSION, REBROADCAST_INTERVAL, SERVICE_MINING,
+    SERVICE_NETWORK, TX_POOL_EXPIRY,
+};
+use crate::error::block::BlockError;
+use crate::error::link::LinkError;
+use crate::error::utxo_pool::UtxoPoolError;
 use crate::error::Error;
+use crate::miner::selfish::SelfishMining;
+use crate::miner::template::BlockTemplate;
 use crate::miner::Miner;
-use crate::network::{Neighbour, Synchronizer};
-use crate::transaction::Transaction;
+use crate::network::eclipse::Attacker;
+use crate::network::link::Link;
+use crate::network::{Neighbour, Packet, Synchronizer};
+use crate::transaction::{Transaction, TransactionOutput};
+use crate::transaction_pool::orphan::OrphanPool;
 use crate::transaction_pool::TransactionPool;
 use crate::utxo_pool::UtxoPool;
 use crate::wallet::Wallet;
+use crate::Hash as BlockHash;
 
 pub struct Node {
     id: usize,
     public_key: PublicKey,
     secret_key: SecretKey,
-    sender: Sender<Arc<Vec<u8>>>,
-    listener: Receiver<Arc<Vec<u8>>>,
+    ephemeral_public_key: PublicKey,
+    ephemeral_secret_key: SecretKey,
+    sender: Sender<Packet>,
+    listener: Receiver<Packet>,
     neighbours: Vec<Neighbour>,
     blockchain: Blockchain,
     utxo_pool: UtxoPool,
     transaction_pool: TransactionPool,
+    orphans: OrphanPool,
     wallet: Wallet,
     miner: Miner,
+    selfish_mining: SelfishMining,
+    templates: HashMap<BlockHash, BlockTemplate>,
+    external_miner: Option<Sender<Packet>>,
+    finality: Option<FinalityGadget>,
     synchronizer: Synchronizer,
     integrity: Behaviour,
+    attacker: Option<Attacker>,
+    nonce: u64,
+    tips: Vec<(Instant, BlockHash)>,
 }
 
 impl Node {
@@ -41,8 +75,8 @@ impl Node {
         id: usize,
         public_key: PublicKey,
         secret_key: SecretKey,
-        sender: Sender<Arc<Vec<u8>>>,
-        listener: Receiver<Arc<Vec<u8>>>,
+        sender: Sender<Packet>,
+        listener: Receiver<Packet>,
         neighbours: Vec<Neighbour>,
         network_public_keys: Vec<PublicKey>,
         synchronizer: Synchronizer,
@@ -50,6 +84,12 @@ impl Node {
     ) -> Self {
         let utxo_pool = UtxoPool::initialize(network_public_keys.clone());
         let blockchain = Blockchain::new(utxo_pool.utxos().clone());
+        let tips = vec![(Instant::now(), *blockchain.top_hash())];
+        let mut ephemeral_secret_key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut ephemeral_secret_key);
+        let ephemeral_secret_key = SecretKey::from_slice(&ephemeral_secret_key).unwrap();
+        let ephemeral_public_key =
+            PublicKey::from_secret_key(&Secp256k1::new(), &ephemeral_secret_key);
         let wallet = Wallet::new(
             public_key,
             secret_key,
@@ -60,58 +100,610 @@ impl Node {
             id,
             public_key,
             secret_key,
+            ephemeral_public_key,
+            ephemeral_secret_key,
             sender,
             listener,
             neighbours,
             blockchain,
             utxo_pool,
             transaction_pool: TransactionPool::new(),
+            orphans: OrphanPool::new(TX_POOL_EXPIRY),
             wallet,
             miner: Miner::new(),
+            selfish_mining: SelfishMining::new(),
+            templates: HashMap::new(),
+            external_miner: None,
+            finality: None,
             synchronizer,
             integrity,
+            attacker: None,
+            nonce: rand::random(),
+            tips,
         }
     }
 
+    /// Makes the node one of the Sybil identities of the attacker
+    pub fn join(&mut self, attacker: Attacker) {
+        self.attacker = Some(attacker);
+    }
+
+    /// Lets an external miner drive the node instead of its own miner
+    ///
+    /// The node answers `Message::GetBlockTemplate` with a `Message::BlockTemplate` sent to
+    /// `sender` and accepts solved headers in `Message::SubmitBlock`.
+    pub fn connect_miner(&mut self, sender: Sender<Packet>) {
+        self.external_miner = Some(sender);
+    }
+
+    /// Replaces the consensus engine (proof of work by default)
+    pub fn set_consensus(&mut self, engine: Box<dyn ConsensusEngine>) {
+        self.miner.set_engine(engine);
+    }
+
+    /// Makes the node seal blocks in turn with the holders of `authorities`
+    pub fn set_proof_of_authority(&mut self, authorities: Vec<PublicKey>) {
+        let engine = ProofOfAuthority::new(authorities, self.public_key, self.secret_key);
+        self.set_consensus(Box::new(engine));
+    }
+
+    /// Makes the node seal blocks by proof of stake, the stakes being the coins of the utxo pool
+    pub fn set_proof_of_stake(&mut self) {
+        let engine = ProofOfStake::new(self.public_key, self.secret_key);
+        self.set_consensus(Box::new(engine));
+    }
+
+    /// Makes the node vote on checkpoints and never revert the blocks they finalize
+    pub fn set_finality(&mut self, finality: FinalityGadget) {
+        self.finality = Some(finality);
+    }
+
+    /// Throttles the miner to the share `hash_power` of `NETWORK_HASHRATE`
+    pub fn set_hash_power(&mut self, hash_power: f64) {
+        self.miner.throttle(Some(hash_power * NETWORK_HASHRATE));
+    }
+
+    /// Share of `NETWORK_HASHRATE` the miner is throttled to, if any
+    pub fn hash_power(&self) -> Option<f64> {
+        self.miner
+            .hashrate_limit()
+            .map(|hashrate| hashrate / NETWORK_HASHRATE)
+    }
+
     pub fn run(&mut self) {
+        self.greet();
         loop {
-            if let Some(transaction) = self.wallet.initiate() {
-                if self.transaction_pool.compatibility_of(&transaction).is_ok()
-                    && self.blockchain.check_txid_of(&transaction).is_ok()
-                {
-                    self.process_t(transaction);
+            if self.integrity != Behaviour::Sybil {
+                if let Some(transaction) = self.wallet.initiate() {
+                    if self.transaction_pool.compatibility_of(&transaction).is_ok()
+                        && self.blockchain.check_txid_of(&transaction).is_ok()
+                    {
+                        self.process_t(transaction);
+                    } else {
+                        self.release(&transaction);
+                    }
                 }
             }
-            if let Some(block) = self
-                .miner
-                .mine(self.blockchain.top(), &self.transaction_pool)
-            {
-                if self.blockchain.check_id_of(&block).is_ok() {
-                    self.process_b(block, vec![], vec![]);
-                }
+            for transaction in self.transaction_pool.expire() {
+                self.release(&transaction);
             }
-            if let Ok(bytes) = self.listener.try_recv() {
-                match Message::deserialize(bytes.deref()) {
-                    Message::Transaction(transaction) => {
-                        if self.verify(&transaction).is_ok() {
-                            self.process_t(transaction.into_owned())
-                        }
+            for transaction in self.wallet.rebroadcast(REBROADCAST_INTERVAL) {
+                self.rebroadcast(&transaction);
+            }
+            self.orphans.expire();
+            if self.is_mining() && self.external_miner.is_none() {
+                let (top, utxo_pool) = (self.blockchain.top(), &self.utxo_pool);
+                let block = match self.integrity {
+                    Behaviour::Malicious => {
+                        let wallet = &mut self.wallet;
+                        self.miner.mine_with(top, utxo_pool, || {
+                            wallet.double_spend().map(|(tx1, tx2)| vec![tx1, tx2])
+                        })
                     }
-                    Message::Block(block) => {
-                        if let Ok((blocks_to_undo, blocks_to_process)) = self.validate(&block) {
-                            self.process_b(block.into_owned(), blocks_to_undo, blocks_to_process)
+                    _ => self.miner.mine(top, utxo_pool, &self.transaction_pool),
+                };
+                if let Some(block) = block {
+                    if self.blockchain.check_id_of(&block).is_ok() {
+                        match self.integrity {
+                            Behaviour::Malicious => self.spend_twice(block),
+                            Behaviour::SelfishMiner => self.withhold(block),
+                            Behaviour::Sybil => self.forge(block),
+                            Behaviour::Equivocator => self.equivocate(block),
+                            _ => self.process_b(block, vec![], vec![]),
                         }
                     }
-                    Message::ShutDown => {
-                        self.shut_down();
-                        return;
-                    }
                 }
             }
-            if self.integrity == Behaviour::Malicious {
-                self.double_spend()
+            self.vote();
+            if let Ok(packet) = self.listener.try_recv() {
+                if self.handle(packet) {
+                    self.shut_down();
+                    return;
+                }
+            }
+            match self.integrity {
+                Behaviour::Malicious => self.double_spend(),
+                Behaviour::Counterfeiter => self.counterfeit(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Handles a packet and tells if it is a shut down message
+    ///
+    /// Packets from a neighbour are ignored until the handshake with it is complete, and dropped
+    /// if they fail to open on the link with it.
+    pub fn handle(&mut self, packet: Packet) -> bool {
+        let (origin, bytes) = packet;
+        if self.is_banned(origin) {
+            return false;
+        }
+        let bytes = match origin {
+            Some(origin) if !self.is_connected(origin) => {
+                self.handshake(origin, &bytes);
+                return false;
+            }
+            Some(origin) => match self.open(origin, &bytes) {
+                Ok(bytes) => Cow::Owned(bytes),
+                Err(err) => {
+                    self.penalize(Some(origin), &err);
+                    return false;
+                }
+            },
+            None => Cow::Borrowed(bytes.as_slice()),
+        };
+        let message = match Message::deserialize(bytes.deref()) {
+            Ok(message) => message,
+            Err(err) => {
+                self.penalize(origin, &err.into());
+                return false;
+            }
+        };
+        match message {
+            Message::Transaction(transaction) => self.handle_t(transaction.into_owned(), origin),
+            Message::Block(block) => self.handle_b(block.into_owned(), origin),
+            Message::GetBlockTemplate if origin.is_none() => self.serve_template(),
+            Message::SubmitBlock(header) if origin.is_none() => {
+                if let Err(err) = self.submit_block(&header) {
+                    warn!("Node #{} --- Rejected submitted block: {}\n", self.id, err);
+                }
             }
+            Message::Evidence(evidence) => self.handle_evidence(evidence, origin),
+            Message::Vote(vote) => self.handle_vote(vote, origin),
+            Message::Version(_)
+            | Message::Verack
+            | Message::GetBlockTemplate
+            | Message::BlockTemplate(_)
+            | Message::SubmitBlock(_) => {}
+            Message::ShutDown => return true,
         }
+        false
+    }
+
+    pub fn version(&self) -> Version {
+        let services = if self.is_mining() {
+            SERVICE_NETWORK | SERVICE_MINING
+        } else {
+            SERVICE_NETWORK
+        };
+        Version::new(
+            PROTOCOL_VERSION,
+            services,
+            self.blockchain.height(),
+            self.nonce,
+            self.blockchain.genesis().hash(),
+            self.ephemeral_public_key,
+        )
+    }
+
+    /// Introduces the node to its neighbours
+    pub fn greet(&self) {
+        let message = Message::Version(self.version());
+        for neighbour in &self.neighbours {
+            self.send(&message, neighbour);
+        }
+    }
+
+    /// Handles a packet from a neighbour the handshake is not complete with
+    ///
+    /// The version of the neighbour comes in the clear and establishes the link with it, over
+    /// which the verack is then exchanged. A neighbour whose verack fails to open does not hold
+    /// the node key it is known by and is refused.
+    pub fn handshake(&mut self, origin: usize, bytes: &[u8]) {
+        let version = self.version();
+        let (id, public_key, secret_key, ephemeral_key) = (
+            self.id,
+            self.public_key,
+            self.secret_key,
+            self.ephemeral_secret_key,
+        );
+        let neighbour = match self.neighbours.iter_mut().find(|n| n.id() == origin) {
+            Some(neighbour) if !neighbour.is_refused() => neighbour,
+            _ => return,
+        };
+        if neighbour.link().is_some() {
+            match neighbour.open(bytes) {
+                Ok(bytes) if bytes == Message::Verack.serialize() => neighbour.acknowledge(),
+                Ok(_) => {}
+                Err(err) => {
+                    warn!(
+                        "Node #{} --- Refused unauthenticated neighbour #{}: {}\n",
+                        id, origin, err
+                    );
+                    neighbour.refuse();
+                }
+            }
+        } else if bytes.first() == Some(&b'v') {
+            match Version::deserialize(bytes) {
+                Some(peer_version)
+                    if peer_version.version() == version.version()
+                        && peer_version.genesis() == version.genesis()
+                        && peer_version.nonce() != version.nonce() =>
+                {
+                    let link = Link::new(
+                        &public_key,
+                        &secret_key,
+                        &ephemeral_key,
+                        neighbour.public_key(),
+                        peer_version.ephemeral_key(),
+                    );
+                    neighbour.accept(peer_version);
+                    neighbour.establish(link);
+                    let bytes = neighbour.seal(&Message::Verack.serialize());
+                    neighbour
+                        .sender()
+                        .send((Some(id), Arc::new(bytes)))
+                        .unwrap();
+                }
+                _ => {
+                    warn!(
+                        "Node #{} --- Refused incompatible neighbour #{}\n",
+                        id, origin
+                    );
+                    neighbour.refuse();
+                }
+            }
+        }
+        if neighbour.is_connected() {
+            info!("Node #{} --- Connected to neighbour #{}\n", self.id, origin);
+            let neighbour = self.neighbours.iter().find(|n| n.id() == origin).unwrap();
+            self.synchronize(neighbour);
+        }
+    }
+
+    /// Sends the main chain to a neighbour that is behind, then the unconfirmed transactions
+    /// propagated before the neighbour was connected
+    pub fn synchronize(&self, neighbour: &Neighbour) {
+        let best_height = neighbour.version().map_or(0, |v| v.best_height());
+        for block in self.blockchain.main_chain().into_iter().rev() {
+            if block.height() > best_height {
+                self.send(&Message::Block(Cow::Borrowed(block)), neighbour);
+            }
+        }
+        for transaction in self.transaction_pool.transactions() {
+            self.send(&Message::Transaction(Cow::Borrowed(transaction)), neighbour);
+        }
+    }
+
+    /// Decrypts a packet from a neighbour
+    pub fn open(&mut self, origin: usize, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.neighbours.iter_mut().find(|n| n.id() == origin) {
+            Some(neighbour) => Ok(neighbour.open(bytes)?),
+            None => Err(LinkError::NotEstablished.into()),
+        }
+    }
+
+    /// Checks if the handshake with the neighbour is complete
+    pub fn is_connected(&self, id: usize) -> bool {
+        self.neighbours
+            .iter()
+            .any(|n| n.id() == id && n.is_connected())
+    }
+
+    /// Checks if the node mines (only the leader of the Sybil identities does)
+    pub fn is_mining(&self) -> bool {
+        self.attacker
+            .as_ref()
+            .is_none_or(|attacker| attacker.leader() == self.id)
+    }
+
+    /// Builds a block on top of the blockchain from transactions of the pool, to be solved by an
+    /// external miner
+    pub fn block_template(&mut self) -> Option<BlockTemplate> {
+        let transactions = self.transaction_pool.select()?;
+        let block = Block::new(self.blockchain.top(), transactions).unwrap();
+        let template = BlockTemplate::new(block);
+        self.templates
+            .insert(*template.header().hash_merkle_root(), template.clone());
+        Some(template)
+    }
+
+    /// Rebuilds the block of a template handed out from its solved header, then validates and
+    /// propagates it
+    pub fn submit_block(&mut self, header: &BlockHeader) -> Result<(), Error> {
+        let template = self
+            .templates
+            .get(header.hash_merkle_root())
+            .ok_or(BlockError::UnknownTemplate)?;
+        let parent = self
+            .blockchain
+            .chain()
+            .get(template.header().hash_prev_block())
+            .ok_or(BlockError::UnknownTemplate)?;
+        let block = template.solve(header, parent, &self.utxo_pool, self.miner.engine())?;
+        self.blockchain.check_id_of(&block)?;
+        let (blocks_to_undo, blocks_to_process) = self.validate(&block)?;
+        self.process_b(block, blocks_to_undo, blocks_to_process);
+        Ok(())
+    }
+
+    /// Forgets the templates built on blocks older than the parent of the top
+    ///
+    /// Templates one block behind still get accepted, their blocks forking the top, so that an
+    /// external miner racing a new block does not lose its work.
+    pub fn prune_templates(&mut self) {
+        let height = self.blockchain.height();
+        self.templates
+            .retain(|_, template| template.height() >= height);
+    }
+
+    /// Sends a block template to the external miner, if there is one and enough transactions
+    pub fn serve_template(&mut self) {
+        if self.external_miner.is_none() {
+            return;
+        }
+        if let Some(template) = self.block_template() {
+            let bytes = Message::BlockTemplate(Cow::Owned(template)).serialize();
+            let sender = self.external_miner.as_ref().unwrap();
+            if sender.send((Some(self.id), Arc::new(bytes))).is_err() {
+                self.external_miner = None;
+            }
+        }
+    }
+
+    /// Handles a transaction, keeping it aside as an orphan if it spends unknown utxos
+    pub fn handle_t(&mut self, transaction: Transaction, origin: Option<usize>) {
+        match self.verify(&transaction) {
+            Err(Error::UtxoPool(UtxoPoolError::TransactionHasUnknownUtxo)) => {
+                if self.orphans.add(transaction.clone()) {
+                    info!(
+                        "Node #{} --- Received orphan transaction:\n{}\n",
+                        self.id, transaction
+                    );
+                }
+                return;
+            }
+            Err(err) => {
+                self.penalize(origin, &err);
+                return;
+            }
+            Ok(()) => {}
+        }
+        match self.integrity {
+            Behaviour::Sybil => self.hide_t(transaction.clone()),
+            _ => self.process_t(transaction.clone()),
+        }
+        self.adopt_orphans_of(&transaction);
+    }
+
+    /// Handles again the orphans spending the outputs of a transaction that showed up
+    pub fn adopt_orphans_of(&mut self, transaction: &Transaction) {
+        for child in self.orphans.take_children_of(transaction) {
+            self.handle_t(child, None);
+        }
+    }
+
+    pub fn handle_b(&mut self, block: Block, origin: Option<usize>) {
+        if let Some(attacker) = self.attacker.as_ref() {
+            let extends_attack = self
+                .blockchain
+                .get_parent_of(&block)
+                .is_some_and(|parent| attacker.owns_chain_of(parent, &self.blockchain));
+            if !attacker.owns(&block) && !extends_attack {
+                return;
+            }
+        }
+        match self.validate(&block) {
+            Ok((blocks_to_undo, blocks_to_process)) => {
+                let evidence = self.miner.engine_mut().check_equivocation(&block);
+                self.receive_b(block, blocks_to_undo, blocks_to_process);
+                if let Some(evidence) = evidence {
+                    self.handle_evidence(evidence, None);
+                }
+            }
+            Err(err) => self.penalize(origin, &err),
+        }
+    }
+
+    /// Slashes the sealer of two blocks for the same slot and relays the evidence
+    pub fn handle_evidence(&mut self, evidence: Evidence, origin: Option<usize>) {
+        match self.miner.engine_mut().punish(&evidence) {
+            Ok(true) => {
+                warn!(
+                    "Node #{} --- Slashed equivocating validator at height {}\n",
+                    self.id,
+                    evidence.height()
+                );
+                self.propagate(Message::Evidence(evidence));
+                self.reconsider_top();
+            }
+            Ok(false) => {}
+            Err(err) => self.penalize(origin, &err.into()),
+        }
+    }
+
+    /// Counts a checkpoint vote, relaying it and finalizing the checkpoint it completes if any
+    pub fn handle_vote(&mut self, vote: Vote, origin: Option<usize>) {
+        let finality = match self.finality.as_mut() {
+            Some(finality) => finality,
+            None => return,
+        };
+        match finality.add(&vote) {
+            Ok(true) => {
+                if finality.is_equivocator(vote.public_key()) {
+                    warn!(
+                        "Node #{} --- Validator voted twice at height {}\n",
+                        self.id,
+                        vote.height()
+                    );
+                }
+                self.propagate(Message::Vote(vote));
+                self.finalize();
+            }
+            Ok(false) => {}
+            Err(err) => self.penalize(origin, &err.into()),
+        }
+    }
+
+    /// Votes for the main chain block at the last checkpoint buried under a whole interval
+    ///
+    /// The node does not vote for a checkpoint conflicting with its last vote, unless a later
+    /// checkpoint was finalized since or its last vote can no longer gather a quorum.
+    pub fn vote(&mut self) {
+        let finality = match self.finality.as_ref() {
+            Some(finality) => finality,
+            None => return,
+        };
+        let interval = finality.interval();
+        let height = self.blockchain.height().saturating_sub(interval) / interval * interval;
+        if height <= self.blockchain.finalized().height() {
+            return;
+        }
+        let checkpoint = self
+            .blockchain
+            .ancestor_at(self.blockchain.top(), height)
+            .unwrap();
+        if let Some(last_vote) = finality.last_vote_of(&self.public_key) {
+            if last_vote.height() >= height {
+                return;
+            }
+            let is_locked = last_vote.height() > self.blockchain.finalized().height()
+                && finality.can_finalize(last_vote.height(), last_vote.hash())
+                && self
+                    .blockchain
+                    .chain()
+                    .get(last_vote.hash())
+                    .is_none_or(|block| !self.blockchain.descends_from(checkpoint, block));
+            if is_locked {
+                return;
+            }
+        }
+        let vote = Vote::sign(height, checkpoint.hash(), &self.secret_key);
+        info!("Node #{} --- Voting at height {}\n", self.id, height);
+        if self.integrity == Behaviour::Equivocator {
+            self.equivocate_vote(vote);
+        } else {
+            self.handle_vote(vote, None);
+        }
+    }
+
+    /// Votes for a second block at the same checkpoint and sends each vote to half of the
+    /// neighbours
+    pub fn equivocate_vote(&mut self, vote: Vote) {
+        let mut hash = BlockHash::default();
+        rand::thread_rng().fill_bytes(&mut hash);
+        let twin = Vote::sign(vote.height(), hash, &self.secret_key);
+        warn!(
+            "Node #{} --- Voting twice at height {}\n",
+            self.id,
+            vote.height()
+        );
+        self.send_halves(&[Message::Vote(vote.clone()), Message::Vote(twin)]);
+        if let Some(finality) = self.finality.as_mut() {
+            finality.add(&vote).unwrap();
+        }
+    }
+
+    /// Finalizes the highest checkpoint voted for by a quorum whose block the node has
+    pub fn finalize(&mut self) {
+        let finality = match self.finality.as_ref() {
+            Some(finality) => finality,
+            None => return,
+        };
+        let finalized = self.blockchain.finalized().height();
+        let checkpoint = finality
+            .checkpoints()
+            .into_iter()
+            .take_while(|&(height, _)| height > finalized)
+            .find(|(_, hash)| self.blockchain.chain().contains_key(hash));
+        if let Some((height, hash)) = checkpoint {
+            match self.blockchain.finalize(hash) {
+                Ok(()) => {
+                    info!(
+                        "Node #{} --- Finalized block at height {}\n",
+                        self.id, height
+                    );
+                    self.reconsider_top();
+                }
+                Err(err) => warn!("Node #{} --- {}\n", self.id, err),
+            }
+        }
+    }
+
+    /// Moves the top of the blockchain to the best block descending from the finalized one
+    /// according to the fork choice rule, e.g. after a validator got slashed or a checkpoint got
+    /// finalized
+    pub fn reconsider_top(&mut self) {
+        let engine = self.miner.engine();
+        let blockchain = &self.blockchain;
+        let top = blockchain.top();
+        let finalized = blockchain.finalized();
+        let start = if blockchain.descends_from(top, finalized) {
+            top
+        } else {
+            finalized
+        };
+        let best = blockchain
+            .chain()
+            .values()
+            .filter(|block| blockchain.descends_from(block, finalized))
+            .fold(start, |best, block| {
+                if engine.fork_choice(best, block) {
+                    block
+                } else {
+                    best
+                }
+            });
+        if best.hash() == top.hash() {
+            return;
+        }
+        let best = best.clone();
+        let (blocks_to_undo, blocks_to_process) = blockchain.path(top, &best);
+        if self.recalculate(blocks_to_undo, blocks_to_process).is_err() {
+            return;
+        }
+        self.blockchain.set_top(best.hash()).unwrap();
+        self.miner.discard_block();
+        self.prune_templates();
+        self.tips.push((Instant::now(), best.hash()));
+    }
+
+    /// Adds to the misbehaviour score of the neighbour that sent data failing with the error
+    pub fn penalize(&mut self, origin: Option<usize>, err: &Error) {
+        let score = err.misbehaviour_score();
+        if score == 0 {
+            return;
+        }
+        let id = self.id;
+        if let Some(neighbour) = self.neighbours.iter_mut().find(|n| Some(n.id()) == origin) {
+            warn!(
+                "Node #{} --- Neighbour #{} misbehaved (score +{}): {}\n",
+                id,
+                neighbour.id(),
+                score,
+                err
+            );
+            if neighbour.misbehave(score) {
+                warn!("Node #{} --- Banned neighbour #{}\n", id, neighbour.id());
+            }
+        }
+    }
+
+    /// Checks if the packet comes from a banned neighbour
+    pub fn is_banned(&self, origin: Option<usize>) -> bool {
+        self.neighbours
+            .iter()
+            .any(|n| Some(n.id()) == origin && n.is_banned())
     }
 
     pub fn process_t(&mut self, transaction: Transaction) {
@@ -119,18 +711,95 @@ impl Node {
             "Node #{} --- Received new transaction:\n{}\n",
             self.id, transaction
         );
-        self.propagate(Message::Transaction(Cow::Borrowed(&transaction)));
-        self.transaction_pool.add(transaction).unwrap();
+        if self.add_t(transaction.clone()) {
+            self.propagate(Message::Transaction(Cow::Borrowed(&transaction)));
+        }
     }
 
+    /// Keeps a transaction from the victim and the honest nodes by only relaying it to the other
+    /// Sybil identities
+    pub fn hide_t(&mut self, transaction: Transaction) {
+        info!(
+            "Node #{} --- Hiding new transaction:\n{}\n",
+            self.id, transaction
+        );
+        if let Some(attacker) = self.attacker.as_ref() {
+            let message = Message::Transaction(Cow::Borrowed(&transaction));
+            for neighbour in self.neighbours.iter().filter(|n| n.is_connected()) {
+                if attacker.is_identity(neighbour.id()) {
+                    self.send(&message, neighbour);
+                }
+            }
+        }
+        self.add_t(transaction);
+    }
+
+    /// Adds a transaction to the pool and tells if it was accepted
+    ///
+    /// The transactions evicted to make room for it, or itself if the pool is full, are released
+    /// from the wallet.
+    pub fn add_t(&mut self, transaction: Transaction) -> bool {
+        let fee = self
+            .utxo_pool
+            .fee_of(&transaction)
+            .or_else(|| {
+                self.transaction_pool
+                    .utxo_view_of(&self.utxo_pool)
+                    .fee_of(&transaction)
+            })
+            .unwrap_or(0);
+        match self.transaction_pool.add(transaction.clone(), fee) {
+            Ok(evicted) => {
+                for transaction in &evicted {
+                    self.release(transaction);
+                }
+                true
+            }
+            Err(err) => {
+                info!("Node #{} --- Transaction rejected: {}", self.id, err);
+                self.release(&transaction);
+                false
+            }
+        }
+    }
+
+    /// Releases the inputs of a transaction of the wallet that left the pool without being mined
+    pub fn release(&mut self, transaction: &Transaction) {
+        if self.wallet.release(transaction) {
+            info!(
+                "Node #{} --- Released transaction:\n{}\n",
+                self.id, transaction
+            );
+        }
+    }
+
+    /// Broadcasts a pending transaction of the wallet again if it is still in the pool, and
+    /// abandons it otherwise as it will not be mined
+    pub fn rebroadcast(&mut self, transaction: &Transaction) {
+        if self.transaction_pool.transactions().contains(transaction) {
+            self.propagate(Message::Transaction(Cow::Borrowed(transaction)));
+        } else if self.wallet.abandon(transaction) {
+            info!(
+                "Node #{} --- Abandoned transaction:\n{}\n",
+                self.id, transaction
+            );
+        }
+    }
+
+    /// Checks a transaction against the utxos of the blockchain and of the transaction pool
+    ///
+    /// A transaction conflicting with some of the pool is only valid if it pays enough to
+    /// replace them.
     pub fn verify(&self, transaction: &Transaction) -> Result<(), Error> {
         transaction.has_inputs_and_outputs()?;
         transaction.check_double_spending()?;
-        self.transaction_pool.compatibility_of(&transaction)?;
         self.blockchain.check_txid_of(transaction)?;
-        self.utxo_pool.check_utxos_exist_for(&transaction)?;
-        self.utxo_pool.check_balance_of(transaction)?;
-        self.utxo_pool.authenticate(&transaction)?;
+        let utxo_pool = self.transaction_pool.utxo_view_of(&self.utxo_pool);
+        utxo_pool.check_utxos_exist_for(&transaction)?;
+        utxo_pool.check_balance_of(transaction)?;
+        utxo_pool.authenticate(&transaction)?;
+        let fee = utxo_pool.fee_of(transaction).unwrap();
+        self.transaction_pool.check_replacement(transaction, fee)?;
         Ok(())
     }
 
@@ -142,78 +811,217 @@ impl Node {
     ) {
         info!("Node #{} --- Received new block:\n{}\n", self.id, block);
         self.propagate(Message::Block(Cow::Borrowed(&block)));
-        if block.height() > self.blockchain.height() {
-            if block.hash_prev_block() != self.blockchain.top_hash() {
-                self.recalculate(blocks_to_undo, blocks_to_process);
-            }
+        self.store_b(block, blocks_to_undo, blocks_to_process);
+    }
+
+    /// Adds a block to the blockchain and updates the node state without propagating it
+    pub fn store_b(
+        &mut self,
+        block: Block,
+        blocks_to_undo: Vec<Block>,
+        blocks_to_process: Vec<Block>,
+    ) {
+        let is_top = self
+            .miner
+            .engine()
+            .fork_choice(self.blockchain.top(), &block)
+            && (block.hash_prev_block() == self.blockchain.top_hash()
+                || self.recalculate(blocks_to_undo, blocks_to_process).is_ok());
+        if is_top {
             self.utxo_pool.process(&block);
             self.wallet.process(&block);
             self.transaction_pool.process(&block);
             self.miner.discard_block();
         }
-        self.blockchain.push(block).unwrap();
+        self.blockchain.insert(block, is_top).unwrap();
+        if is_top {
+            self.prune_templates();
+            for transaction in self.blockchain.top().transactions().clone() {
+                self.adopt_orphans_of(&transaction);
+            }
+        }
+        if self.tips.last().map(|(_, hash)| hash) != Some(self.blockchain.top_hash()) {
+            self.tips
+                .push((Instant::now(), *self.blockchain.top_hash()));
+        }
+        self.finalize();
+    }
+
+    /// Processes a block received from a neighbour
+    ///
+    /// A selfish miner does not relay it, so as not to help the honest chain propagate, and may
+    /// publish some of its withheld blocks in response.
+    pub fn receive_b(
+        &mut self,
+        block: Block,
+        blocks_to_undo: Vec<Block>,
+        blocks_to_process: Vec<Block>,
+    ) {
+        if self.integrity != Behaviour::SelfishMiner {
+            self.process_b(block, blocks_to_undo, blocks_to_process);
+            return;
+        }
+        let private_height = self.blockchain.height();
+        let height = block.height();
+        self.store_b(block, blocks_to_undo, blocks_to_process);
+        let blocks = self.selfish_mining.on_public_block(height, private_height);
+        self.publish(blocks);
+    }
+
+    /// Keeps a block found by a selfish miner on its private branch
+    pub fn withhold(&mut self, block: Block) {
+        info!("Node #{} --- Withholding new block:\n{}\n", self.id, block);
+        let private_height = self.blockchain.height();
+        self.store_b(block.clone(), vec![], vec![]);
+        let blocks = self.selfish_mining.on_own_block(block, private_height);
+        self.publish(blocks);
+    }
+
+    /// Seals a second block for the same slot and sends each block to half of the neighbours
+    pub fn equivocate(&mut self, block: Block) {
+        let mut twin = block.clone();
+        twin.set_extra_nonce(block.extra_nonce().wrapping_add(1));
+        let engine = self.miner.engine_mut();
+        if let Some(parent) = self.blockchain.get_parent_of(&block) {
+            engine.start(parent, &twin, &self.utxo_pool);
+        }
+        if let Some(twin) = engine.seal() {
+            warn!(
+                "Node #{} --- Equivocating at height {}\n",
+                self.id,
+                block.height()
+            );
+            self.send_halves(&[
+                Message::Block(Cow::Borrowed(&block)),
+                Message::Block(Cow::Borrowed(&twin)),
+            ]);
+            self.store_b(block, vec![], vec![]);
+        } else {
+            self.process_b(block, vec![], vec![]);
+        }
+    }
+
+    /// Sends two conflicting messages each to half of the connected neighbours, or both to a
+    /// lone neighbour
+    fn send_halves(&self, messages: &[Message; 2]) {
+        let neighbours: Vec<_> = self
+            .neighbours
+            .iter()
+            .filter(|n| n.is_connected() && !n.is_banned())
+            .collect();
+        for (i, neighbour) in neighbours.iter().enumerate() {
+            if neighbours.len() == 1 {
+                self.send(&messages[0], neighbour);
+                self.send(&messages[1], neighbour);
+            } else {
+                self.send(&messages[i % 2], neighbour);
+            }
+        }
+    }
+
+    /// Adds a block mined by the attacker to its fake chain
+    pub fn forge(&mut self, block: Block) {
+        if let Some(attacker) = self.attacker.as_ref() {
+            attacker.add(&block);
+        }
+        self.process_b(block, vec![], vec![]);
+    }
+
+    pub fn publish(&self, blocks: Vec<Block>) {
+        for block in blocks {
+            warn!(
+                "Node #{} --- Publishing withheld block:\n{}\n",
+                self.id, block
+            );
+            self.propagate(Message::Block(Cow::Borrowed(&block)));
+        }
     }
 
     pub fn validate(&mut self, block: &Block) -> Result<(Vec<Block>, Vec<Block>), Error> {
         self.blockchain.check_id_of(block)?;
         self.blockchain.check_txids_of(block)?;
+        let parent = self.blockchain.parent_of(block)?;
         block.check_transaction_count_is_power_of_two()?;
         block.check_double_spending()?;
-        let parent = self.blockchain.parent_of(block)?;
         let (blocks_to_undo, blocks_to_process) =
             self.blockchain.path(self.blockchain.top(), parent);
         self.utxo_pool
             .recalculate(&blocks_to_undo, &blocks_to_process, &self.blockchain);
-        self.utxo_pool.check_utxos_exist(block)?;
-        self.utxo_pool.check_signatures_of(block)?;
+        let utxo_pool = &self.utxo_pool;
+        let result = self
+            .miner
+            .engine()
+            .verify_seal(parent, block, utxo_pool)
+            .map_err(Error::from)
+            .and_then(|()| Ok(utxo_pool.check_utxos_exist(block)?))
+            .and_then(|()| Ok(utxo_pool.check_signatures_of(block)?));
         self.utxo_pool
             .recalculate(&blocks_to_process, &blocks_to_undo, &self.blockchain);
+        result?;
         Ok((blocks_to_undo, blocks_to_process))
     }
 
-    pub fn recalculate(&mut self, blocks_to_undo: Vec<Block>, blocks_to_process: Vec<Block>) {
+    /// Moves the node state to another branch, failing if that would revert the finalized block
+    pub fn recalculate(
+        &mut self,
+        blocks_to_undo: Vec<Block>,
+        blocks_to_process: Vec<Block>,
+    ) -> Result<(), Error> {
+        self.blockchain.check_reversible(&blocks_to_undo)?;
         self.utxo_pool
             .recalculate(&blocks_to_undo, &blocks_to_process, &self.blockchain);
         self.wallet
             .recalculate(&blocks_to_undo, &blocks_to_process, &self.blockchain);
         self.transaction_pool
             .recalculate(blocks_to_undo, &self.blockchain, &self.utxo_pool);
+        Ok(())
     }
 
+    /// Sends a message to the connected neighbours, only the targets of the attacker for a Sybil
+    /// identity
     pub fn propagate(&self, message: Message) {
-        let bytes = Arc::new(message.serialize());
-        for neighbour in self.neighbours.iter() {
-            neighbour.sender().send(Arc::clone(&bytes)).unwrap();
+        let bytes = message.serialize();
+        let targets = |id| {
+            self.attacker
+                .as_ref()
+                .is_none_or(|attacker| attacker.targets(id))
+        };
+        for neighbour in self
+            .neighbours
+            .iter()
+            .filter(|n| n.is_connected() && !n.is_banned() && targets(n.id()))
+        {
+            neighbour
+                .sender()
+                .send((Some(self.id), Arc::new(neighbour.seal(&bytes))))
+                .unwrap();
         }
     }
 
+    /// Sends a message to a neighbour, sealed on the link with it if there is one
     pub fn send(&self, message: &Message, neighbour: &Neighbour) {
-        let bytes = Arc::new(message.serialize());
-        neighbour.sender().send(Arc::clone(&bytes)).unwrap();
+        let bytes = neighbour.seal(&message.serialize());
+        neighbour
+            .sender()
+            .send((Some(self.id), Arc::new(bytes)))
+            .unwrap();
     }
 
     pub fn shut_down(&mut self) {
+        self.miner.discard_block();
         info!(
-            "Node {} shutting down\nPublic key: {}\n",
-            self.id, self.public_key,
+            "Node {} shutting down\nPublic key: {}\nHashrate: {:.0} H/s\n",
+            self.id,
+            self.public_key,
+            self.miner.hashrate(),
         );
         self.synchronizer.barrier().wait();
         loop {
             let state = self.synchronizer.state();
             let mut state = state.lock().unwrap();
-            while let Ok(bytes) = self.listener.try_recv() {
-                match Message::deserialize(bytes.deref()) {
-                    Message::Transaction(transaction) => {
-                        if self.verify(&transaction).is_ok() {
-                            self.process_t(transaction.into_owned())
-                        }
-                    }
-                    Message::Block(block) => {
-                        if let Ok((blocks_to_undo, blocks_to_process)) = self.validate(&block) {
-                            self.process_b(block.into_owned(), blocks_to_undo, blocks_to_process)
-                        }
-                    }
-                    Message::ShutDown => panic!("Unexpected shut down message"),
+            while let Ok(packet) = self.listener.try_recv() {
+                if self.handle(packet) {
+                    panic!("Unexpected shut down message");
                 }
                 for neighbour in self.neighbours.iter().map(|n| n.id()) {
                     state[neighbour] = true;
@@ -228,8 +1036,13 @@ impl Node {
 
     pub fn double_spend(&mut self) {
         let mut rng = rand::thread_rng();
-        let mut neighbours = self.neighbours.choose_multiple(&mut rng, 2);
-        if neighbours.len() == 1 {
+        let mut neighbours = self
+            .neighbours
+            .iter()
+            .filter(|n| n.is_connected())
+            .choose_multiple(&mut rng, 2)
+            .into_iter();
+        if neighbours.len() < 2 {
             return;
         }
         if let Some((tx1, tx2)) = self.wallet.double_spend() {
@@ -254,8 +1067,48 @@ impl Node {
                 let msg2 = Message::Transaction(Cow::Borrowed(&tx2));
                 self.send(&msg1, neighbour1);
                 self.send(&msg2, neighbour2);
-                self.transaction_pool.add(tx1).unwrap();
+                self.add_t(tx1);
+            }
+        }
+    }
+
+    /// Sends a block spending the same utxo twice to the neighbours, without adding it to the
+    /// blockchain as it is invalid
+    pub fn spend_twice(&self, block: Block) {
+        warn!(
+            "Node #{} --- Double spend --- New block:\n{}\n",
+            self.id, block
+        );
+        self.propagate(Message::Block(Cow::Borrowed(&block)));
+    }
+
+    /// Relays a transaction spending someone else's utxo, hence carrying an invalid signature
+    pub fn counterfeit(&mut self) {
+        let mut rng = rand::thread_rng();
+        if !rng.gen_bool(COUNTERFEIT_PROBA) {
+            return;
+        }
+        if let Some((utxo_id, utxo_data)) = self
+            .utxo_pool
+            .utxos()
+            .iter()
+            .filter(|(_, data)| !data.pays(&self.public_key))
+            .choose(&mut rng)
+        {
+            let output = TransactionOutput::new(utxo_data.amount(), self.public_key);
+            let transaction = Transaction::sign(vec![*utxo_id], vec![output], &self.secret_key);
+            if self
+                .transaction_pool
+                .compatibility_of(&transaction)
+                .is_err()
+            {
+                return;
             }
+            warn!(
+                "Node #{} --- Counterfeit --- New transaction:\n{}\n",
+                self.id, transaction
+            );
+            self.propagate(Message::Transaction(Cow::Borrowed(&transaction)));
         }
     }
 
@@ -267,15 +1120,11 @@ impl Node {
         &self.public_key
     }
 
-    pub fn secret_key(&self) -> &SecretKey {
-        &self.secret_key
-    }
-
-    pub fn sender(&self) -> &Sender<Arc<Vec<u8>>> {
+    pub fn sender(&self) -> &Sender<Packet> {
         &self.sender
     }
 
-    pub fn listener(&self) -> &Receiver<Arc<Vec<u8>>> {
+    pub fn listener(&self) -> &Receiver<Packet> {
         &self.listener
     }
 
@@ -283,6 +1132,14 @@ impl Node {
         &self.neighbours
     }
 
+    pub fn miner(&self) -> &Miner {
+        &self.miner
+    }
+
+    pub fn selfish_mining(&self) -> &SelfishMining {
+        &self.selfish_mining
+    }
+
     pub fn utxo_pool(&self) -> &UtxoPool {
         &self.utxo_pool
     }
@@ -291,6 +1148,10 @@ impl Node {
         &self.transaction_pool
     }
 
+    pub fn orphans(&self) -> &OrphanPool {
+        &self.orphans
+    }
+
     pub fn wallet(&self) -> &Wallet {
         &self.wallet
     }
@@ -299,9 +1160,22 @@ impl Node {
         &self.blockchain
     }
 
+    pub fn finality(&self) -> Option<&FinalityGadget> {
+        self.finality.as_ref()
+    }
+
     pub fn integrity(&self) -> Behaviour {
         self.integrity
     }
+
+    pub fn attacker(&self) -> Option<&Attacker> {
+        self.attacker.as_ref()
+    }
+
+    /// Successive tops of the blockchain with the time they were adopted
+    pub fn tips(&self) -> &Vec<(Instant, BlockHash)> {
+        &self.tips
+    }
 }
 
 impl Eq for Node {}
@@ -342,3 +1216,4 @@ impl fmt::Display for Node {
 
 pub mod behaviour;
 pub mod message;
+pub mod version;
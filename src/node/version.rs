@@ -0,0 +1,125 @@
+use secp256k1::PublicKey;
+use std::convert::TryInto;
+use std::fmt;
+use std::iter;
+
+use crate::constants::VERSION_BYTES;
+use crate::Hash as BlockHash;
+
+/// Introduction a node sends to each of its neighbours before exchanging anything else
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Version {
+    version: u32,
+    services: u64,
+    best_height: usize,
+    nonce: u64,
+    genesis: BlockHash,
+    ephemeral_key: PublicKey,
+}
+
+impl Version {
+    pub fn new(
+        version: u32,
+        services: u64,
+        best_height: usize,
+        nonce: u64,
+        genesis: BlockHash,
+        ephemeral_key: PublicKey,
+    ) -> Self {
+        Self {
+            version,
+            services,
+            best_height,
+            nonce,
+            genesis,
+            ephemeral_key,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        iter::once(b'v')
+            .chain(self.version.to_be_bytes().iter().copied())
+            .chain(self.services.to_be_bytes().iter().copied())
+            .chain((self.best_height as u64).to_be_bytes().iter().copied())
+            .chain(self.nonce.to_be_bytes().iter().copied())
+            .chain(self.genesis.iter().copied())
+            .chain(self.ephemeral_key.serialize().iter().copied())
+            .collect()
+    }
+
+    /// Deserializes a version message
+    ///
+    /// Unlike the other messages, a version may come from a peer speaking another protocol, so
+    /// malformed bytes are reported rather than trusted.
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        if bytes.len() != VERSION_BYTES || bytes[0] != b'v' {
+            return None;
+        }
+        let mut i = 1;
+        let version = u32::from_be_bytes(bytes[i..i + 4].try_into().unwrap());
+        i += 4;
+        let services = u64::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
+        i += 8;
+        let best_height = u64::from_be_bytes(bytes[i..i + 8].try_into().unwrap())
+            .try_into()
+            .ok()?;
+        i += 8;
+        let nonce = u64::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
+        i += 8;
+        let genesis = *BlockHash::from_slice(&bytes[i..i + 32]);
+        i += 32;
+        let ephemeral_key = PublicKey::from_slice(&bytes[i..i + 33]).ok()?;
+        Some(Self {
+            version,
+            services,
+            best_height,
+            nonce,
+            genesis,
+            ephemeral_key,
+        })
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn services(&self) -> u64 {
+        self.services
+    }
+
+    pub fn best_height(&self) -> usize {
+        self.best_height
+    }
+
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    pub fn genesis(&self) -> &BlockHash {
+        &self.genesis
+    }
+
+    /// Public key the link with the node is agreed on, fresh for each run
+    pub fn ephemeral_key(&self) -> &PublicKey {
+        &self.ephemeral_key
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Version {{\n  version: {}\n  services: {:#x}\n  best_height: {}\n  nonce: {}\n  genesis: {:x}\n  ephemeral_key: {}\n}}",
+            self.version,
+            self.services,
+            self.best_height,
+            self.nonce,
+            self.genesis,
+            self.ephemeral_key
+        )
+    }
+}
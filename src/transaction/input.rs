@@ -1,40 +1,61 @@
-use secp256k1::Signature;
+use secp256k1::PublicKey;
+use std::convert::TryInto;
 use std::fmt;
 
-use crate::constants::{TX_INPUT_BYTES, UTXO_ID_BYTES};
+use crate::constants::{SIGNATURE_BYTES, TX_INPUT_BYTES, UTXO_ID_BYTES};
 use crate::utxo::UtxoId;
 use crate::Hash;
 
+/// Spending of a utxo, revealing the public key its output pays the hash of, signed with the
+/// scheme of its output type
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TransactionInput {
     utxo_id: UtxoId,
-    sig: Signature,
+    public_key: PublicKey,
+    sig: [u8; SIGNATURE_BYTES],
 }
 
 impl TransactionInput {
-    pub fn new(utxo_id: UtxoId, sig: Signature) -> Self {
-        Self { utxo_id, sig }
+    pub fn new(utxo_id: UtxoId, public_key: PublicKey, sig: [u8; SIGNATURE_BYTES]) -> Self {
+        Self {
+            utxo_id,
+            public_key,
+            sig,
+        }
     }
 
     pub fn serialize(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(TX_INPUT_BYTES);
         bytes.extend(self.utxo_id.serialize());
-        bytes.extend(self.sig.serialize_compact().iter());
+        bytes.extend(self.public_key.serialize().iter());
+        bytes.extend(self.sig.iter());
         bytes
     }
 
-    pub fn deserialize<B>(bytes: B) -> Self
+    /// Deserializes bytes that may be malformed, e.g. sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
     where
         B: AsRef<[u8]>,
     {
-        Self::from(bytes)
+        let bytes = bytes.as_ref();
+        if bytes.len() != TX_INPUT_BYTES {
+            return None;
+        }
+        let utxo_id = UtxoId::deserialize(&bytes[..UTXO_ID_BYTES]);
+        let public_key = PublicKey::from_slice(&bytes[UTXO_ID_BYTES..UTXO_ID_BYTES + 33]).ok()?;
+        let sig = bytes[UTXO_ID_BYTES + 33..].try_into().unwrap();
+        Some(Self::new(utxo_id, public_key, sig))
     }
 
     pub fn utxo_id(&self) -> &UtxoId {
         &self.utxo_id
     }
 
-    pub fn sig(&self) -> &Signature {
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn sig(&self) -> &[u8; SIGNATURE_BYTES] {
         &self.sig
     }
 
@@ -47,26 +68,18 @@ impl TransactionInput {
     }
 }
 
-impl<B> From<B> for TransactionInput
-where
-    B: AsRef<[u8]>,
-{
-    fn from(bytes: B) -> Self {
-        let bytes = bytes.as_ref();
-        let utxo_id = UtxoId::deserialize(bytes);
-        let sig = Signature::from_compact(&bytes[UTXO_ID_BYTES..]).unwrap();
-        Self { utxo_id, sig }
-    }
-}
-
 impl fmt::Display for TransactionInput {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Transaction input {{\n  txid: {:x}\n  vout: {}\n  sig: {}\n}}",
+            "Transaction input {{\n  txid: {:x}\n  vout: {}\n  public_key: {}\n  sig: ",
             self.txid(),
             self.vout(),
-            self.sig
-        )
+            self.public_key,
+        )?;
+        for byte in self.sig.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "\n}}")
     }
 }
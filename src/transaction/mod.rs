@@ -1,16 +1,17 @@
 use merkle_cbt::merkle_tree::CBMT;
-use secp256k1::{Message as MessageToSign, Secp256k1, SecretKey};
+use secp256k1::{Message as MessageToSign, PublicKey, Secp256k1, SecretKey, Signature, VerifyOnly};
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::convert::TryInto;
 use std::fmt;
 use std::hash::{Hash as HashTrait, Hasher};
 use std::iter;
+use std::sync::OnceLock;
 
 use self::merkle_tree::MergeHash;
-use crate::constants::{TX_INPUT_BYTES, TX_OUTPUT_BYTES};
+use crate::constants::{SIGNATURE_BYTES, TX_INPUT_BYTES, TX_OUTPUT_BYTES};
 use crate::error::transaction::TransactionError;
-use crate::utxo::UtxoId;
+use crate::utxo::{OutputType, Utxo, UtxoData, UtxoId};
 use crate::Hash;
 
 pub use self::input::TransactionInput;
@@ -45,22 +46,53 @@ impl Transaction {
         outputs: Vec<TransactionOutput>,
         secret_key: &SecretKey,
     ) -> Self {
-        let mut message = Vec::new();
-        for utxo_id in &utxo_ids {
-            message.extend(utxo_id.serialize());
-        }
-        for output in &outputs {
-            message.extend(output.serialize());
-        }
-        let mut hasher = Sha256::new();
-        hasher.input(message);
-        let hash = hasher.result();
-        let message = MessageToSign::from_slice(&hash).unwrap();
+        let secret_keys = vec![*secret_key; utxo_ids.len()];
+        Self::sign_with(utxo_ids, outputs, &secret_keys)
+    }
+
+    /// Signs each input with the secret key at the same index, for inputs owned by different
+    /// keys
+    ///
+    /// Inputs are signed with ECDSA, see `sign_utxos` to spend outputs of other types.
+    pub fn sign_with(
+        utxo_ids: Vec<UtxoId>,
+        outputs: Vec<TransactionOutput>,
+        secret_keys: &[SecretKey],
+    ) -> Self {
+        assert_eq!(utxo_ids.len(), secret_keys.len());
+        let message = message_of(&utxo_ids, &outputs);
         let secp = Secp256k1::new();
-        let sig = secp.sign(&message, &secret_key);
         let inputs = utxo_ids
             .iter()
-            .map(|id| TransactionInput::new(*id, sig))
+            .zip(secret_keys)
+            .map(|(id, secret_key)| {
+                let public_key = PublicKey::from_secret_key(&secp, secret_key);
+                let sig = secp.sign(&message, secret_key).serialize_compact();
+                TransactionInput::new(*id, public_key, sig)
+            })
+            .collect();
+        Transaction::new(inputs, outputs)
+    }
+
+    /// Signs each input with the secret key at the same index, with the scheme of the output
+    /// type of its utxo
+    pub fn sign_utxos(
+        utxos: &[Utxo],
+        outputs: Vec<TransactionOutput>,
+        secret_keys: &[SecretKey],
+    ) -> Self {
+        assert_eq!(utxos.len(), secret_keys.len());
+        let utxo_ids: Vec<_> = utxos.iter().map(|utxo| *utxo.utxo_id()).collect();
+        let message = message_of(&utxo_ids, &outputs);
+        let secp = Secp256k1::new();
+        let inputs = utxos
+            .iter()
+            .zip(secret_keys)
+            .map(|(utxo, secret_key)| {
+                let public_key = PublicKey::from_secret_key(&secp, secret_key);
+                let sig = sign_message(&message, utxo.output_type(), secret_key);
+                TransactionInput::new(*utxo.utxo_id(), public_key, sig)
+            })
             .collect();
         Transaction::new(inputs, outputs)
     }
@@ -75,30 +107,36 @@ impl Transaction {
             .collect()
     }
 
-    pub fn deserialize<B>(bytes: B) -> (Self, usize)
+    /// Deserializes the transaction the bytes start with, along with its size, failing on
+    /// malformed bytes, e.g. sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<(Self, usize)>
     where
         B: AsRef<[u8]>,
     {
         let bytes = bytes.as_ref();
-        let mut i = 1;
-        let size = usize::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
-        i += 8;
-        let inputs_len = usize::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
-        i += 8;
-        let outputs_len = usize::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
-        i += 8;
-        let inputs = bytes[i..]
+        if bytes.len() < 1 + 3 * 8 || bytes[0] != b't' {
+            return None;
+        }
+        let len = |i: usize| usize::from_be_bytes(bytes[i..i + 8].try_into().unwrap());
+        let (size, inputs_len, outputs_len) = (len(1), len(1 + 8), len(1 + 2 * 8));
+        let inputs_bytes = inputs_len.checked_mul(TX_INPUT_BYTES)?;
+        let outputs_bytes = outputs_len.checked_mul(TX_OUTPUT_BYTES)?;
+        let expected_size = (1 + 3 * 8usize)
+            .checked_add(inputs_bytes)?
+            .checked_add(outputs_bytes)?;
+        if size != expected_size || size > bytes.len() {
+            return None;
+        }
+        let (inputs, outputs) = bytes[1 + 3 * 8..size].split_at(inputs_bytes);
+        let inputs = inputs
             .chunks_exact(TX_INPUT_BYTES)
-            .take(inputs_len)
             .map(|c| TransactionInput::deserialize(c))
-            .collect();
-        i += inputs_len * TX_INPUT_BYTES;
-        let outputs = bytes[i..]
+            .collect::<Option<_>>()?;
+        let outputs = outputs
             .chunks_exact(TX_OUTPUT_BYTES)
-            .take(outputs_len)
             .map(|c| TransactionOutput::deserialize(c))
-            .collect();
-        (Self::new(inputs, outputs), size)
+            .collect::<Option<_>>()?;
+        Some((Self::new(inputs, outputs), size))
     }
 
     pub fn hash_merkle_root(transactions: &Vec<Self>) -> Hash {
@@ -118,6 +156,11 @@ impl Transaction {
         false
     }
 
+    /// Tells if the transaction spends an output of another one, i.e. is its child
+    pub fn spends_output_of(&self, other: &Transaction) -> bool {
+        self.inputs().iter().any(|input| input.txid() == other.id())
+    }
+
     pub fn has_inputs_and_outputs(&self) -> Result<(), TransactionError> {
         if self.inputs.is_empty() {
             Err(TransactionError::NoInputs)
@@ -145,6 +188,12 @@ impl Transaction {
         &self.id
     }
 
+    /// Message signed by the inputs
+    pub fn message(&self) -> MessageToSign {
+        let utxo_ids: Vec<_> = self.inputs.iter().map(|input| *input.utxo_id()).collect();
+        message_of(&utxo_ids, &self.outputs)
+    }
+
     pub fn inputs(&self) -> &Vec<TransactionInput> {
         &self.inputs
     }
@@ -154,6 +203,62 @@ impl Transaction {
     }
 }
 
+/// Message signed by each input of a transaction, committing to all its inputs and outputs
+fn message_of(utxo_ids: &[UtxoId], outputs: &[TransactionOutput]) -> MessageToSign {
+    let mut message = Vec::new();
+    for utxo_id in utxo_ids {
+        message.extend(utxo_id.serialize());
+    }
+    for output in outputs {
+        message.extend(output.serialize());
+    }
+    let mut hasher = Sha256::new();
+    hasher.input(message);
+    let hash = hasher.result();
+    MessageToSign::from_slice(&hash).unwrap()
+}
+
+/// Signs a message with the scheme of an output type
+fn sign_message(
+    message: &MessageToSign,
+    output_type: OutputType,
+    secret_key: &SecretKey,
+) -> [u8; SIGNATURE_BYTES] {
+    match output_type {
+        OutputType::Ecdsa => Secp256k1::new()
+            .sign(message, secret_key)
+            .serialize_compact(),
+        OutputType::Schnorr => schnorr::sign(&message[..], secret_key),
+    }
+}
+
+/// Context shared by every ECDSA verification, whose creation precomputes tables
+fn verification_context() -> &'static Secp256k1<VerifyOnly> {
+    static SECP: OnceLock<Secp256k1<VerifyOnly>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::verification_only)
+}
+
+/// Tells if a signature of a message is valid for a public key paid by a utxo, with the scheme
+/// of its output type
+pub fn verify_message(
+    message: &MessageToSign,
+    public_key: &PublicKey,
+    sig: &[u8; SIGNATURE_BYTES],
+    utxo_data: &UtxoData,
+) -> bool {
+    if !utxo_data.pays(public_key) {
+        return false;
+    }
+    match utxo_data.output_type() {
+        OutputType::Ecdsa => Signature::from_compact(sig).is_ok_and(|sig| {
+            verification_context()
+                .verify(message, &sig, public_key)
+                .is_ok()
+        }),
+        OutputType::Schnorr => schnorr::verify(&message[..], sig, public_key),
+    }
+}
+
 impl Eq for Transaction {}
 
 impl PartialEq for Transaction {
@@ -177,9 +282,9 @@ impl fmt::Display for Transaction {
         for (o, output) in self.outputs().iter().enumerate() {
             write!(
                 f,
-                "  Output {}:  public_key: {}  amount: {}\n",
+                "  Output {}:  address: {}  amount: {}\n",
                 o,
-                output.public_key(),
+                output.address(),
                 output.amount(),
             )?;
         }
@@ -196,3 +301,5 @@ impl HashTrait for Transaction {
 pub mod input;
 pub mod merkle_tree;
 pub mod output;
+pub mod partial;
+pub mod schnorr;
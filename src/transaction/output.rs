@@ -1,25 +1,39 @@
 use secp256k1::PublicKey;
 use std::fmt;
 
-use crate::utxo::UtxoData;
+use crate::address::Address;
+use crate::utxo::{KeyHash, OutputType, UtxoData};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct TransactionOutput(UtxoData);
 
 impl TransactionOutput {
-    pub fn new(amount: u32, public_key: PublicKey) -> Self {
-        Self(UtxoData::new(amount, public_key))
+    /// Output paying an address, or a public key to be spent with ECDSA
+    pub fn new<A>(amount: u32, recipient: A) -> Self
+    where
+        A: Into<Address>,
+    {
+        let address = recipient.into();
+        Self(UtxoData::paying(
+            amount,
+            *address.key_hash(),
+            address.output_type(),
+        ))
+    }
+
+    pub fn with_type(amount: u32, public_key: PublicKey, output_type: OutputType) -> Self {
+        Self(UtxoData::with_type(amount, public_key, output_type))
     }
 
     pub fn serialize(&self) -> Vec<u8> {
         self.0.serialize()
     }
 
-    pub fn deserialize<B>(bytes: B) -> Self
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
     where
         B: AsRef<[u8]>,
     {
-        Self(UtxoData::deserialize(bytes))
+        UtxoData::deserialize(bytes).map(Self)
     }
 
     pub fn utxo_data(&self) -> &UtxoData {
@@ -30,8 +44,16 @@ impl TransactionOutput {
         self.0.amount()
     }
 
-    pub fn public_key(&self) -> &PublicKey {
-        &self.0.public_key()
+    pub fn key_hash(&self) -> &KeyHash {
+        self.0.key_hash()
+    }
+
+    pub fn output_type(&self) -> OutputType {
+        self.0.output_type()
+    }
+
+    pub fn address(&self) -> Address {
+        self.0.address()
     }
 }
 
@@ -45,9 +67,9 @@ impl fmt::Display for TransactionOutput {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Transaction output {{\n  amount: {}\n  pulic_key: {}\n}}",
+            "Transaction output {{\n  amount: {}\n  address: {}\n}}",
             self.amount(),
-            self.public_key()
+            self.address()
         )
     }
 }
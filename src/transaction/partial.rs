@@ -0,0 +1,213 @@
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use std::convert::TryInto;
+use std::iter;
+
+use super::TransactionOutput;
+use super::{message_of, sign_message, verify_message, Transaction, TransactionInput};
+use crate::constants::{SIGNATURE_BYTES, TX_OUTPUT_BYTES, UTXO_DATA_BYTES, UTXO_ID_BYTES};
+use crate::error::transaction::TransactionError;
+use crate::utxo::{Utxo, UtxoData, UtxoId};
+
+/// Transaction waiting for the signatures of the owners of its inputs
+///
+/// Each party signs the inputs it owns on its own copy, then the copies are combined and the
+/// transaction finalized once every input is signed. Inputs carry the utxos they spend, so that
+/// offline signers know the key hashes and amounts involved, and signatures the public keys they
+/// verify with.
+///
+/// The signed message commits to the ids of the spent utxos but not to their amounts or keys. A
+/// signer must therefore check the utxos against its own view of the chain: one that trusts the
+/// amounts carried here can be lied to about the fee it pays.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PartialTransaction {
+    inputs: Vec<Utxo>,
+    outputs: Vec<TransactionOutput>,
+    signatures: Vec<Option<(PublicKey, [u8; SIGNATURE_BYTES])>>,
+}
+
+impl PartialTransaction {
+    pub fn new(inputs: Vec<Utxo>, outputs: Vec<TransactionOutput>) -> Self {
+        let signatures = vec![None; inputs.len()];
+        Self {
+            inputs,
+            outputs,
+            signatures,
+        }
+    }
+
+    fn utxo_ids(&self) -> Vec<UtxoId> {
+        self.inputs.iter().map(|utxo| *utxo.utxo_id()).collect()
+    }
+
+    /// Signs the inputs spending utxos of the public key of a secret key, returning how many
+    pub fn sign(&mut self, secret_key: &SecretKey) -> usize {
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::from_secret_key(&secp, secret_key);
+        let message = message_of(&self.utxo_ids(), &self.outputs);
+        let mut signed = 0;
+        for (utxo, signature) in self.inputs.iter().zip(&mut self.signatures) {
+            if utxo.utxo_data().pays(&public_key) {
+                let sig = sign_message(&message, utxo.output_type(), secret_key);
+                *signature = Some((public_key, sig));
+                signed += 1;
+            }
+        }
+        signed
+    }
+
+    /// Adds the signature of an input, failing if it is not signed by the key its utxo pays
+    pub fn add_signature(
+        &mut self,
+        index: usize,
+        public_key: PublicKey,
+        signature: [u8; SIGNATURE_BYTES],
+    ) -> Result<(), TransactionError> {
+        let utxo = self
+            .inputs
+            .get(index)
+            .ok_or(TransactionError::InvalidPartialTransaction)?;
+        let message = message_of(&self.utxo_ids(), &self.outputs);
+        if !verify_message(&message, &public_key, &signature, utxo.utxo_data()) {
+            return Err(TransactionError::InvalidSignature);
+        }
+        self.signatures[index] = Some((public_key, signature));
+        Ok(())
+    }
+
+    /// Adds the signatures of another copy of the same transaction
+    pub fn combine(&mut self, other: &Self) -> Result<(), TransactionError> {
+        if self.inputs != other.inputs || self.outputs != other.outputs {
+            return Err(TransactionError::MismatchedPartialTransactions);
+        }
+        for (signature, other) in self.signatures.iter_mut().zip(&other.signatures) {
+            if signature.is_none() {
+                *signature = *other;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures.iter().all(Option::is_some)
+    }
+
+    /// Utxos spent by the inputs not signed yet
+    pub fn unsigned(&self) -> Vec<&Utxo> {
+        self.inputs
+            .iter()
+            .zip(&self.signatures)
+            .filter(|(_, signature)| signature.is_none())
+            .map(|(utxo, _)| utxo)
+            .collect()
+    }
+
+    /// Transaction with the signatures of every input
+    pub fn finalize(self) -> Result<Transaction, TransactionError> {
+        let inputs = self
+            .inputs
+            .iter()
+            .zip(self.signatures)
+            .map(|(utxo, signature)| {
+                let (public_key, signature) =
+                    signature.ok_or(TransactionError::MissingSignatures)?;
+                Ok(TransactionInput::new(
+                    *utxo.utxo_id(),
+                    public_key,
+                    signature,
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Transaction::new(inputs, self.outputs))
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        iter::once(b'p')
+            .chain(self.inputs.len().to_be_bytes().iter().copied())
+            .chain(self.outputs.len().to_be_bytes().iter().copied())
+            .chain(
+                self.inputs
+                    .iter()
+                    .zip(&self.signatures)
+                    .flat_map(|(utxo, signature)| {
+                        utxo.utxo_id()
+                            .serialize()
+                            .into_iter()
+                            .chain(utxo.utxo_data().serialize())
+                            .chain(Some(signature.is_some() as u8))
+                            .chain(signature.iter().flat_map(|(public_key, signature)| {
+                                public_key
+                                    .serialize()
+                                    .iter()
+                                    .chain(signature.iter())
+                                    .copied()
+                                    .collect::<Vec<_>>()
+                            }))
+                            .collect::<Vec<_>>()
+                    }),
+            )
+            .chain(self.outputs.iter().flat_map(|o| o.serialize()))
+            .collect()
+    }
+
+    /// Deserializes a copy received from another party, failing on malformed bytes or invalid
+    /// signatures
+    pub fn deserialize<B>(bytes: B) -> Result<Self, TransactionError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let mut bytes = bytes.as_ref();
+        let mut take = |len: usize| {
+            if len > bytes.len() {
+                return Err(TransactionError::InvalidPartialTransaction);
+            }
+            let (taken, rest) = bytes.split_at(len);
+            bytes = rest;
+            Ok(taken)
+        };
+        let utxo_data = |bytes: &[u8]| {
+            UtxoData::deserialize(bytes).ok_or(TransactionError::InvalidPartialTransaction)
+        };
+        if take(1)? != b"p" {
+            return Err(TransactionError::InvalidPartialTransaction);
+        }
+        let inputs_len = usize::from_be_bytes(take(8)?.try_into().unwrap());
+        let outputs_len = usize::from_be_bytes(take(8)?.try_into().unwrap());
+        let mut inputs = Vec::new();
+        let mut signatures = Vec::new();
+        for _ in 0..inputs_len {
+            let utxo_id = UtxoId::deserialize(take(UTXO_ID_BYTES)?);
+            inputs.push(Utxo::new(utxo_id, utxo_data(take(UTXO_DATA_BYTES)?)?));
+            signatures.push(match take(1)?[0] {
+                0 => None,
+                1 => Some((
+                    PublicKey::from_slice(take(33)?)
+                        .map_err(|_| TransactionError::InvalidPartialTransaction)?,
+                    take(SIGNATURE_BYTES)?.try_into().unwrap(),
+                )),
+                _ => return Err(TransactionError::InvalidPartialTransaction),
+            });
+        }
+        let outputs = (0..outputs_len)
+            .map(|_| Ok(TransactionOutput::from(utxo_data(take(TX_OUTPUT_BYTES)?)?)))
+            .collect::<Result<_, _>>()?;
+        if !bytes.is_empty() {
+            return Err(TransactionError::InvalidPartialTransaction);
+        }
+
+        let mut partial = Self::new(inputs, outputs);
+        for (index, signature) in signatures.into_iter().enumerate() {
+            if let Some((public_key, signature)) = signature {
+                partial.add_signature(index, public_key, signature)?;
+            }
+        }
+        Ok(partial)
+    }
+
+    pub fn inputs(&self) -> &Vec<Utxo> {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &Vec<TransactionOutput> {
+        &self.outputs
+    }
+}
@@ -0,0 +1,91 @@
+use k256::elliptic_curve::bigint::U256;
+use k256::elliptic_curve::group::Group;
+use k256::elliptic_curve::ops::{LinearCombinationExt, Reduce};
+use k256::elliptic_curve::point::DecompactPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::schnorr::{SigningKey, VerifyingKey};
+use k256::{AffinePoint, FieldBytes, ProjectivePoint, Scalar};
+use rand::RngCore;
+use secp256k1::{PublicKey, SecretKey};
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+
+use crate::constants::SIGNATURE_BYTES;
+
+const CHALLENGE_TAG: &[u8] = b"BIP0340/challenge";
+
+/// Signs a message, under the x-coordinate of the public key (BIP340)
+pub fn sign(message: &[u8], secret_key: &SecretKey) -> [u8; SIGNATURE_BYTES] {
+    let mut aux_rand = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut aux_rand);
+    let signing_key = SigningKey::from_bytes(&secret_key[..]).unwrap();
+    let signature = signing_key.sign_raw(message, &aux_rand).unwrap();
+    signature.to_bytes()
+}
+
+pub fn verify(message: &[u8], signature: &[u8; SIGNATURE_BYTES], public_key: &PublicKey) -> bool {
+    let verifying_key = match VerifyingKey::from_bytes(&public_key.serialize()[1..]) {
+        Ok(verifying_key) => verifying_key,
+        Err(_) => return false,
+    };
+    match k256::schnorr::Signature::try_from(&signature[..]) {
+        Ok(signature) => verifying_key.verify_raw(message, &signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Point of even y-coordinate with the given x-coordinate
+fn lift_x(x: &[u8]) -> Option<ProjectivePoint> {
+    Option::<AffinePoint>::from(AffinePoint::decompact(FieldBytes::from_slice(x)))
+        .map(ProjectivePoint::from)
+}
+
+/// Challenge of a signature, `tagged_hash(r || x(P) || message)`
+fn challenge(r: &[u8], public_key: &[u8], message: &[u8]) -> Scalar {
+    let tag = Sha256::digest(CHALLENGE_TAG);
+    let mut hasher = Sha256::new();
+    hasher.input(tag);
+    hasher.input(tag);
+    hasher.input(r);
+    hasher.input(public_key);
+    hasher.input(message);
+    <Scalar as Reduce<U256>>::reduce_bytes(FieldBytes::from_slice(&hasher.result()))
+}
+
+/// Verifies signatures at once, telling if they are all valid
+///
+/// Each signature `(r, s)` of a key `P` satisfies `s.G = R + e.P`. The equations are weighted by
+/// random factors `a` and added up, so that a single multi-scalar multiplication checks
+/// `(sum a.s).G - sum a.R - sum (a.e).P = 0`, with the doublings shared by all the terms. An
+/// invalid signature passes only if the factors are guessed.
+pub fn verify_batch(batch: &[(&[u8], &[u8; SIGNATURE_BYTES], &PublicKey)]) -> bool {
+    let mut rng = rand::thread_rng();
+    let mut terms = Vec::with_capacity(1 + 2 * batch.len());
+    let mut s_sum = Scalar::ZERO;
+    for (i, (message, signature, public_key)) in batch.iter().enumerate() {
+        let public_key = &public_key.serialize()[1..];
+        let (r, s) = signature.split_at(32);
+        let points = (lift_x(r), lift_x(public_key));
+        let s = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(s)));
+        let (r_point, p_point, s) = match (points, s) {
+            ((Some(r_point), Some(p_point)), Some(s)) => (r_point, p_point, s),
+            _ => return false,
+        };
+        // The first factor is 1, which keeps the batch of a single signature a plain check
+        let a = if i == 0 {
+            Scalar::ONE
+        } else {
+            let mut bytes = [0u8; 32];
+            rng.fill_bytes(&mut bytes);
+            <Scalar as Reduce<U256>>::reduce_bytes(FieldBytes::from_slice(&bytes))
+        };
+        let e = challenge(r, public_key, message);
+        s_sum += a * s;
+        terms.push((r_point, -a));
+        terms.push((p_point, -(a * e)));
+    }
+    terms.push((ProjectivePoint::GENERATOR, s_sum));
+    ProjectivePoint::lincomb_ext(&terms[..])
+        .is_identity()
+        .into()
+}
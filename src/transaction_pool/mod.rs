@@ -1,23 +1,71 @@
-use rand::seq::IteratorRandom;
-use std::collections::HashSet;
+use rand::seq::SliceRandom;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::iter;
+use std::time::{Duration, Instant};
 
 use crate::block::Block;
 use crate::blockchain::Blockchain;
-use crate::constants::TXS_PER_BLOCK;
+use crate::constants::{TXS_PER_BLOCK, TX_POOL_BYTES, TX_POOL_EXPIRY};
 use crate::error::transaction_pool::TransactionPoolError;
 use crate::transaction::Transaction;
+use crate::utxo::{UtxoData, UtxoId};
+use crate::utxo_pool::view::UtxoView;
 use crate::utxo_pool::UtxoPool;
+use crate::Hash as TransactionId;
 
+/// Unconfirmed transactions waiting to be mined
+///
+/// Transactions may spend the outputs of other transactions of the pool. The pool holds at most
+/// `max_bytes` of transactions: once full, the transactions paying the lowest fee rates are
+/// evicted to make room for better paying ones. Transactions that stay in the pool longer than
+/// `expiry` are dropped.
 #[derive(Debug)]
 pub struct TransactionPool {
     transactions: HashSet<Transaction>,
+    entries: HashMap<TransactionId, Entry>,
+    outputs: HashMap<UtxoId, UtxoData>,
+    bytes: usize,
+    max_bytes: usize,
+    expiry: Duration,
+}
+
+#[derive(Debug)]
+struct Entry {
+    fee: u32,
+    bytes: usize,
+    time: Instant,
+}
+
+impl Entry {
+    fn new(transaction: &Transaction, fee: u32) -> Self {
+        Self {
+            fee,
+            bytes: transaction.bytes(),
+            time: Instant::now(),
+        }
+    }
+
+    /// Compares the fee rates of two entries without rounding
+    fn cmp_fee_rate(&self, other: &Entry) -> Ordering {
+        (self.fee as u64 * other.bytes as u64).cmp(&(other.fee as u64 * self.bytes as u64))
+    }
 }
 
 impl TransactionPool {
     pub fn new() -> Self {
+        Self::with_limits(TX_POOL_BYTES, TX_POOL_EXPIRY)
+    }
+
+    pub fn with_limits(max_bytes: usize, expiry: Duration) -> Self {
         Self {
             transactions: HashSet::new(),
+            entries: HashMap::new(),
+            outputs: HashMap::new(),
+            bytes: 0,
+            max_bytes,
+            expiry,
         }
     }
 
@@ -25,22 +73,222 @@ impl TransactionPool {
         self.transactions.len()
     }
 
-    pub fn add(&mut self, transaction: Transaction) -> Result<(), TransactionPoolError> {
-        if self.transactions.insert(transaction) {
-            Ok(())
-        } else {
-            Err(TransactionPoolError::KnownTransaction)
+    /// Adds a transaction paying `fee` and returns the transactions it replaces and those
+    /// evicted to make room for it, along with their descendants
+    ///
+    /// Fails if it cannot replace the transactions it conflicts with, or if the pool is full of
+    /// transactions paying at least its fee rate. The ancestors of the transaction are never
+    /// evicted for it.
+    pub fn add(
+        &mut self,
+        transaction: Transaction,
+        fee: u32,
+    ) -> Result<Vec<Transaction>, TransactionPoolError> {
+        let replaced = self.check_replacement(&transaction, fee)?;
+        let entry = Entry::new(&transaction, fee);
+        let ancestors = self.ancestors_of(&transaction);
+        let mut evictable: Vec<_> = self
+            .transactions
+            .iter()
+            .filter(|tx| self.entries[tx.id()].cmp_fee_rate(&entry) == Ordering::Less)
+            .filter(|tx| !ancestors.contains(tx) && !replaced.contains(tx))
+            .collect();
+        evictable.sort_by(|tx1, tx2| self.entries[tx1.id()].cmp_fee_rate(&self.entries[tx2.id()]));
+        let replaced_bytes: usize = replaced.iter().map(|tx| self.entries[tx.id()].bytes).sum();
+        let mut bytes = self.bytes + entry.bytes - replaced_bytes;
+        let mut lowest: Vec<Transaction> = Vec::new();
+        for transaction in evictable {
+            if bytes <= self.max_bytes {
+                break;
+            }
+            if lowest.contains(transaction) {
+                continue;
+            }
+            let descendants = self.descendants_of(transaction);
+            if descendants.iter().any(|tx| ancestors.contains(tx)) {
+                continue;
+            }
+            for transaction in iter::once(transaction).chain(&descendants) {
+                if !replaced.contains(transaction) && !lowest.contains(transaction) {
+                    bytes -= self.entries[transaction.id()].bytes;
+                    lowest.push(transaction.clone());
+                }
+            }
+        }
+        if bytes > self.max_bytes {
+            return Err(TransactionPoolError::Full);
+        }
+        let mut evicted = replaced;
+        evicted.extend(lowest);
+        for transaction in &evicted {
+            self.remove(transaction).unwrap();
         }
+        self.insert(transaction, entry);
+        Ok(evicted)
     }
 
     pub fn remove(&mut self, transaction: &Transaction) -> Result<(), TransactionPoolError> {
         if self.transactions.remove(transaction) {
+            let entry = self.entries.remove(transaction.id()).unwrap();
+            self.bytes -= entry.bytes;
+            for vout in 0..transaction.outputs().len() {
+                self.outputs.remove(&UtxoId::new(*transaction.id(), vout));
+            }
             Ok(())
         } else {
             Err(TransactionPoolError::UnknownTransaction)
         }
     }
 
+    /// Removes and returns the transactions that stayed in the pool longer than the expiry,
+    /// along with their descendants
+    pub fn expire(&mut self) -> Vec<Transaction> {
+        let expiry = self.expiry;
+        let mut expired = self.take_if(|_, entry| entry.time.elapsed() > expiry);
+        for transaction in expired.clone() {
+            expired.extend(self.take_descendants_of(&transaction));
+        }
+        expired
+    }
+
+    /// Checks that a transaction paying `fee` may replace the transactions of the pool it
+    /// conflicts with, and returns them along with their descendants
+    ///
+    /// The replacement must pay strictly more fee than all the replaced transactions, at a
+    /// strictly higher fee rate than each conflicting one, and must not spend their outputs.
+    pub fn check_replacement(
+        &self,
+        transaction: &Transaction,
+        fee: u32,
+    ) -> Result<Vec<Transaction>, TransactionPoolError> {
+        if self.transactions.contains(transaction) {
+            return Err(TransactionPoolError::KnownTransaction);
+        }
+        let entry = Entry::new(transaction, fee);
+        let mut replaced = Vec::new();
+        for conflict in self.conflicts_of(transaction) {
+            if self.entries[conflict.id()].cmp_fee_rate(&entry) != Ordering::Less {
+                return Err(TransactionPoolError::InsufficientFee(*conflict.id()));
+            }
+            if !replaced.contains(conflict) {
+                replaced.push(conflict.clone());
+                for descendant in self.descendants_of(conflict) {
+                    if !replaced.contains(&descendant) {
+                        replaced.push(descendant);
+                    }
+                }
+            }
+        }
+        if let Some(parent) = replaced.iter().find(|tx| transaction.spends_output_of(tx)) {
+            return Err(TransactionPoolError::UnknownUtxo(*parent.id()));
+        }
+        let replaced_fee: u64 = replaced
+            .iter()
+            .map(|tx| u64::from(self.entries[tx.id()].fee))
+            .sum();
+        match replaced.first() {
+            Some(conflict) if u64::from(fee) <= replaced_fee => {
+                Err(TransactionPoolError::InsufficientFee(*conflict.id()))
+            }
+            _ => Ok(replaced),
+        }
+    }
+
+    /// Transactions of the pool spending a utxo that a transaction spends as well
+    pub fn conflicts_of<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+    ) -> impl Iterator<Item = &'a Transaction> {
+        self.transactions
+            .iter()
+            .filter(move |tx| tx.shares_utxo_with(transaction))
+    }
+
+    /// Transactions of the pool whose outputs are spent by a transaction
+    pub fn parents_of<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+    ) -> impl Iterator<Item = &'a Transaction> {
+        self.transactions
+            .iter()
+            .filter(move |tx| transaction.spends_output_of(tx))
+    }
+
+    /// Transactions of the pool a transaction depends on, parents before children
+    pub fn ancestors_of(&self, transaction: &Transaction) -> Vec<Transaction> {
+        let mut ancestors = Vec::new();
+        self.visit_ancestors_of(transaction, &mut ancestors);
+        ancestors
+    }
+
+    fn visit_ancestors_of(&self, transaction: &Transaction, ancestors: &mut Vec<Transaction>) {
+        for parent in self.parents_of(transaction) {
+            if !ancestors.contains(parent) {
+                self.visit_ancestors_of(parent, ancestors);
+                ancestors.push(parent.clone());
+            }
+        }
+    }
+
+    /// Transactions of the pool depending on a transaction
+    pub fn descendants_of(&self, transaction: &Transaction) -> Vec<Transaction> {
+        let mut descendants: Vec<&Transaction> = Vec::new();
+        let mut parents = vec![transaction];
+        while let Some(parent) = parents.pop() {
+            for child in &self.transactions {
+                if child.spends_output_of(parent) && !descendants.contains(&child) {
+                    descendants.push(child);
+                    parents.push(child);
+                }
+            }
+        }
+        descendants.into_iter().cloned().collect()
+    }
+
+    /// Removes and returns the transactions of the pool depending on a transaction
+    fn take_descendants_of(&mut self, transaction: &Transaction) -> Vec<Transaction> {
+        let descendants = self.descendants_of(transaction);
+        for descendant in &descendants {
+            self.remove(descendant).unwrap();
+        }
+        descendants
+    }
+
+    /// Utxo pool seen along with the outputs of the transactions of the pool
+    pub fn utxo_view_of<'a>(&'a self, utxo_pool: &'a UtxoPool) -> UtxoView<'a> {
+        UtxoView::new(utxo_pool, &self.outputs)
+    }
+
+    /// Inserts a transaction unless it is already in the pool
+    fn insert(&mut self, transaction: Transaction, entry: Entry) {
+        for (vout, output) in transaction.outputs().iter().enumerate() {
+            let utxo_id = UtxoId::new(*transaction.id(), vout);
+            self.outputs.insert(utxo_id, *output.utxo_data());
+        }
+        let id = *transaction.id();
+        if self.transactions.insert(transaction) {
+            self.bytes += entry.bytes;
+            self.entries.insert(id, entry);
+        }
+    }
+
+    /// Removes and returns the transactions matching a predicate
+    fn take_if<F>(&mut self, mut f: F) -> Vec<Transaction>
+    where
+        F: FnMut(&Transaction, &Entry) -> bool,
+    {
+        let taken: Vec<_> = self
+            .transactions
+            .iter()
+            .filter(|tx| f(tx, &self.entries[tx.id()]))
+            .cloned()
+            .collect();
+        for transaction in &taken {
+            self.remove(transaction).unwrap();
+        }
+        taken
+    }
+
     pub fn compatibility_of(&self, transaction: &Transaction) -> Result<(), TransactionPoolError> {
         for pool_transaction in self.transactions() {
             for pool_input in pool_transaction.inputs() {
@@ -54,25 +302,67 @@ impl TransactionPool {
         Ok(())
     }
 
+    /// Picks the transactions of a block by ancestor packages, i.e. transactions preceded by
+    /// their unconfirmed ancestors, the packages paying the highest combined fee rate first
+    ///
+    /// A low fee parent is thus mined along with a child paying for it. Ties are broken randomly.
     pub fn select(&self) -> Option<Vec<Transaction>> {
         if self.size() < TXS_PER_BLOCK {
             return None;
         }
-        Some(
-            self.transactions
-                .iter()
-                .choose_multiple(&mut rand::thread_rng(), TXS_PER_BLOCK)
+        let mut candidates: Vec<_> = self.transactions.iter().collect();
+        candidates.shuffle(&mut rand::thread_rng());
+        let mut selected = Vec::with_capacity(TXS_PER_BLOCK);
+        while selected.len() < TXS_PER_BLOCK {
+            let package = candidates
                 .iter()
-                .map(|&tx| tx.clone())
-                .collect(),
-        )
+                .filter(|tx| !selected.contains(**tx))
+                .map(|tx| self.package_of(tx, &selected))
+                .filter(|package| selected.len() + package.len() <= TXS_PER_BLOCK)
+                .max_by(|p1, p2| self.entry_of(p1).cmp_fee_rate(&self.entry_of(p2)))?;
+            selected.extend(package);
+        }
+        Some(selected)
     }
 
-    /// Removes the block transactions (that are in the pool) from the pool
+    /// Transaction preceded by its ancestors, except the excluded ones, parents before children
+    pub fn package_of(
+        &self,
+        transaction: &Transaction,
+        excluded: &[Transaction],
+    ) -> Vec<Transaction> {
+        let mut package = self.ancestors_of(transaction);
+        package.retain(|tx| !excluded.contains(tx));
+        package.push(transaction.clone());
+        package
+    }
+
+    /// Combined fee of transactions of the pool
+    pub fn package_fee(&self, package: &[Transaction]) -> u32 {
+        self.entry_of(package).fee
+    }
+
+    fn entry_of(&self, package: &[Transaction]) -> Entry {
+        let mut entry = Entry {
+            fee: 0,
+            bytes: 0,
+            time: Instant::now(),
+        };
+        for transaction in package {
+            entry.fee = entry.fee.saturating_add(self.entries[transaction.id()].fee);
+            entry.bytes += self.entries[transaction.id()].bytes;
+        }
+        entry
+    }
+
+    /// Removes the block transactions (that are in the pool) from the pool, along with the
+    /// descendants of the transactions they double spend
     pub fn process(&mut self, block: &Block) {
         for block_transaction in block.transactions() {
-            self.transactions
-                .retain(|tx| !tx.shares_utxo_with(block_transaction));
+            let conflicting = self.take_if(|tx, _| tx.shares_utxo_with(block_transaction));
+            for transaction in conflicting.iter().filter(|&tx| tx != block_transaction) {
+                self.take_descendants_of(transaction);
+            }
         }
     }
 
@@ -88,16 +378,28 @@ impl TransactionPool {
         utxo_pool: &UtxoPool,
         fork_block: Option<&Block>,
     ) {
-        self.transactions.retain(|tx| {
-            !blockchain.contains_tx(tx.id(), fork_block, None)
-                && utxo_pool.check_utxos_exist_for(tx).is_ok()
-        });
+        self.take_if(|tx, _| blockchain.contains_tx(tx.id(), fork_block, None));
+        let utxo_pool = self.utxo_view_of(utxo_pool);
+        let invalid: Vec<_> = self
+            .transactions
+            .iter()
+            .filter(|tx| utxo_pool.check_utxos_exist_for(tx).is_err())
+            .cloned()
+            .collect();
+        for transaction in &invalid {
+            self.remove(transaction).unwrap();
+        }
+        for transaction in &invalid {
+            self.take_descendants_of(transaction);
+        }
     }
 
-    pub fn undo_all(&mut self, blocks: Vec<Block>) {
+    /// Puts the transactions of undone blocks back into the pool, regardless of its limits
+    pub fn undo_all(&mut self, blocks: Vec<Block>, utxo_pool: &UtxoPool) {
         for mut block in blocks {
-            for transaction in block.transactions_mut().pop() {
-                self.add(transaction).unwrap();
+            for transaction in block.transactions_mut().drain(..) {
+                let entry = Entry::new(&transaction, utxo_pool.fee_of(&transaction).unwrap_or(0));
+                self.insert(transaction, entry);
             }
         }
     }
@@ -112,13 +414,30 @@ impl TransactionPool {
             .get(0)
             .map(|b| blockchain.get_parent_of(b))
             .flatten();
-        self.undo_all(blocks_to_undo);
+        self.undo_all(blocks_to_undo, utxo_pool);
         self.synchronize_with(blockchain, utxo_pool, fork_block);
     }
 
     pub fn transactions(&self) -> &HashSet<Transaction> {
         &self.transactions
     }
+
+    pub fn fee_of(&self, transaction: &Transaction) -> Option<u32> {
+        self.entries.get(transaction.id()).map(|entry| entry.fee)
+    }
+
+    /// Total size of the transactions in the pool
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn expiry(&self) -> Duration {
+        self.expiry
+    }
 }
 
 impl fmt::Display for TransactionPool {
@@ -141,3 +460,5 @@ impl PartialEq for TransactionPool {
             .is_none()
     }
 }
+
+pub mod orphan;
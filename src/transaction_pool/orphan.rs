@@ -0,0 +1,75 @@
+use rand::seq::IteratorRandom;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::constants::ORPHAN_POOL_SIZE;
+use crate::transaction::Transaction;
+use crate::Hash as TransactionId;
+
+/// Transactions received before their parents
+///
+/// They wait here until their parents show up, in the transaction pool or in a block. The pool
+/// holds at most `ORPHAN_POOL_SIZE` transactions, evicting random ones when full, and drops the
+/// transactions older than `expiry`.
+#[derive(Debug)]
+pub struct OrphanPool {
+    transactions: HashMap<TransactionId, (Transaction, Instant)>,
+    expiry: Duration,
+}
+
+impl OrphanPool {
+    pub fn new(expiry: Duration) -> Self {
+        Self {
+            transactions: HashMap::new(),
+            expiry,
+        }
+    }
+
+    /// Adds an orphan transaction and tells if it was unknown
+    pub fn add(&mut self, transaction: Transaction) -> bool {
+        if self.transactions.contains_key(transaction.id()) {
+            return false;
+        }
+        if self.transactions.len() >= ORPHAN_POOL_SIZE {
+            let txid = *self
+                .transactions
+                .keys()
+                .choose(&mut rand::thread_rng())
+                .unwrap();
+            self.transactions.remove(&txid);
+        }
+        self.transactions
+            .insert(*transaction.id(), (transaction, Instant::now()));
+        true
+    }
+
+    /// Removes and returns the orphans spending an output of a transaction
+    pub fn take_children_of(&mut self, parent: &Transaction) -> Vec<Transaction> {
+        let txids: Vec<_> = self
+            .transactions
+            .values()
+            .filter(|(tx, _)| tx.spends_output_of(parent))
+            .map(|(tx, _)| *tx.id())
+            .collect();
+        txids
+            .iter()
+            .filter_map(|txid| self.transactions.remove(txid))
+            .map(|(tx, _)| tx)
+            .collect()
+    }
+
+    /// Drops the orphans whose parents did not show up in time
+    pub fn expire(&mut self) {
+        let expiry = self.expiry;
+        self.transactions
+            .retain(|_, (_, time)| time.elapsed() <= expiry);
+    }
+
+    pub fn contains(&self, transaction: &Transaction) -> bool {
+        self.transactions.contains_key(transaction.id())
+    }
+
+    pub fn size(&self) -> usize {
+        self.transactions.len()
+    }
+}
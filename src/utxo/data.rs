@@ -1,53 +1,117 @@
 use secp256k1::PublicKey;
+use sha2::{Digest, Sha256};
 use std::convert::TryInto;
 use std::fmt;
 
-use crate::constants::UTXO_DATA_BYTES;
+use crate::address::Address;
+use crate::constants::{KEY_HASH_BYTES, UTXO_DATA_BYTES};
 use crate::transaction::TransactionOutput;
 
+/// Signature scheme the inputs spending an output are signed with
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OutputType {
+    /// ECDSA signature in compact form
+    Ecdsa = 0,
+    /// BIP340 Schnorr signature, verified in batches, over the x-coordinate of the public key
+    Schnorr = 1,
+}
+
+impl OutputType {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Ecdsa),
+            1 => Some(Self::Schnorr),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OutputType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ecdsa => write!(f, "ecdsa"),
+            Self::Schnorr => write!(f, "schnorr"),
+        }
+    }
+}
+
+/// Hash of a public key, which outputs pay so that the key is only revealed when spent
+pub type KeyHash = [u8; KEY_HASH_BYTES];
+
+/// SHA-256 of the compressed public key, truncated to `KEY_HASH_BYTES`
+pub fn key_hash(public_key: &PublicKey) -> KeyHash {
+    let mut hasher = Sha256::new();
+    hasher.input(&public_key.serialize()[..]);
+    hasher.result()[..KEY_HASH_BYTES].try_into().unwrap()
+}
+
+/// Amount paid to the hash of a public key, to be spent with a signature by the key
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct UtxoData {
     amount: u32,
-    public_key: PublicKey,
+    key_hash: KeyHash,
+    output_type: OutputType,
 }
 
 impl UtxoData {
     pub fn new(amount: u32, public_key: PublicKey) -> Self {
-        Self { amount, public_key }
+        Self::with_type(amount, public_key, OutputType::Ecdsa)
+    }
+
+    pub fn with_type(amount: u32, public_key: PublicKey, output_type: OutputType) -> Self {
+        Self::paying(amount, key_hash(&public_key), output_type)
+    }
+
+    pub fn paying(amount: u32, key_hash: KeyHash, output_type: OutputType) -> Self {
+        Self {
+            amount,
+            key_hash,
+            output_type,
+        }
     }
 
     pub fn serialize(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(UTXO_DATA_BYTES);
         bytes.extend(&self.amount.to_be_bytes());
-        bytes.extend(self.public_key.serialize().iter());
+        bytes.extend(self.key_hash.iter());
+        bytes.push(self.output_type as u8);
         bytes
     }
 
-    pub fn deserialize<B>(bytes: B) -> Self
+    /// Deserializes bytes that may be malformed, e.g. read from a file or sent by a neighbour
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
     where
         B: AsRef<[u8]>,
     {
-        Self::from(bytes)
+        let bytes = bytes.as_ref();
+        if bytes.len() != UTXO_DATA_BYTES {
+            return None;
+        }
+        let amount = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        let key_hash = bytes[4..4 + KEY_HASH_BYTES].try_into().unwrap();
+        let output_type = OutputType::from_byte(bytes[4 + KEY_HASH_BYTES])?;
+        Some(Self::paying(amount, key_hash, output_type))
     }
 
     pub fn amount(&self) -> u32 {
         self.amount
     }
 
-    pub fn public_key(&self) -> &PublicKey {
-        &self.public_key
+    pub fn key_hash(&self) -> &KeyHash {
+        &self.key_hash
     }
-}
 
-impl<B> From<B> for UtxoData
-where
-    B: AsRef<[u8]>,
-{
-    fn from(bytes: B) -> Self {
-        let bytes = bytes.as_ref();
-        let amount = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
-        let public_key = PublicKey::from_slice(bytes[4..37].try_into().unwrap()).unwrap();
-        Self { amount, public_key }
+    /// Tells if the output pays a public key
+    pub fn pays(&self, public_key: &PublicKey) -> bool {
+        key_hash(public_key) == self.key_hash
+    }
+
+    pub fn output_type(&self) -> OutputType {
+        self.output_type
+    }
+
+    pub fn address(&self) -> Address {
+        Address::paying(self.key_hash, self.output_type)
     }
 }
 
@@ -61,8 +125,9 @@ impl fmt::Display for UtxoData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Utxo data {{\n  amount: {}\n  pulic_key: {}\n}}",
-            self.amount, self.public_key
+            "Utxo data {{\n  amount: {}\n  address: {}\n}}",
+            self.amount,
+            self.address()
         )
     }
 }
@@ -1,11 +1,12 @@
-use secp256k1::PublicKey;
-
-pub use self::data::UtxoData;
+pub use self::data::{key_hash, KeyHash, OutputType, UtxoData};
 pub use self::id::UtxoId;
 use crate::Hash;
+use std::borrow::Borrow;
 use std::fmt;
+use std::hash::{self, Hasher};
 
-#[derive(Clone, Copy, Debug, Hash)]
+/// Utxos are identified by their id alone, so that sets of utxos can be looked up by id
+#[derive(Clone, Copy, Debug)]
 pub struct Utxo {
     id: UtxoId,
     data: UtxoData,
@@ -36,8 +37,12 @@ impl Utxo {
         self.data.amount()
     }
 
-    pub fn public_key(&self) -> &PublicKey {
-        &self.data.public_key()
+    pub fn key_hash(&self) -> &KeyHash {
+        self.data.key_hash()
+    }
+
+    pub fn output_type(&self) -> OutputType {
+        self.data.output_type()
     }
 
     pub fn utxo_id(&self) -> &UtxoId {
@@ -57,15 +62,27 @@ impl PartialEq for Utxo {
     }
 }
 
+impl hash::Hash for Utxo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Borrow<UtxoId> for Utxo {
+    fn borrow(&self) -> &UtxoId {
+        &self.id
+    }
+}
+
 impl fmt::Display for Utxo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Utxo {{\n  txid: {:x}\n  vout: {}\n  amount: {}\n  public_key: {}\n}}\n",
+            "Utxo {{\n  txid: {:x}\n  vout: {}\n  amount: {}\n  address: {}\n}}\n",
             self.txid(),
             self.vout(),
             self.amount(),
-            self.public_key()
+            self.data.address()
         )
     }
 }
@@ -1,54 +1,74 @@
-use secp256k1::{Message as MessageToSign, PublicKey, Secp256k1};
-use sha2::{Digest, Sha256};
+use rayon::prelude::*;
+use secp256k1::PublicKey;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+use self::view::UtxoView;
 use crate::block::Block;
 use crate::blockchain::Blockchain;
-use crate::constants::{UTXO_AMOUNT_INIT, UTXO_HASH_INIT};
+use crate::constants::{SCHNORR_BATCH_SIZE, UTXO_AMOUNT_INIT, UTXO_HASH_INIT};
 use crate::error::transaction::TransactionError;
 use crate::error::utxo_pool::UtxoPoolError;
-use crate::transaction::Transaction;
-use crate::utxo::{Utxo, UtxoData, UtxoId};
+use crate::transaction::{self, schnorr, Transaction};
+use crate::utxo::{key_hash, KeyHash, OutputType, Utxo, UtxoData, UtxoId};
 use crate::Hash;
 
+/// Unspent transaction outputs, indexed by the key hash they pay
 #[derive(Clone, Debug)]
 pub struct UtxoPool {
     utxos: HashMap<UtxoId, UtxoData>,
+    by_key_hash: HashMap<KeyHash, HashSet<UtxoId>>,
 }
 
 impl UtxoPool {
     pub fn new() -> Self {
         Self {
             utxos: HashMap::new(),
+            by_key_hash: HashMap::new(),
         }
     }
 
     pub fn initialize(public_keys: Vec<PublicKey>) -> Self {
-        let utxos: HashMap<UtxoId, UtxoData> = public_keys
-            .into_iter()
-            .enumerate()
-            .map(|(n, pk)| {
-                (
-                    UtxoId::new(Hash::from(UTXO_HASH_INIT), n),
-                    UtxoData::new(UTXO_AMOUNT_INIT, pk),
-                )
-            })
-            .collect();
-        Self { utxos }
+        let mut utxo_pool = Self::new();
+        for (n, pk) in public_keys.into_iter().enumerate() {
+            utxo_pool.insert(
+                UtxoId::new(Hash::from(UTXO_HASH_INIT), n),
+                UtxoData::new(UTXO_AMOUNT_INIT, pk),
+            );
+        }
+        utxo_pool
+    }
+
+    /// Inserts a utxo, keeping the index up to date
+    fn insert(&mut self, utxo_id: UtxoId, utxo_data: UtxoData) -> Option<UtxoData> {
+        self.by_key_hash
+            .entry(*utxo_data.key_hash())
+            .or_default()
+            .insert(utxo_id);
+        self.utxos.insert(utxo_id, utxo_data)
+    }
+
+    /// Removes a utxo, keeping the index up to date
+    fn take(&mut self, utxo_id: &UtxoId) -> Option<UtxoData> {
+        let utxo_data = self.utxos.remove(utxo_id)?;
+        let utxo_ids = self.by_key_hash.get_mut(utxo_data.key_hash()).unwrap();
+        utxo_ids.remove(utxo_id);
+        if utxo_ids.is_empty() {
+            self.by_key_hash.remove(utxo_data.key_hash());
+        }
+        Some(utxo_data)
     }
 
     pub fn add(&mut self, utxo: Utxo) -> Result<(), UtxoPoolError> {
-        match self.utxos.insert(*utxo.id(), *utxo.data()) {
-            None => Ok(()),
-            Some(_) => Err(UtxoPoolError::KnownUtxo),
+        if self.utxos.contains_key(utxo.id()) {
+            return Err(UtxoPoolError::KnownUtxo);
         }
+        self.insert(*utxo.id(), *utxo.data());
+        Ok(())
     }
 
     pub fn remove(&mut self, utxo: &Utxo) -> Result<UtxoData, UtxoPoolError> {
-        self.utxos
-            .remove(utxo.id())
-            .ok_or(UtxoPoolError::UnknownUtxo)
+        self.take(utxo.id()).ok_or(UtxoPoolError::UnknownUtxo)
     }
 
     pub fn contains(&self, utxo: &Utxo) -> bool {
@@ -56,20 +76,31 @@ impl UtxoPool {
     }
 
     pub fn owned_by(&self, pk: &PublicKey) -> HashSet<Utxo> {
-        self.utxos
-            .iter()
-            .filter(|(_id, data)| data.public_key() == pk)
-            .map(|(id, data)| Utxo::new(id.clone(), data.clone()))
+        self.by_key_hash
+            .get(&key_hash(pk))
+            .into_iter()
+            .flatten()
+            .map(|id| Utxo::new(*id, self.utxos[id]))
             .collect()
     }
 
+    /// Coins held by a public key
+    pub fn balance_of(&self, pk: &PublicKey) -> u64 {
+        self.by_key_hash
+            .get(&key_hash(pk))
+            .into_iter()
+            .flatten()
+            .map(|id| u64::from(self.utxos[id].amount()))
+            .sum()
+    }
+
     pub fn process_t(&mut self, transaction: &Transaction) {
         for input in transaction.inputs() {
-            self.utxos.remove(input.utxo_id());
+            self.take(input.utxo_id());
         }
         for (vout, output) in transaction.outputs().iter().enumerate() {
             let utxo_id = UtxoId::new(*transaction.id(), vout);
-            let utxo_data = UtxoData::new(output.amount(), *output.public_key());
+            let utxo_data = *output.utxo_data();
             let utxo = Utxo::new(utxo_id, utxo_data);
             self.add(utxo).unwrap();
         }
@@ -90,7 +121,7 @@ impl UtxoPool {
     pub fn undo_t(&mut self, transaction: &Transaction, blockchain: &Blockchain, block: &Block) {
         for (vout, output) in transaction.outputs().iter().enumerate() {
             let utxo_id = UtxoId::new(*transaction.id(), vout);
-            let utxo_data = UtxoData::new(output.amount(), *output.public_key());
+            let utxo_data = *output.utxo_data();
             let utxo = Utxo::new(utxo_id, utxo_data);
             self.remove(&utxo).unwrap();
         }
@@ -102,7 +133,7 @@ impl UtxoPool {
     }
 
     pub fn undo(&mut self, block: &Block, blockchain: &Blockchain) {
-        for transaction in block.transactions() {
+        for transaction in block.transactions().iter().rev() {
             self.undo_t(transaction, blockchain, block);
         }
     }
@@ -124,66 +155,120 @@ impl UtxoPool {
     }
 
     pub fn check_utxos_exist_for(&self, transaction: &Transaction) -> Result<(), UtxoPoolError> {
-        for input in transaction.inputs() {
-            self.utxos
-                .get(input.utxo_id())
-                .ok_or(UtxoPoolError::TransactionHasUnknownUtxo)?;
-        }
-        Ok(())
+        UtxoView::from(self).check_utxos_exist_for(transaction)
     }
 
-    /// Checks that inputs total amount matches outputs total amount.
+    /// Checks that inputs total amount covers outputs total amount, the difference being the
+    /// fee.
+    ///
+    /// Blocks have no coinbase to credit fees to their sealer, so fees are burned: they only buy
+    /// priority in the transaction pool and leave the money supply.
     ///
     /// # Panics
     ///
     /// Panics if an input has unknown utxo.
     pub fn check_balance_of(&self, transaction: &Transaction) -> Result<(), TransactionError> {
-        let inputs_sum: u32 = transaction
-            .inputs()
-            .iter()
-            .map(|i| self.utxos[i.utxo_id()].amount())
-            .sum();
-        let outputs_sum = transaction.outputs().iter().map(|o| o.amount()).sum();
-        if inputs_sum == outputs_sum {
-            Ok(())
-        } else {
-            Err(TransactionError::WrongBalance)
-        }
+        UtxoView::from(self).check_balance_of(transaction)
+    }
+
+    /// Amount of the inputs of a transaction that its outputs leave as fee, if its inputs are
+    /// known and cover its outputs
+    pub fn fee_of(&self, transaction: &Transaction) -> Option<u32> {
+        UtxoView::from(self).fee_of(transaction)
     }
 
     pub fn authenticate(&self, transaction: &Transaction) -> Result<(), UtxoPoolError> {
-        let mut message = Vec::new();
-        for utxo_id in transaction.inputs().iter().map(|i| i.utxo_id()) {
-            message.extend(utxo_id.serialize());
-        }
-        for output in transaction.outputs() {
-            message.extend(output.serialize());
-        }
-        let mut hasher = Sha256::new();
-        hasher.input(message);
-        let hash = hasher.result();
-        let message = MessageToSign::from_slice(&hash).unwrap();
-        let secp = Secp256k1::new();
-        for input in transaction.inputs() {
-            if let Some(utxo_data) = self.utxos.get(input.utxo_id()) {
-                secp.verify(&message, input.sig(), utxo_data.public_key())?;
-            }
-        }
-        Ok(())
+        UtxoView::from(self).authenticate(transaction)
     }
 
+    /// Checks that the block transactions spend known utxos, which may be created by previous
+    /// transactions of the block
     pub fn check_utxos_exist(&self, block: &Block) -> Result<(), UtxoPoolError> {
+        let (mut created, mut spent) = (HashSet::new(), HashSet::new());
         for transaction in block.transactions() {
-            self.check_utxos_exist_for(transaction)?;
+            for input in transaction.inputs() {
+                let utxo_id = input.utxo_id();
+                let exists = self.utxos.contains_key(utxo_id) || created.contains(utxo_id);
+                if !exists || !spent.insert(*utxo_id) {
+                    return Err(UtxoPoolError::BlockHasUnknownUtxo);
+                }
+            }
+            for vout in 0..transaction.outputs().len() {
+                created.insert(UtxoId::new(*transaction.id(), vout));
+            }
         }
         Ok(())
     }
 
+    /// Checks the signatures of the block transactions, which may spend utxos created by previous
+    /// transactions of the block, reporting the first invalid input in block order
+    ///
+    /// Transactions are hashed and inputs verified in parallel on the thread pool, the Schnorr
+    /// signatures in batches of `SCHNORR_BATCH_SIZE`. A failing batch is verified again input by
+    /// input to find the invalid one.
     pub fn check_signatures_of(&self, block: &Block) -> Result<(), UtxoPoolError> {
+        let mut created = HashMap::new();
         for transaction in block.transactions() {
-            self.authenticate(transaction)?;
+            for (vout, output) in transaction.outputs().iter().enumerate() {
+                created.insert(UtxoId::new(*transaction.id(), vout), *output.utxo_data());
+            }
+        }
+        let messages: Vec<_> = block
+            .transactions()
+            .par_iter()
+            .map(Transaction::message)
+            .collect();
+        let (mut ecdsa, mut schnorr) = (Vec::new(), Vec::new());
+        for (t, (transaction, message)) in block.transactions().iter().zip(&messages).enumerate() {
+            for (i, input) in transaction.inputs().iter().enumerate() {
+                let utxo_id = input.utxo_id();
+                let utxo_data = match self.utxos.get(utxo_id).or_else(|| created.get(utxo_id)) {
+                    Some(utxo_data) => utxo_data,
+                    None => continue,
+                };
+                let check = ((t, i), message, input, utxo_data);
+                match utxo_data.output_type() {
+                    OutputType::Ecdsa => ecdsa.push(check),
+                    OutputType::Schnorr => schnorr.push(check),
+                }
+            }
+        }
+
+        let invalid_ecdsa = ecdsa
+            .par_iter()
+            .find_first(|(_, message, input, utxo_data)| {
+                !transaction::verify_message(message, input.public_key(), input.sig(), utxo_data)
+            })
+            .map(|(position, ..)| *position);
+        let invalid_schnorr = schnorr
+            .par_chunks(SCHNORR_BATCH_SIZE)
+            .find_map_first(|checks| {
+                let batch: Vec<_> = checks
+                    .iter()
+                    .map(|(_, message, input, _)| (&message[..], input.sig(), input.public_key()))
+                    .collect();
+                let pays = checks
+                    .iter()
+                    .all(|(.., input, utxo_data)| utxo_data.pays(input.public_key()));
+                if pays && schnorr::verify_batch(&batch) {
+                    return None;
+                }
+                checks
+                    .iter()
+                    .find(|(_, message, input, utxo_data)| {
+                        !transaction::verify_message(
+                            message,
+                            input.public_key(),
+                            input.sig(),
+                            utxo_data,
+                        )
+                    })
+                    .map(|(position, ..)| *position)
+            });
+        match invalid_ecdsa.into_iter().chain(invalid_schnorr).min() {
+            Some((t, i)) => Err(UtxoPoolError::BlockHasInvalidSignature(t, i)),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     pub fn size(&self) -> usize {
@@ -211,10 +296,10 @@ impl fmt::Display for UtxoPool {
         for (utxo_id, utxo_data) in &self.utxos {
             write!(
                 f,
-                "\n  txid: {:x}  vout:{}\n  public_key: {}  amount: {}\n",
+                "\n  txid: {:x}  vout:{}\n  address: {}  amount: {}\n",
                 utxo_id.txid(),
                 utxo_id.vout(),
-                utxo_data.public_key(),
+                utxo_data.address(),
                 utxo_data.amount()
             )?;
         }
@@ -224,9 +309,11 @@ impl fmt::Display for UtxoPool {
 
 impl From<HashSet<Utxo>> for UtxoPool {
     fn from(utxos: HashSet<Utxo>) -> Self {
-        Self {
-            utxos: utxos.iter().map(|u| (*u.id(), *u.data())).collect(),
+        let mut utxo_pool = Self::new();
+        for utxo in utxos {
+            utxo_pool.insert(*utxo.id(), *utxo.data());
         }
+        utxo_pool
     }
 }
 
@@ -238,3 +325,5 @@ impl Into<HashSet<Utxo>> for &UtxoPool {
             .collect()
     }
 }
+
+pub mod view;
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use super::UtxoPool;
+use crate::error::transaction::TransactionError;
+use crate::error::utxo_pool::UtxoPoolError;
+use crate::transaction::{self, Transaction};
+use crate::utxo::{UtxoData, UtxoId};
+
+/// Utxo pool seen along with the outputs of unconfirmed transactions, without copying it
+///
+/// Utxos are looked up among the unconfirmed outputs first, then in the pool. The inputs of the
+/// unconfirmed transactions stay visible, conflicts being checked by the transaction pool.
+#[derive(Clone, Copy, Debug)]
+pub struct UtxoView<'a> {
+    utxo_pool: &'a UtxoPool,
+    unconfirmed: Option<&'a HashMap<UtxoId, UtxoData>>,
+}
+
+impl<'a> UtxoView<'a> {
+    pub fn new(utxo_pool: &'a UtxoPool, unconfirmed: &'a HashMap<UtxoId, UtxoData>) -> Self {
+        Self {
+            utxo_pool,
+            unconfirmed: Some(unconfirmed),
+        }
+    }
+
+    pub fn get(&self, utxo_id: &UtxoId) -> Option<&'a UtxoData> {
+        self.unconfirmed
+            .and_then(|unconfirmed| unconfirmed.get(utxo_id))
+            .or_else(|| self.utxo_pool.utxos.get(utxo_id))
+    }
+
+    pub fn check_utxos_exist_for(&self, transaction: &Transaction) -> Result<(), UtxoPoolError> {
+        for input in transaction.inputs() {
+            self.get(input.utxo_id())
+                .ok_or(UtxoPoolError::TransactionHasUnknownUtxo)?;
+        }
+        Ok(())
+    }
+
+    /// See `UtxoPool::check_balance_of`
+    pub fn check_balance_of(&self, transaction: &Transaction) -> Result<(), TransactionError> {
+        let inputs_sum: u32 = transaction
+            .inputs()
+            .iter()
+            .map(|i| self.get(i.utxo_id()).unwrap().amount())
+            .sum();
+        let outputs_sum = transaction.outputs().iter().map(|o| o.amount()).sum();
+        if inputs_sum >= outputs_sum {
+            Ok(())
+        } else {
+            Err(TransactionError::WrongBalance)
+        }
+    }
+
+    /// See `UtxoPool::fee_of`
+    pub fn fee_of(&self, transaction: &Transaction) -> Option<u32> {
+        let inputs_sum = transaction
+            .inputs()
+            .iter()
+            .map(|i| self.get(i.utxo_id()).map(|data| data.amount()))
+            .sum::<Option<u32>>()?;
+        let outputs_sum = transaction.outputs().iter().map(|o| o.amount()).sum();
+        inputs_sum.checked_sub(outputs_sum)
+    }
+
+    pub fn authenticate(&self, transaction: &Transaction) -> Result<(), UtxoPoolError> {
+        let message = transaction.message();
+        for input in transaction.inputs() {
+            if let Some(utxo_data) = self.get(input.utxo_id()) {
+                if !transaction::verify_message(
+                    &message,
+                    input.public_key(),
+                    input.sig(),
+                    utxo_data,
+                ) {
+                    return Err(secp256k1::Error::IncorrectSignature.into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a UtxoPool> for UtxoView<'a> {
+    fn from(utxo_pool: &'a UtxoPool) -> Self {
+        Self {
+            utxo_pool,
+            unconfirmed: None,
+        }
+    }
+}
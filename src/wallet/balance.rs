@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Coins of a wallet by how settled they are, see `Wallet::balance`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Balance {
+    confirmed: u64,
+    immature: u64,
+    pending: u64,
+}
+
+impl Balance {
+    pub fn new(confirmed: u64, immature: u64, pending: u64) -> Self {
+        Self {
+            confirmed,
+            immature,
+            pending,
+        }
+    }
+
+    pub fn confirmed(&self) -> u64 {
+        self.confirmed
+    }
+
+    pub fn immature(&self) -> u64 {
+        self.immature
+    }
+
+    pub fn pending(&self) -> u64 {
+        self.pending
+    }
+
+    pub fn total(&self) -> u64 {
+        self.confirmed + self.immature + self.pending
+    }
+}
+
+impl fmt::Display for Balance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "confirmed: {}  immature: {}  pending: {}",
+            self.confirmed, self.immature, self.pending
+        )
+    }
+}
@@ -0,0 +1,198 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::constants::{BNB_TRIES, KNAPSACK_ITERATIONS};
+use crate::utxo::Utxo;
+
+/// Strategy choosing the utxos a payment spends
+///
+/// Values are effective values: the amount of a utxo less the fee of spending it, so that a
+/// selection reaching `target` pays for its own inputs.
+pub trait CoinSelection: Send {
+    /// Chooses utxos whose effective values add up to at least `target`, or `None` if they cannot
+    ///
+    /// Sums up to `target + cost_of_change` are worth no change output, the excess being left
+    /// as fee.
+    fn select(
+        &self,
+        utxos: &[Utxo],
+        input_fee: u32,
+        target: u64,
+        cost_of_change: u64,
+    ) -> Option<Vec<Utxo>>;
+}
+
+/// Utxos worth spending along with their effective values, the largest first
+fn effective_values(utxos: &[Utxo], input_fee: u32) -> Vec<(Utxo, u64)> {
+    let mut values: Vec<_> = utxos
+        .iter()
+        .filter(|utxo| utxo.amount() > input_fee)
+        .map(|utxo| (*utxo, (utxo.amount() - input_fee) as u64))
+        .collect();
+    values.sort_by(|(_, value1), (_, value2)| value2.cmp(value1));
+    values
+}
+
+/// Spends the largest utxos first, which keeps few utxos but reveals the largest ones
+pub struct LargestFirst;
+
+impl CoinSelection for LargestFirst {
+    fn select(&self, utxos: &[Utxo], input_fee: u32, target: u64, _: u64) -> Option<Vec<Utxo>> {
+        let mut selected = Vec::new();
+        let mut sum = 0;
+        for (utxo, value) in effective_values(utxos, input_fee) {
+            if sum >= target {
+                break;
+            }
+            selected.push(utxo);
+            sum += value;
+        }
+        if sum >= target {
+            Some(selected)
+        } else {
+            None
+        }
+    }
+}
+
+/// Branch and bound search for a selection needing no change output (Bitcoin Core)
+///
+/// Explores the subsets of the utxos, the largest first, for `BNB_TRIES` steps and keeps the
+/// one wasting the least above `target`. If no such subset is found, falls back on knapsack.
+pub struct BranchAndBound;
+
+impl CoinSelection for BranchAndBound {
+    fn select(
+        &self,
+        utxos: &[Utxo],
+        input_fee: u32,
+        target: u64,
+        cost_of_change: u64,
+    ) -> Option<Vec<Utxo>> {
+        let values = effective_values(utxos, input_fee);
+        // Sum of the values not decided yet, to prune branches that cannot reach the target
+        let mut remaining: u64 = values.iter().map(|(_, value)| value).sum();
+        if remaining < target {
+            return None;
+        }
+        let mut included = vec![false; values.len()];
+        let mut best: Option<(u64, Vec<bool>)> = None;
+        let mut depth = 0;
+        let mut sum = 0;
+        for _ in 0..BNB_TRIES {
+            let backtrack = if sum + remaining < target || sum > target + cost_of_change {
+                true
+            } else if sum >= target {
+                if best.as_ref().is_none_or(|(waste, _)| sum - target < *waste) {
+                    best = Some((sum - target, included.clone()));
+                }
+                true
+            } else {
+                false
+            };
+            if backtrack {
+                // Excludes the last included utxo and tries the next ones instead
+                while depth > 0 && !included[depth - 1] {
+                    depth -= 1;
+                    remaining += values[depth].1;
+                }
+                if depth == 0 {
+                    break;
+                }
+                included[depth - 1] = false;
+                sum -= values[depth - 1].1;
+            } else {
+                remaining -= values[depth].1;
+                included[depth] = true;
+                sum += values[depth].1;
+                depth += 1;
+            }
+        }
+        match best {
+            Some((_, included)) => Some(
+                values
+                    .iter()
+                    .zip(included)
+                    .filter(|(_, included)| *included)
+                    .map(|((utxo, _), _)| *utxo)
+                    .collect(),
+            ),
+            None => Knapsack.select(utxos, input_fee, target, cost_of_change),
+        }
+    }
+}
+
+/// Stochastic approximation of the smallest subset reaching the target (former Bitcoin Core)
+///
+/// Takes a single utxo matching the target exactly, else looks for the subset of the smaller
+/// utxos closest to the target over `KNAPSACK_ITERATIONS` random tries, unless the smallest
+/// larger utxo is closer.
+pub struct Knapsack;
+
+impl CoinSelection for Knapsack {
+    fn select(&self, utxos: &[Utxo], input_fee: u32, target: u64, _: u64) -> Option<Vec<Utxo>> {
+        let mut values = effective_values(utxos, input_fee);
+        if let Some((utxo, _)) = values.iter().find(|(_, value)| *value == target) {
+            return Some(vec![*utxo]);
+        }
+        let lowest_larger = values
+            .iter()
+            .rev()
+            .find(|(_, value)| *value > target)
+            .copied();
+        values.retain(|(_, value)| *value < target);
+        let total: u64 = values.iter().map(|(_, value)| value).sum();
+        if total < target {
+            return lowest_larger.map(|(utxo, _)| vec![utxo]);
+        }
+
+        let mut rng = rand::thread_rng();
+        values.shuffle(&mut rng);
+        values.sort_by(|(_, value1), (_, value2)| value2.cmp(value1));
+        let mut best = (total, vec![true; values.len()]);
+        for _ in 0..KNAPSACK_ITERATIONS {
+            if best.0 == target {
+                break;
+            }
+            let mut included = vec![false; values.len()];
+            let mut sum = 0;
+            // Randomly picks utxos on the first pass, then the ones left out until the target
+            // is reached, dropping the last one picked each time it is
+            'passes: for pass in 0..2 {
+                for i in 0..values.len() {
+                    let pick = if pass == 0 {
+                        rng.gen_bool(0.5)
+                    } else {
+                        !included[i]
+                    };
+                    if !pick {
+                        continue;
+                    }
+                    sum += values[i].1;
+                    included[i] = true;
+                    if sum >= target {
+                        if sum < best.0 {
+                            best = (sum, included.clone());
+                        }
+                        sum -= values[i].1;
+                        included[i] = false;
+                        if best.0 == target {
+                            break 'passes;
+                        }
+                    }
+                }
+            }
+        }
+        match lowest_larger {
+            Some((utxo, value)) if value <= best.0 => Some(vec![utxo]),
+            _ => Some(
+                values
+                    .iter()
+                    .zip(best.1)
+                    .filter(|(_, included)| *included)
+                    .map(|((utxo, _), _)| *utxo)
+                    .collect(),
+            ),
+        }
+    }
+}
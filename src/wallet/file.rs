@@ -0,0 +1,231 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use secp256k1::{PublicKey, SecretKey};
+use std::convert::TryInto;
+use std::iter;
+
+use crate::constants::{
+    KDF_MAX_MEMORY_KIB, KDF_MAX_PASSES, KDF_MEMORY_KIB, KDF_PASSES, KDF_SALT_BYTES, KEY_HASH_BYTES,
+    UTXO_DATA_BYTES, UTXO_ID_BYTES,
+};
+use crate::error::wallet::WalletError;
+use crate::transaction::Transaction;
+use crate::utxo::{KeyHash, Utxo, UtxoData, UtxoId};
+
+const NONCE_BYTES: usize = 12;
+
+/// Key sealing the secrets of a wallet, stretched from a passphrase with Argon2id
+///
+/// Argon2id is memory-hard, so each passphrase guessed against a stolen wallet file costs
+/// `memory` KiB as well as time.
+pub struct SealingKey {
+    cipher: ChaCha20Poly1305,
+    salt: [u8; KDF_SALT_BYTES],
+    memory: u32,
+    passes: u32,
+}
+
+impl SealingKey {
+    /// Key of a passphrase under a new salt
+    pub fn new(passphrase: &str) -> Self {
+        let mut salt = [0u8; KDF_SALT_BYTES];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self::derive(passphrase, salt, KDF_MEMORY_KIB, KDF_PASSES).unwrap()
+    }
+
+    /// Key of a passphrase under the salt and cost a box was sealed with
+    pub fn of(passphrase: &str, sealed: &Sealed) -> Result<Self, WalletError> {
+        Self::derive(passphrase, sealed.salt, sealed.memory, sealed.passes)
+    }
+
+    fn derive(
+        passphrase: &str,
+        salt: [u8; KDF_SALT_BYTES],
+        memory: u32,
+        passes: u32,
+    ) -> Result<Self, WalletError> {
+        let params =
+            Params::new(memory, passes, 1, Some(32)).map_err(|_| WalletError::InvalidWalletFile)?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|_| WalletError::InvalidWalletFile)?;
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            salt,
+            memory,
+            passes,
+        })
+    }
+
+    /// Encrypts under a new random nonce
+    pub fn seal(&self, bytes: &[u8]) -> Sealed {
+        let mut nonce = [0u8; NONCE_BYTES];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), bytes)
+            .expect("Plaintext too long");
+        Sealed {
+            salt: self.salt,
+            memory: self.memory,
+            passes: self.passes,
+            nonce,
+            ciphertext,
+        }
+    }
+
+    /// Decrypts a box, failing if it was sealed under another passphrase or tampered with
+    pub fn open(&self, sealed: &Sealed) -> Result<Vec<u8>, WalletError> {
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(&sealed.nonce),
+                sealed.ciphertext.as_slice(),
+            )
+            .map_err(|_| WalletError::WrongPassphrase)
+    }
+}
+
+/// Bytes encrypted by a sealing key, along with the salt and cost to derive that key again
+#[derive(Clone, Debug)]
+pub struct Sealed {
+    salt: [u8; KDF_SALT_BYTES],
+    memory: u32,
+    passes: u32,
+    nonce: [u8; NONCE_BYTES],
+    ciphertext: Vec<u8>,
+}
+
+impl Sealed {
+    pub fn serialize(&self) -> Vec<u8> {
+        self.salt
+            .iter()
+            .copied()
+            .chain(self.memory.to_be_bytes().iter().copied())
+            .chain(self.passes.to_be_bytes().iter().copied())
+            .chain(self.nonce.iter().copied())
+            .chain(self.ciphertext.len().to_be_bytes().iter().copied())
+            .chain(self.ciphertext.iter().copied())
+            .collect()
+    }
+
+    /// Fails on a key derivation cost below the one wallets are sealed with or above
+    /// `KDF_MAX_MEMORY_KIB` and `KDF_MAX_PASSES`, as the cost is not authenticated and a tampered
+    /// one would weaken the key or exhaust the memory
+    pub fn read(reader: &mut Reader) -> Result<Self, WalletError> {
+        let salt = reader.take(KDF_SALT_BYTES)?.try_into().unwrap();
+        let (memory, passes) = (reader.u32()?, reader.u32()?);
+        if !(KDF_MEMORY_KIB..=KDF_MAX_MEMORY_KIB).contains(&memory)
+            || !(KDF_PASSES..=KDF_MAX_PASSES).contains(&passes)
+        {
+            return Err(WalletError::InvalidWalletFile);
+        }
+        Ok(Self {
+            salt,
+            memory,
+            passes,
+            nonce: reader.take(NONCE_BYTES)?.try_into().unwrap(),
+            ciphertext: {
+                let len = reader.len()?;
+                reader.take(len)?.to_vec()
+            },
+        })
+    }
+}
+
+/// Cursor over the bytes of a wallet file, failing instead of panicking on malformed bytes
+///
+/// Unlike the secrets, the public part of a wallet file is not authenticated, so it is not
+/// trusted.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], WalletError> {
+        if len > self.bytes.len() {
+            return Err(WalletError::InvalidWalletFile);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Presence of an optional item, as serialized by `serialize_option`
+    pub fn flag(&mut self) -> Result<bool, WalletError> {
+        match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(WalletError::InvalidWalletFile),
+        }
+    }
+
+    pub fn u32(&mut self) -> Result<u32, WalletError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Length of the next list, as serialized by `usize::to_be_bytes`
+    pub fn len(&mut self) -> Result<usize, WalletError> {
+        Ok(usize::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn public_key(&mut self) -> Result<PublicKey, WalletError> {
+        PublicKey::from_slice(self.take(33)?).map_err(|_| WalletError::InvalidWalletFile)
+    }
+
+    pub fn key_hash(&mut self) -> Result<KeyHash, WalletError> {
+        Ok(self.take(KEY_HASH_BYTES)?.try_into().unwrap())
+    }
+
+    pub fn secret_key(&mut self) -> Result<SecretKey, WalletError> {
+        SecretKey::from_slice(self.take(32)?).map_err(|_| WalletError::InvalidWalletFile)
+    }
+
+    pub fn utxo(&mut self) -> Result<Utxo, WalletError> {
+        let utxo_id = UtxoId::deserialize(self.take(UTXO_ID_BYTES)?);
+        Ok(Utxo::new(utxo_id, self.utxo_data()?))
+    }
+
+    fn utxo_data(&mut self) -> Result<UtxoData, WalletError> {
+        UtxoData::deserialize(self.take(UTXO_DATA_BYTES)?).ok_or(WalletError::InvalidWalletFile)
+    }
+
+    pub fn transaction(&mut self) -> Result<Transaction, WalletError> {
+        let (transaction, size) =
+            Transaction::deserialize(self.bytes).ok_or(WalletError::InvalidWalletFile)?;
+        self.take(size)?;
+        Ok(transaction)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Serializes a list prefixed with its length, as read back with `Reader::len`
+pub fn serialize_list<T, F>(items: &[T], serialize: F) -> Vec<u8>
+where
+    F: Fn(&T) -> Vec<u8>,
+{
+    iter::once(items.len().to_be_bytes().to_vec())
+        .chain(items.iter().map(serialize))
+        .flatten()
+        .collect()
+}
+
+/// Serializes an optional item prefixed with its presence, as read back with `Reader::flag`
+pub fn serialize_option<T, F>(item: Option<&T>, serialize: F) -> Vec<u8>
+where
+    F: Fn(&T) -> Vec<u8>,
+{
+    match item {
+        Some(item) => iter::once(1).chain(serialize(item)).collect(),
+        None => vec![0],
+    }
+}
@@ -0,0 +1,358 @@
+use hmac::{Hmac, Mac};
+use secp256k1::{All, PublicKey, Secp256k1, SecretKey, Signing, Verification};
+use sha2::Sha512;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::constants::{EXTENDED_PUBLIC_KEY_BYTES, GAP_LIMIT, KEYCHAIN_BYTES};
+use crate::utxo::{key_hash, KeyHash};
+
+/// Index from which child keys are hardened, i.e. derived from the secret key of their parent
+pub const HARDENED: u32 = 1 << 31;
+
+/// HMAC-SHA512 (RFC 2104)
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_varkey(key).unwrap();
+    mac.input(data);
+    let mut code = [0u8; 64];
+    code.copy_from_slice(&mac.result().code());
+    code
+}
+
+/// Secret key along with the chain code its children are derived from (BIP32)
+#[derive(Clone, Eq, PartialEq)]
+pub struct ExtendedKey {
+    secret_key: SecretKey,
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from(hmac_sha512(b"Bitcoin seed", seed))
+    }
+
+    /// Child key at `index`, hardened from `HARDENED` on
+    ///
+    /// As BIP32 requires, an index giving an invalid key, which happens with probability lower
+    /// than 2^-127, is skipped for the next one.
+    pub fn derive<C: Signing>(&self, index: u32, secp: &Secp256k1<C>) -> Self {
+        (index..)
+            .find_map(|index| self.child_at(index, secp))
+            .unwrap()
+    }
+
+    fn child_at<C: Signing>(&self, index: u32, secp: &Secp256k1<C>) -> Option<Self> {
+        let mut data = Vec::with_capacity(33 + 4);
+        if index >= HARDENED {
+            data.push(0);
+            data.extend(&self.secret_key[..]);
+        } else {
+            data.extend(&PublicKey::from_secret_key(secp, &self.secret_key).serialize()[..]);
+        }
+        data.extend(&index.to_be_bytes());
+        let bytes = hmac_sha512(&self.chain_code, &data);
+        let mut secret_key = SecretKey::from_slice(&bytes[..32]).ok()?;
+        secret_key.add_assign(&self.secret_key[..]).ok()?;
+        Some(Self {
+            secret_key,
+            chain_code: bytes[32..].try_into().unwrap(),
+        })
+    }
+
+    /// Descendant key along a derivation path, e.g. `[HARDENED, 1]` for m/0'/1
+    pub fn derive_path<C: Signing>(&self, path: &[u32], secp: &Secp256k1<C>) -> Self {
+        path.iter()
+            .fold(self.clone(), |key, &index| key.derive(index, secp))
+    }
+
+    /// Extended public key deriving the same non-hardened children
+    pub fn public<C: Signing>(&self, secp: &Secp256k1<C>) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            public_key: PublicKey::from_secret_key(secp, &self.secret_key),
+            chain_code: self.chain_code,
+        }
+    }
+
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+}
+
+impl From<[u8; 64]> for ExtendedKey {
+    fn from(bytes: [u8; 64]) -> Self {
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&bytes[32..]);
+        Self {
+            secret_key: SecretKey::from_slice(&bytes[..32]).unwrap(),
+            chain_code,
+        }
+    }
+}
+
+impl fmt::Debug for ExtendedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExtendedKey {{ .. }}")
+    }
+}
+
+/// Public key along with the chain code its non-hardened children are derived from (BIP32)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExtendedPublicKey {
+    public_key: PublicKey,
+    chain_code: [u8; 32],
+}
+
+impl ExtendedPublicKey {
+    /// Child key at a non-hardened `index`, public counterpart of `ExtendedKey::derive` skipping
+    /// the same invalid indexes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is hardened.
+    pub fn derive<C: Verification>(&self, index: u32, secp: &Secp256k1<C>) -> Self {
+        assert!(index < HARDENED);
+        (index..)
+            .find_map(|index| self.child_at(index, secp))
+            .unwrap()
+    }
+
+    fn child_at<C: Verification>(&self, index: u32, secp: &Secp256k1<C>) -> Option<Self> {
+        let mut data = self.public_key.serialize().to_vec();
+        data.extend(&index.to_be_bytes());
+        let bytes = hmac_sha512(&self.chain_code, &data);
+        SecretKey::from_slice(&bytes[..32]).ok()?;
+        let mut public_key = self.public_key;
+        public_key.add_exp_assign(secp, &bytes[..32]).ok()?;
+        Some(Self {
+            public_key,
+            chain_code: bytes[32..].try_into().unwrap(),
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        self.public_key
+            .serialize()
+            .iter()
+            .chain(self.chain_code.iter())
+            .copied()
+            .collect()
+    }
+
+    /// Fails on malformed bytes, which may come from a tampered wallet file
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        if bytes.len() != EXTENDED_PUBLIC_KEY_BYTES {
+            return None;
+        }
+        Some(Self {
+            public_key: PublicKey::from_slice(&bytes[..33]).ok()?,
+            chain_code: bytes[33..].try_into().unwrap(),
+        })
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+}
+
+/// Derivation chain of a key: payments are received on one, change on the other
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Chain {
+    Receive = 0,
+    Change = 1,
+}
+
+/// Keys of a wallet derived from a seed, along m/0'/0/i for receiving and m/0'/1/i for change
+///
+/// Each chain is derived `GAP_LIMIT` keys past the last key used or handed out, so that payments
+/// to keys handed out by a restored wallet are recognised. Public keys are derived from the
+/// extended public keys of the chains, so a locked keychain, which forgot its secrets, still
+/// recognises its keys and hands out fresh ones.
+pub struct Keychain {
+    chains: [ExtendedPublicKey; 2],
+    secrets: Option<[ExtendedKey; 2]>,
+    keys: HashMap<KeyHash, (Chain, u32)>,
+    derived: [u32; 2],
+    issued: [u32; 2],
+    used: [u32; 2],
+    secp: Secp256k1<All>,
+}
+
+impl Keychain {
+    pub fn new(seed: &[u8]) -> Self {
+        let secp = Secp256k1::new();
+        let secrets = Self::secrets_of(seed, &secp);
+        let chains = [secrets[0].public(&secp), secrets[1].public(&secp)];
+        let mut keychain = Self::with(chains, [0; 2], [0; 2], secp);
+        keychain.secrets = Some(secrets);
+        keychain
+    }
+
+    fn with(
+        chains: [ExtendedPublicKey; 2],
+        issued: [u32; 2],
+        used: [u32; 2],
+        secp: Secp256k1<All>,
+    ) -> Self {
+        let mut keychain = Self {
+            chains,
+            secrets: None,
+            keys: HashMap::new(),
+            derived: [0; 2],
+            issued,
+            used,
+            secp,
+        };
+        keychain.look_ahead();
+        keychain
+    }
+
+    fn secrets_of(seed: &[u8], secp: &Secp256k1<All>) -> [ExtendedKey; 2] {
+        let account = ExtendedKey::master(seed).derive(HARDENED, secp);
+        [
+            account.derive(Chain::Receive as u32, secp),
+            account.derive(Chain::Change as u32, secp),
+        ]
+    }
+
+    /// Forgets the secret keys
+    pub fn lock(&mut self) {
+        self.secrets = None;
+    }
+
+    /// Recovers the secret keys from the seed and tells if it is the seed of the keychain
+    pub fn unlock(&mut self, seed: &[u8]) -> bool {
+        let secrets = Self::secrets_of(seed, &self.secp);
+        if [secrets[0].public(&self.secp), secrets[1].public(&self.secp)] != self.chains {
+            return false;
+        }
+        self.secrets = Some(secrets);
+        true
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.secrets.is_none()
+    }
+
+    /// Hands out a key of a chain that was never handed out before
+    pub fn fresh(&mut self, chain: Chain) -> PublicKey {
+        let index = self.issued[chain as usize];
+        self.issued[chain as usize] += 1;
+        self.look_ahead();
+        self.public_key_at(chain, index)
+    }
+
+    /// Records that the hash of a key received a payment and tells if it belongs to the keychain
+    pub fn mark_used(&mut self, key_hash: &KeyHash) -> bool {
+        let (chain, index) = match self.keys.get(key_hash) {
+            Some(&(chain, index)) => (chain, index),
+            None => return false,
+        };
+        let used = &mut self.used[chain as usize];
+        *used = (*used).max(index + 1);
+        self.look_ahead();
+        true
+    }
+
+    fn look_ahead(&mut self) {
+        for chain in [Chain::Receive, Chain::Change].iter().copied() {
+            let c = chain as usize;
+            let target = self.issued[c].max(self.used[c]) + GAP_LIMIT as u32;
+            while self.derived[c] < target {
+                let index = self.derived[c];
+                self.keys
+                    .insert(key_hash(&self.public_key_at(chain, index)), (chain, index));
+                self.derived[c] += 1;
+            }
+        }
+    }
+
+    fn public_key_at(&self, chain: Chain, index: u32) -> PublicKey {
+        *self.chains[chain as usize]
+            .derive(index, &self.secp)
+            .public_key()
+    }
+
+    pub fn contains(&self, key_hash: &KeyHash) -> bool {
+        self.keys.contains_key(key_hash)
+    }
+
+    /// Secret key of the hash of a derived key, unless the keychain is locked
+    pub fn secret_key_of(&self, key_hash: &KeyHash) -> Option<SecretKey> {
+        let (chain, index) = self.keys.get(key_hash)?;
+        let secrets = self.secrets.as_ref()?;
+        Some(
+            *secrets[*chain as usize]
+                .derive(*index, &self.secp)
+                .secret_key(),
+        )
+    }
+
+    /// Chain and index of the hash of a derived key
+    pub fn path_of(&self, key_hash: &KeyHash) -> Option<(Chain, u32)> {
+        self.keys.get(key_hash).copied()
+    }
+
+    /// Number of keys handed out on a chain
+    pub fn issued(&self, chain: Chain) -> u32 {
+        self.issued[chain as usize]
+    }
+
+    /// Number of keys derived on a chain, including the look-ahead ones
+    pub fn derived(&self, chain: Chain) -> u32 {
+        self.derived[chain as usize]
+    }
+
+    /// Serializes the extended public keys and counters, the secrets are never serialized
+    pub fn serialize(&self) -> Vec<u8> {
+        self.chains
+            .iter()
+            .flat_map(|chain| chain.serialize())
+            .chain(
+                self.issued
+                    .iter()
+                    .flat_map(|issued| issued.to_be_bytes().to_vec()),
+            )
+            .chain(
+                self.used
+                    .iter()
+                    .flat_map(|used| used.to_be_bytes().to_vec()),
+            )
+            .collect()
+    }
+
+    /// Locked keychain, failing on malformed bytes
+    pub fn deserialize<B>(bytes: B) -> Option<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        if bytes.len() != KEYCHAIN_BYTES {
+            return None;
+        }
+        let (chains, counters) = bytes.split_at(2 * EXTENDED_PUBLIC_KEY_BYTES);
+        let (receive, change) = chains.split_at(EXTENDED_PUBLIC_KEY_BYTES);
+        let counter = |i: usize| u32::from_be_bytes(counters[4 * i..4 * i + 4].try_into().unwrap());
+        Some(Self::with(
+            [
+                ExtendedPublicKey::deserialize(receive)?,
+                ExtendedPublicKey::deserialize(change)?,
+            ],
+            [counter(0), counter(1)],
+            [counter(2), counter(3)],
+            Secp256k1::new(),
+        ))
+    }
+}
@@ -0,0 +1,106 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use super::file::Reader;
+use crate::error::wallet::WalletError;
+use crate::Hash;
+
+/// Way coins moved in a transaction, from the point of view of the wallet
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    /// Spends no utxo of the wallet
+    Received = 0,
+    /// Spends utxos of the wallet and pays someone else
+    Sent = 1,
+    /// Spends utxos of the wallet and pays only the wallet
+    Internal = 2,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Received => write!(f, "received"),
+            Self::Sent => write!(f, "sent"),
+            Self::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+/// Transaction of the main chain spending or paying the wallet
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryEntry {
+    txid: Hash,
+    direction: Direction,
+    amount: i64,
+    block_id: Hash,
+    height: usize,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        txid: Hash,
+        direction: Direction,
+        amount: i64,
+        block_id: Hash,
+        height: usize,
+    ) -> Self {
+        Self {
+            txid,
+            direction,
+            amount,
+            block_id,
+            height,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        self.txid
+            .iter()
+            .copied()
+            .chain(Some(self.direction as u8))
+            .chain(self.amount.to_be_bytes().iter().copied())
+            .chain(self.block_id.iter().copied())
+            .chain(self.height.to_be_bytes().iter().copied())
+            .collect()
+    }
+
+    pub fn read(reader: &mut Reader) -> Result<Self, WalletError> {
+        let hash = |bytes: &[u8]| {
+            let bytes: [u8; 32] = bytes.try_into().unwrap();
+            Hash::from(bytes)
+        };
+        let txid = hash(reader.take(32)?);
+        let direction = match reader.take(1)?[0] {
+            0 => Direction::Received,
+            1 => Direction::Sent,
+            2 => Direction::Internal,
+            _ => return Err(WalletError::InvalidWalletFile),
+        };
+        let amount = i64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let block_id = hash(reader.take(32)?);
+        let height = usize::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        Ok(Self::new(txid, direction, amount, block_id, height))
+    }
+
+    pub fn txid(&self) -> &Hash {
+        &self.txid
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Change of the balance of the wallet, the fee included when the wallet paid it
+    pub fn amount(&self) -> i64 {
+        self.amount
+    }
+
+    /// Block of the main chain holding the transaction
+    pub fn block_id(&self) -> &Hash {
+        &self.block_id
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
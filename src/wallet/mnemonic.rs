@@ -0,0 +1,141 @@
+use hmac::Hmac;
+use pbkdf2::pbkdf2;
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+
+use crate::constants::{MNEMONIC_ENTROPY_BYTES, SEED_ROUNDS};
+use crate::error::wallet::WalletError;
+
+const CONSONANTS: [char; 16] = [
+    'b', 'd', 'f', 'g', 'h', 'j', 'k', 'l', 'm', 'n', 'p', 'r', 's', 't', 'v', 'z',
+];
+const MIDDLE_CONSONANTS: [char; 8] = ['b', 'd', 'k', 'l', 'm', 'n', 'r', 's'];
+const VOWELS: [char; 4] = ['a', 'e', 'i', 'o'];
+
+/// BIP39-style backup phrase: entropy followed by a checksum taken from its SHA-256 hash, split
+/// into words of 11 bits
+///
+/// Words are made of two syllables instead of being taken from the BIP39 English list, so
+/// phrases cannot be shared with other wallets.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    pub fn generate() -> Self {
+        let mut entropy = vec![0u8; MNEMONIC_ENTROPY_BYTES];
+        rand::thread_rng().fill_bytes(&mut entropy);
+        Self { entropy }
+    }
+
+    /// Fails unless the entropy is 16 to 32 bytes long, by steps of 4
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, WalletError> {
+        if entropy.len() < 16 || entropy.len() > 32 || entropy.len() % 4 != 0 {
+            return Err(WalletError::InvalidMnemonic);
+        }
+        Ok(Self {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// Reads a phrase back, checking its words and checksum
+    pub fn parse(phrase: &str) -> Result<Self, WalletError> {
+        let indexes = phrase
+            .split_whitespace()
+            .map(index_of)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(WalletError::InvalidMnemonic)?;
+        let bits = indexes.len() * 11;
+        let entropy_bits = bits * 32 / 33;
+        if bits != entropy_bits * 33 / 32 {
+            return Err(WalletError::InvalidMnemonic);
+        }
+        let mut bytes = vec![0u8; bits.div_ceil(8)];
+        for (i, index) in indexes.into_iter().enumerate() {
+            for bit in 0..11 {
+                if index & (1 << (10 - bit)) != 0 {
+                    let position = i * 11 + bit;
+                    bytes[position / 8] |= 0x80 >> (position % 8);
+                }
+            }
+        }
+        bytes.truncate(entropy_bits / 8);
+        let mnemonic = Self::from_entropy(&bytes)?;
+        if mnemonic
+            .to_string()
+            .split(' ')
+            .ne(phrase.split_whitespace())
+        {
+            return Err(WalletError::InvalidMnemonic);
+        }
+        Ok(mnemonic)
+    }
+
+    pub fn words(&self) -> Vec<String> {
+        let checksum = Sha256::digest(&self.entropy);
+        let bytes: Vec<u8> = self
+            .entropy
+            .iter()
+            .chain(checksum.iter())
+            .copied()
+            .collect();
+        let bits = self.entropy.len() * 8 * 33 / 32;
+        (0..bits / 11)
+            .map(|i| {
+                let index = (0..11).fold(0, |index, bit| {
+                    let position = i * 11 + bit;
+                    let is_set = bytes[position / 8] & (0x80 >> (position % 8)) != 0;
+                    index << 1 | is_set as usize
+                });
+                word_at(index)
+            })
+            .collect()
+    }
+
+    /// Seed of the keychain, stretched from the phrase and a passphrase with PBKDF2-HMAC-SHA512
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let password = self.to_string();
+        let salt = format!("mnemonic{}", passphrase);
+        let mut seed = [0u8; 64];
+        pbkdf2::<Hmac<Sha512>>(password.as_bytes(), salt.as_bytes(), SEED_ROUNDS, &mut seed);
+        seed
+    }
+
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.words().join(" "))
+    }
+}
+
+/// Word of an 11 bit index: consonant, vowel, consonant, vowel
+fn word_at(index: usize) -> String {
+    [
+        CONSONANTS[index >> 7],
+        VOWELS[index >> 5 & 3],
+        MIDDLE_CONSONANTS[index >> 2 & 7],
+        VOWELS[index & 3],
+    ]
+    .iter()
+    .collect()
+}
+
+fn index_of(word: &str) -> Option<usize> {
+    let letters: Vec<char> = word.chars().collect();
+    if letters.len() != 4 {
+        return None;
+    }
+    let position = |alphabet: &[char], letter| alphabet.iter().position(|&c| c == letter);
+    Some(
+        position(&CONSONANTS, letters[0])? << 7
+            | position(&VOWELS, letters[1])? << 5
+            | position(&MIDDLE_CONSONANTS, letters[2])? << 2
+            | position(&VOWELS, letters[3])?,
+    )
+}
@@ -1,41 +1,342 @@
 use rand::seq::IteratorRandom;
 use rand::Rng;
 use secp256k1::{PublicKey, SecretKey};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::fmt;
+use std::fs;
+use std::iter;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
+use self::balance::Balance;
+use self::coin_selection::{BranchAndBound, CoinSelection};
+use self::file::{Reader, Sealed, SealingKey};
+use self::hd::{Chain, Keychain};
+use self::history::{Direction, HistoryEntry};
+use self::mnemonic::Mnemonic;
+use crate::address::Address;
 use crate::block::Block;
 use crate::blockchain::Blockchain;
-use crate::constants::{DOUBLE_SPEND_PROBA, SPEND_PROBA, UTXO_HASH_INIT};
+use crate::constants::{
+    DOUBLE_SPEND_PROBA, KEYCHAIN_BYTES, MATURITY, SPEND_PROBA, TX_INPUT_BYTES, TX_OUTPUT_BYTES,
+    UTXO_HASH_INIT,
+};
 use crate::error::wallet::WalletError;
+use crate::transaction::partial::PartialTransaction;
 use crate::transaction::{Transaction, TransactionInput, TransactionOutput};
-use crate::utxo::{Utxo, UtxoData, UtxoId};
+use crate::utxo::{key_hash, KeyHash, OutputType, Utxo, UtxoId};
 use crate::Hash;
 
+/// Coins of a node
+///
+/// Keys are derived from a seed backed up as a mnemonic, with a fresh key for each payment
+/// received and each change output. Keys given outside the keychain, such as the node key
+/// holding the initial coins, are imported.
+///
+/// Once encrypted under a passphrase, the wallet can be locked: it forgets its secrets, keeping
+/// them sealed, and cannot sign until unlocked, but still tracks its utxos.
+///
+/// A watch-only wallet has no secrets nor keychain: it only tracks the utxos and history of
+/// watched keys.
 pub struct Wallet {
-    public_key: PublicKey,
-    secret_key: SecretKey,
+    keychain: Option<Keychain>,
+    imported: HashMap<KeyHash, PublicKey>,
+    watched: HashSet<KeyHash>,
+    secrets: Option<Secrets>,
+    sealing_key: Option<SealingKey>,
+    sealed: Option<Sealed>,
     recipients: Vec<PublicKey>,
     utxos: HashSet<Utxo>,
+    pending: HashMap<Transaction, Instant>,
+    coin_selection: Box<dyn CoinSelection>,
+    output_type: OutputType,
+    history: Vec<HistoryEntry>,
+    height: usize,
+}
+
+/// What a wallet forgets when locked
+struct Secrets {
+    mnemonic: Mnemonic,
+    seed: [u8; 64],
+    imported: HashMap<PublicKey, SecretKey>,
+}
+
+impl Secrets {
+    fn serialize(&self) -> Vec<u8> {
+        let imported: Vec<_> = self.imported.iter().collect();
+        iter::once(self.mnemonic.entropy().len() as u8)
+            .chain(self.mnemonic.entropy().iter().copied())
+            .chain(self.seed.iter().copied())
+            .chain(file::serialize_list(
+                &imported,
+                |(public_key, secret_key)| {
+                    public_key
+                        .serialize()
+                        .iter()
+                        .chain(secret_key[..].iter())
+                        .copied()
+                        .collect()
+                },
+            ))
+            .collect()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, WalletError> {
+        let mut reader = Reader::new(bytes);
+        let entropy_len = reader.take(1)?[0] as usize;
+        let mnemonic = Mnemonic::from_entropy(reader.take(entropy_len)?)?;
+        let seed = reader.take(64)?.try_into().unwrap();
+        let mut imported = HashMap::new();
+        for _ in 0..reader.len()? {
+            imported.insert(reader.public_key()?, reader.secret_key()?);
+        }
+        Ok(Self {
+            mnemonic,
+            seed,
+            imported,
+        })
+    }
 }
 
 impl Wallet {
+    /// Wallet with a new mnemonic, holding the utxos of an imported key
     pub fn new(
         public_key: PublicKey,
         secret_key: SecretKey,
         recipients: Vec<PublicKey>,
         utxos: HashSet<Utxo>,
     ) -> Self {
+        let mut wallet = Self::from_mnemonic(Mnemonic::generate(), "", recipients);
+        wallet.import(public_key, secret_key).unwrap();
+        wallet.utxos = utxos;
+        wallet
+    }
+
+    /// Empty wallet restored from a mnemonic and its passphrase, see `scan` to find its utxos
+    pub fn from_mnemonic(mnemonic: Mnemonic, passphrase: &str, recipients: Vec<PublicKey>) -> Self {
+        let seed = mnemonic.to_seed(passphrase);
         Self {
-            public_key,
-            secret_key,
+            keychain: Some(Keychain::new(&seed)),
+            imported: HashMap::new(),
+            watched: HashSet::new(),
+            secrets: Some(Secrets {
+                mnemonic,
+                seed,
+                imported: HashMap::new(),
+            }),
+            sealing_key: None,
+            sealed: None,
             recipients,
-            utxos,
+            utxos: HashSet::new(),
+            pending: HashMap::new(),
+            coin_selection: Box::new(BranchAndBound),
+            output_type: OutputType::Ecdsa,
+            history: Vec::new(),
+            height: 0,
+        }
+    }
+
+    /// Wallet tracking the outputs paying addresses, or public keys, without being able to spend
+    /// them, see `scan` to find its utxos
+    pub fn watch_only<A>(addresses: Vec<A>, recipients: Vec<PublicKey>) -> Self
+    where
+        A: Into<Address>,
+    {
+        Self {
+            keychain: None,
+            imported: HashMap::new(),
+            watched: addresses
+                .into_iter()
+                .map(|address| *address.into().key_hash())
+                .collect(),
+            secrets: None,
+            sealing_key: None,
+            sealed: None,
+            recipients,
+            utxos: HashSet::new(),
+            pending: HashMap::new(),
+            coin_selection: Box::new(BranchAndBound),
+            output_type: OutputType::Ecdsa,
+            history: Vec::new(),
+            height: 0,
+        }
+    }
+
+    /// Fails if the wallet is locked
+    pub fn import(
+        &mut self,
+        public_key: PublicKey,
+        secret_key: SecretKey,
+    ) -> Result<(), WalletError> {
+        let secrets = self.secrets.as_mut().ok_or(WalletError::Locked)?;
+        secrets.imported.insert(public_key, secret_key);
+        let key_hash = key_hash(&public_key);
+        self.imported.insert(key_hash, public_key);
+        self.watched.remove(&key_hash);
+        Ok(())
+    }
+
+    /// Tracks the outputs paying an address the wallet does not own, see `scan` to find its utxos
+    pub fn watch<A>(&mut self, address: A) -> bool
+    where
+        A: Into<Address>,
+    {
+        let key_hash = *address.into().key_hash();
+        !self.owns(&key_hash) && self.watched.insert(key_hash)
+    }
+
+    /// Encrypts the secrets under a passphrase, replacing the previous one, so that the wallet
+    /// can be locked and saved
+    pub fn encrypt(&mut self, passphrase: &str) -> Result<(), WalletError> {
+        let secrets = self.secrets.as_ref().ok_or(WalletError::Locked)?;
+        let sealing_key = SealingKey::new(passphrase);
+        self.sealed = Some(sealing_key.seal(&secrets.serialize()));
+        self.sealing_key = Some(sealing_key);
+        Ok(())
+    }
+
+    /// Forgets the secrets, keeping them sealed under the passphrase of the wallet
+    pub fn lock_keys(&mut self) -> Result<(), WalletError> {
+        if let Some(secrets) = self.secrets.take() {
+            let sealing_key = match self.sealing_key.take() {
+                Some(sealing_key) => sealing_key,
+                None => {
+                    self.secrets = Some(secrets);
+                    return Err(WalletError::NotEncrypted);
+                }
+            };
+            self.sealed = Some(sealing_key.seal(&secrets.serialize()));
+            if let Some(keychain) = self.keychain.as_mut() {
+                keychain.lock();
+            }
+        }
+        Ok(())
+    }
+
+    /// Recovers the secrets sealed under a passphrase
+    pub fn unlock_keys(&mut self, passphrase: &str) -> Result<(), WalletError> {
+        if self.secrets.is_some() {
+            return Ok(());
+        }
+        let sealed = self.sealed.as_ref().ok_or(WalletError::NotEncrypted)?;
+        let sealing_key = SealingKey::of(passphrase, sealed)?;
+        let secrets = Secrets::deserialize(&sealing_key.open(sealed)?)?;
+        if secrets.imported.keys().collect::<HashSet<_>>() != self.imported.values().collect()
+            || !self
+                .keychain
+                .as_mut()
+                .is_some_and(|keychain| keychain.unlock(&secrets.seed))
+        {
+            return Err(WalletError::InvalidWalletFile);
+        }
+        self.secrets = Some(secrets);
+        self.sealing_key = Some(sealing_key);
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.secrets.is_none()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.sealed.is_some()
+    }
+
+    /// Tells if the wallet has no secrets to unlock, only watched keys
+    pub fn is_watch_only(&self) -> bool {
+        self.keychain.is_none()
+    }
+
+    /// Rebuilds the utxos of the wallet from the main chain, discovering the derived keys that
+    /// received payments within the gap limit
+    pub fn scan(&mut self, blockchain: &Blockchain) {
+        self.utxos.clear();
+        self.history.clear();
+        self.height = 0;
+        for (utxo_id, utxo_data) in blockchain.initial_utxos() {
+            if self.tracks(utxo_data.key_hash()) {
+                self.utxos.insert(Utxo::new(*utxo_id, *utxo_data));
+            }
         }
+        for block in blockchain.main_chain().into_iter().rev() {
+            self.process(block);
+        }
+    }
+
+    /// Hands out a key never handed out before to receive a payment
+    pub fn receive_key(&mut self) -> Result<PublicKey, WalletError> {
+        let keychain = self.keychain.as_mut().ok_or(WalletError::WatchOnly)?;
+        Ok(keychain.fresh(Chain::Receive))
+    }
+
+    /// Same as `receive_key`, as an address of the output type of the wallet to give to the
+    /// payer
+    pub fn receive_address(&mut self) -> Result<Address, WalletError> {
+        let output_type = self.output_type;
+        self.receive_key()
+            .map(|public_key| Address::with_type(public_key, output_type))
+    }
+
+    /// Address of a fresh change key, of the output type of the wallet
+    fn change_address(&mut self) -> Result<Address, WalletError> {
+        let output_type = self.output_type;
+        self.change_key()
+            .map(|public_key| Address::with_type(public_key, output_type))
+    }
+
+    /// Hands out a key never handed out before to receive change
+    pub fn change_key(&mut self) -> Result<PublicKey, WalletError> {
+        let keychain = self.keychain.as_mut().ok_or(WalletError::WatchOnly)?;
+        Ok(keychain.fresh(Chain::Change))
+    }
+
+    /// Tells if the wallet can spend the outputs paying a key hash
+    pub fn owns(&self, key_hash: &KeyHash) -> bool {
+        self.imported.contains_key(key_hash)
+            || self
+                .keychain
+                .as_ref()
+                .is_some_and(|keychain| keychain.contains(key_hash))
+    }
+
+    /// Tells if the wallet tracks the outputs paying a key hash, owned or watched
+    pub fn tracks(&self, key_hash: &KeyHash) -> bool {
+        self.watched.contains(key_hash) || self.owns(key_hash)
+    }
+
+    /// Secret key of an owned key hash, unless the wallet is locked
+    pub fn secret_key_of(&self, key_hash: &KeyHash) -> Option<SecretKey> {
+        let secrets = self.secrets.as_ref()?;
+        self.imported
+            .get(key_hash)
+            .and_then(|public_key| secrets.imported.get(public_key))
+            .copied()
+            .or_else(|| self.keychain.as_ref()?.secret_key_of(key_hash))
+    }
+
+    /// Signs a transaction spending utxos of the wallet
+    pub fn sign(
+        &self,
+        utxos: &[Utxo],
+        outputs: Vec<TransactionOutput>,
+    ) -> Result<Transaction, WalletError> {
+        let secret_keys = utxos
+            .iter()
+            .map(|utxo| {
+                if !self.utxos.contains(utxo) {
+                    Err(WalletError::UnknownUtxo)
+                } else {
+                    self.secret_key_of(utxo.key_hash())
+                        .ok_or(WalletError::Locked)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Transaction::sign_utxos(utxos, outputs, &secret_keys))
     }
 
     pub fn add(&mut self, utxo: Utxo) -> Result<(), WalletError> {
-        if utxo.public_key() != self.public_key() {
+        if !self.tracks(utxo.key_hash()) {
             Err(WalletError::WrongPublicKey)
         } else {
             if self.utxos.insert(utxo) {
@@ -55,67 +356,238 @@ impl Wallet {
     }
 
     pub fn remove_if_utxo_from(&mut self, input: &TransactionInput) -> bool {
-        if let Some(utxo) = self
-            .utxos
+        self.utxos.remove(input.utxo_id())
+    }
+
+    /// Marks the inputs of a transaction as spent until it is mined or released
+    pub fn add_pending(&mut self, transaction: &Transaction) -> bool {
+        self.pending
+            .insert(transaction.clone(), Instant::now())
+            .is_none()
+    }
+
+    /// Releases the inputs of a pending transaction (e.g. evicted from the pool) so that they
+    /// can be spent again
+    pub fn release(&mut self, transaction: &Transaction) -> bool {
+        self.pending.remove(transaction).is_some()
+    }
+
+    /// Returns the pending transactions last broadcast more than `interval` ago, to be broadcast
+    /// again or abandoned
+    pub fn rebroadcast(&mut self, interval: Duration) -> Vec<Transaction> {
+        let now = Instant::now();
+        self.pending
+            .iter_mut()
+            .filter(|(_, broadcast)| now.duration_since(**broadcast) > interval)
+            .map(|(transaction, broadcast)| {
+                *broadcast = now;
+                transaction.clone()
+            })
+            .collect()
+    }
+
+    /// Gives up on a pending transaction that will not be mined (e.g. dropped by the transaction
+    /// pool), so that its inputs can be spent again
+    pub fn abandon(&mut self, transaction: &Transaction) -> bool {
+        self.release(transaction)
+    }
+
+    /// Owned utxos not spent by a pending transaction
+    pub fn spendable(&self) -> Vec<&Utxo> {
+        self.unspent()
+            .filter(|utxo| self.owns(utxo.key_hash()))
+            .collect()
+    }
+
+    /// Tracked utxos not spent by a pending transaction
+    fn unspent(&self) -> impl Iterator<Item = &Utxo> {
+        let pending: HashSet<&UtxoId> = self
+            .pending
+            .keys()
+            .flat_map(|tx| tx.inputs())
+            .map(|input| input.utxo_id())
+            .collect();
+        self.utxos
             .iter()
-            .filter(|utxo| utxo.id() == input.utxo_id())
-            .copied()
-            .last()
-        {
-            self.remove(&utxo).is_ok()
-        } else {
-            false
+            .filter(move |utxo| !pending.contains(utxo.id()))
+    }
+
+    /// Signs a transaction paying `amount` to `recipient` and the fee at `fee_rate` per 1000
+    /// bytes, out of utxos chosen by the coin selection strategy of the wallet
+    ///
+    /// The excess comes back to a fresh change key, unless it is worth less than the fee of the
+    /// change output. The transaction is pending until it is mined, released or abandoned, so
+    /// that its inputs are not spent twice.
+    pub fn create_payment<A>(
+        &mut self,
+        recipient: A,
+        amount: u32,
+        fee_rate: u32,
+    ) -> Result<Transaction, WalletError>
+    where
+        A: Into<Address>,
+    {
+        if self.is_locked() {
+            return Err(WalletError::Locked);
+        }
+        let spendable: Vec<Utxo> = self.spendable().into_iter().copied().collect();
+        let (utxos, outputs) = self.fund(&spendable, recipient.into(), amount, fee_rate)?;
+        let transaction = self.sign(&utxos, outputs)?;
+        self.add_pending(&transaction);
+        Ok(transaction)
+    }
+
+    /// Same as `create_payment`, but leaves the transaction to be signed, e.g. by an offline
+    /// wallet holding the secret keys of a watch-only one
+    ///
+    /// Watched utxos are spent as well, and a watch-only wallet sends the change back to the key
+    /// of the first input.
+    pub fn create_partial<A>(
+        &mut self,
+        recipient: A,
+        amount: u32,
+        fee_rate: u32,
+    ) -> Result<PartialTransaction, WalletError>
+    where
+        A: Into<Address>,
+    {
+        let unspent: Vec<Utxo> = self.unspent().copied().collect();
+        let (utxos, outputs) = self.fund(&unspent, recipient.into(), amount, fee_rate)?;
+        Ok(PartialTransaction::new(utxos, outputs))
+    }
+
+    /// Chooses utxos paying `amount` to `recipient` and the fee, along with the outputs
+    fn fund(
+        &mut self,
+        utxos: &[Utxo],
+        recipient: Address,
+        amount: u32,
+        fee_rate: u32,
+    ) -> Result<(Vec<Utxo>, Vec<TransactionOutput>), WalletError> {
+        let fee_of = |bytes: usize| (bytes as u64 * fee_rate as u64).div_ceil(1000);
+        let input_fee = fee_of(TX_INPUT_BYTES) as u32;
+        let target = amount as u64 + fee_of(1 + 3 * 8 + TX_OUTPUT_BYTES);
+        let cost_of_change = fee_of(TX_OUTPUT_BYTES);
+        let utxos = self
+            .coin_selection
+            .select(utxos, input_fee, target, cost_of_change)
+            .ok_or(WalletError::InsufficientFunds)?;
+
+        // A custom coin selection may pick too few utxos, or utxos not worth their input fee
+        let mut outputs = vec![TransactionOutput::new(amount, recipient)];
+        let value = utxos
+            .iter()
+            .map(|u| u64::from(u.amount()))
+            .sum::<u64>()
+            .checked_sub(u64::from(input_fee) * utxos.len() as u64)
+            .filter(|&value| value >= target)
+            .ok_or(WalletError::InsufficientFunds)?;
+        if value > target + cost_of_change {
+            let change = (value - target - cost_of_change)
+                .try_into()
+                .map_err(|_| WalletError::ChangeTooLarge)?;
+            let change_address = match self.change_address() {
+                Err(WalletError::WatchOnly) => utxos[0].utxo_data().address(),
+                change_address => change_address?,
+            };
+            outputs.push(TransactionOutput::new(change, change_address));
+        }
+        Ok((utxos, outputs))
+    }
+
+    /// Signs the inputs of a partial transaction spending utxos of the wallet, returning how
+    /// many
+    ///
+    /// Fails if an input pays a key of the wallet but is not one of its utxos, as its amount
+    /// cannot be trusted.
+    pub fn sign_partial(&self, partial: &mut PartialTransaction) -> Result<usize, WalletError> {
+        let mut secret_keys = HashMap::new();
+        for utxo in partial.inputs() {
+            if !self.owns(utxo.key_hash()) {
+                continue;
+            }
+            // Utxos compare by id only, so the amount and key are checked as well
+            if self.utxos.get(utxo).map(Utxo::data) != Some(utxo.data()) {
+                return Err(WalletError::UnknownUtxo);
+            }
+            let secret_key = self
+                .secret_key_of(utxo.key_hash())
+                .ok_or(WalletError::Locked)?;
+            secret_keys.insert(*utxo.key_hash(), secret_key);
         }
+        Ok(secret_keys
+            .values()
+            .map(|secret_key| partial.sign(secret_key))
+            .sum())
     }
 
+    pub fn set_coin_selection(&mut self, coin_selection: Box<dyn CoinSelection>) {
+        self.coin_selection = coin_selection;
+    }
+
+    /// Sets the output type of the addresses handed out and of the change outputs
+    pub fn set_output_type(&mut self, output_type: OutputType) {
+        self.output_type = output_type;
+    }
+
+    pub fn output_type(&self) -> OutputType {
+        self.output_type
+    }
+
+    /// Randomly pays a recipient, unless the wallet is locked, see `create_payment`
     pub fn initiate(&mut self) -> Option<Transaction> {
-        if self.utxos().is_empty() {
+        let spendable = self.spendable();
+        if spendable.is_empty() || self.is_locked() {
             return None;
         }
         let mut rng = rand::thread_rng();
         match rng.gen_bool(SPEND_PROBA) {
             false => None,
             true => {
-                let inputs_len = rng.gen_range(1, self.utxos().len() + 1);
-                let utxos = self.utxos().iter().choose_multiple(&mut rng, inputs_len);
-                let utxo_ids = utxos.iter().map(|u| *u.utxo_id()).collect();
-                let mut amount: u32 = utxos.iter().map(|u| u.amount()).sum();
-                let mut outputs = Vec::new();
-                loop {
-                    let amount1 = rng.gen_range(1, amount + 1);
-                    let recipient = *self.recipients.iter().choose(&mut rng).unwrap();
-                    let output = TransactionOutput::new(amount1, recipient);
-                    outputs.push(output);
-                    amount -= amount1;
-                    if amount == 0 {
-                        break;
-                    }
+                let inputs_len = rng.gen_range(1, spendable.len() + 1);
+                let utxos: Vec<Utxo> = spendable
+                    .into_iter()
+                    .copied()
+                    .choose_multiple(&mut rng, inputs_len);
+                let amount: u32 = utxos.iter().map(|u| u.amount()).sum();
+                let paid = rng.gen_range(1, amount + 1);
+                let recipient = *self.recipients.iter().choose(&mut rng).unwrap();
+                let mut outputs = vec![TransactionOutput::new(paid, recipient)];
+                if paid < amount {
+                    outputs.push(TransactionOutput::new(
+                        amount - paid,
+                        self.change_address().ok()?,
+                    ));
                 }
-                let transaction = Transaction::sign(utxo_ids, outputs, &self.secret_key);
+                let transaction = self.sign(&utxos, outputs).ok()?;
+                self.add_pending(&transaction);
                 Some(transaction)
             }
         }
     }
 
     pub fn double_spend(&mut self) -> Option<(Transaction, Transaction)> {
-        if self.utxos().is_empty() || self.recipients.len() < 2 {
+        let owned: Vec<Utxo> = self
+            .utxos
+            .iter()
+            .filter(|utxo| self.owns(utxo.key_hash()))
+            .copied()
+            .collect();
+        if owned.is_empty() || self.recipients.len() < 2 || self.is_locked() {
             return None;
         }
         let mut rng = rand::thread_rng();
         match rng.gen_bool(DOUBLE_SPEND_PROBA) {
             false => None,
             true => {
-                let utxo = self.utxos().iter().choose(&mut rng).unwrap();
-                let utxo_ids = vec![*utxo.utxo_id()];
+                let utxo = *owned.iter().choose(&mut rng).unwrap();
                 let recipients = self.recipients.iter().choose_multiple(&mut rng, 2);
 
                 let output1 = TransactionOutput::new(utxo.amount(), *recipients[0]);
-                let outputs1 = vec![output1];
-                let transaction1 = Transaction::sign(utxo_ids.clone(), outputs1, &self.secret_key);
+                let transaction1 = self.sign(&[utxo], vec![output1]).ok()?;
 
                 let output2 = TransactionOutput::new(utxo.amount(), *recipients[1]);
-                let outputs2 = vec![output2];
-                let transaction2 = Transaction::sign(utxo_ids, outputs2, &self.secret_key);
+                let transaction2 = self.sign(&[utxo], vec![output2]).ok()?;
 
                 Some((transaction1, transaction2))
             }
@@ -127,21 +599,59 @@ impl Wallet {
             self.remove_if_utxo_from(input);
         }
         for (vout, output) in transaction.outputs().iter().enumerate() {
-            if output.public_key() != self.public_key() {
+            let marked = self
+                .keychain
+                .as_mut()
+                .is_some_and(|keychain| keychain.mark_used(output.key_hash()));
+            if !marked && !self.tracks(output.key_hash()) {
                 continue;
             }
-            let utxo = Utxo::new(
-                UtxoId::new(*transaction.id(), vout),
-                UtxoData::new(output.amount(), *output.public_key()),
-            );
+            let utxo = Utxo::new(UtxoId::new(*transaction.id(), vout), *output.utxo_data());
             self.add(utxo).unwrap();
         }
     }
 
     pub fn process(&mut self, block: &Block) {
         for transaction in block.transactions() {
+            self.pending
+                .retain(|tx, _| !tx.shares_utxo_with(transaction));
+            let spent: u64 = transaction
+                .inputs()
+                .iter()
+                .filter_map(|input| self.utxos.get(input.utxo_id()))
+                .map(|utxo| utxo.amount() as u64)
+                .sum();
             self.process_t(transaction);
+            self.record(transaction, spent, block);
         }
+        self.height = block.height();
+    }
+
+    /// Adds a transaction of the main chain to the history if it spent or paid the wallet
+    fn record(&mut self, transaction: &Transaction, spent: u64, block: &Block) {
+        let (owned, foreign): (Vec<&TransactionOutput>, Vec<_>) = transaction
+            .outputs()
+            .iter()
+            .partition(|output| self.tracks(output.key_hash()));
+        if spent == 0 && owned.is_empty() {
+            return;
+        }
+        let direction = if spent == 0 {
+            Direction::Received
+        } else if foreign.is_empty() {
+            Direction::Internal
+        } else {
+            Direction::Sent
+        };
+        let received: u64 = owned.iter().map(|output| output.amount() as u64).sum();
+        let entry = HistoryEntry::new(
+            *transaction.id(),
+            direction,
+            received as i64 - spent as i64,
+            block.id(),
+            block.height(),
+        );
+        self.history.push(entry);
     }
 
     pub fn process_all(&mut self, blocks: &[Block]) {
@@ -152,13 +662,10 @@ impl Wallet {
 
     pub fn undo_t(&mut self, transaction: &Transaction, blockchain: &Blockchain) {
         for (vout, output) in transaction.outputs().iter().enumerate() {
-            if output.public_key() != self.public_key() {
+            if !self.tracks(output.key_hash()) {
                 continue;
             }
-            let utxo = Utxo::new(
-                UtxoId::new(*transaction.id(), vout),
-                UtxoData::new(output.amount(), *output.public_key()),
-            );
+            let utxo = Utxo::new(UtxoId::new(*transaction.id(), vout), *output.utxo_data());
             self.remove(&utxo).unwrap();
         }
 
@@ -167,12 +674,12 @@ impl Wallet {
                 let utxo_id = UtxoId::new(*input.txid(), input.vout());
                 let utxo_data = blockchain.initial_utxos()[&utxo_id];
                 let utxo = Utxo::new(utxo_id, utxo_data);
-                if utxo.public_key() == self.public_key() {
+                if self.tracks(utxo.key_hash()) {
                     self.add(utxo).unwrap();
                 }
             } else {
                 let utxo = blockchain.get_utxo(input.utxo_id(), blockchain.top());
-                if utxo.public_key() == self.public_key() {
+                if self.tracks(utxo.key_hash()) {
                     self.add(utxo).unwrap();
                 }
             }
@@ -180,9 +687,12 @@ impl Wallet {
     }
 
     pub fn undo(&mut self, block: &Block, blockchain: &Blockchain) {
-        for transaction in block.transactions() {
+        for transaction in block.transactions().iter().rev() {
             self.undo_t(transaction, blockchain);
         }
+        let block_id = block.id();
+        self.history.retain(|entry| *entry.block_id() != block_id);
+        self.height = block.height() - 1;
     }
 
     pub fn undo_all(&mut self, blocks: &[Block], blockchain: &Blockchain) {
@@ -201,28 +711,220 @@ impl Wallet {
         self.process_all(blocks_to_process);
     }
 
-    pub fn public_key(&self) -> &PublicKey {
-        &self.public_key
+    /// Serializes the wallet, its secrets sealed under its passphrase unless it is watch-only
+    pub fn serialize(&self) -> Result<Vec<u8>, WalletError> {
+        let sealed = match (&self.secrets, &self.sealing_key, &self.sealed) {
+            (Some(secrets), Some(sealing_key), _) => Some(sealing_key.seal(&secrets.serialize())),
+            (None, _, Some(sealed)) => Some(sealed.clone()),
+            (None, _, None) => None,
+            _ => return Err(WalletError::NotEncrypted),
+        };
+        let imported: Vec<_> = self.imported.values().collect();
+        let watched: Vec<_> = self.watched.iter().collect();
+        let utxos: Vec<_> = self.utxos.iter().collect();
+        let pending: Vec<_> = self.pending.keys().collect();
+        let public_key = |public_key: &&PublicKey| public_key.serialize().to_vec();
+        Ok(iter::once(b'w')
+            .chain(file::serialize_option(sealed.as_ref(), Sealed::serialize))
+            .chain(file::serialize_option(self.keychain.as_ref(), |keychain| {
+                keychain.serialize()
+            }))
+            .chain(file::serialize_list(&imported, public_key))
+            .chain(file::serialize_list(&watched, |key_hash| key_hash.to_vec()))
+            .chain(file::serialize_list(
+                &self.recipients.iter().collect::<Vec<_>>(),
+                public_key,
+            ))
+            .chain(file::serialize_list(&utxos, |utxo| {
+                utxo.utxo_id()
+                    .serialize()
+                    .into_iter()
+                    .chain(utxo.utxo_data().serialize())
+                    .collect()
+            }))
+            .chain(file::serialize_list(&pending, |tx| tx.serialize()))
+            .chain(iter::once(self.output_type as u8))
+            .chain(self.height.to_be_bytes().iter().copied())
+            .chain(file::serialize_list(&self.history, |entry| {
+                entry.serialize()
+            }))
+            .collect())
+    }
+
+    /// Deserializes a locked or watch-only wallet, failing on malformed bytes
+    pub fn deserialize<B>(bytes: B) -> Result<Self, WalletError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let mut reader = Reader::new(bytes.as_ref());
+        if reader.take(1)? != b"w" {
+            return Err(WalletError::InvalidWalletFile);
+        }
+        let sealed = match reader.flag()? {
+            true => Some(Sealed::read(&mut reader)?),
+            false => None,
+        };
+        let keychain = match reader.flag()? {
+            true => Some(
+                Keychain::deserialize(reader.take(KEYCHAIN_BYTES)?)
+                    .ok_or(WalletError::InvalidWalletFile)?,
+            ),
+            false => None,
+        };
+        let imported = (0..reader.len()?)
+            .map(|_| {
+                reader
+                    .public_key()
+                    .map(|public_key| (key_hash(&public_key), public_key))
+            })
+            .collect::<Result<_, _>>()?;
+        let watched = (0..reader.len()?)
+            .map(|_| reader.key_hash())
+            .collect::<Result<_, _>>()?;
+        let recipients = (0..reader.len()?)
+            .map(|_| reader.public_key())
+            .collect::<Result<_, _>>()?;
+        let utxos = (0..reader.len()?)
+            .map(|_| reader.utxo())
+            .collect::<Result<_, _>>()?;
+        let pending = (0..reader.len()?)
+            .map(|_| Ok((reader.transaction()?, Instant::now())))
+            .collect::<Result<_, _>>()?;
+        let output_type =
+            OutputType::from_byte(reader.take(1)?[0]).ok_or(WalletError::InvalidWalletFile)?;
+        let height = usize::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let history = (0..reader.len()?)
+            .map(|_| HistoryEntry::read(&mut reader))
+            .collect::<Result<_, _>>()?;
+        // Secrets are sealed along with a keychain, or not at all
+        if !reader.is_empty() || sealed.is_some() != keychain.is_some() {
+            return Err(WalletError::InvalidWalletFile);
+        }
+        Ok(Self {
+            keychain,
+            imported,
+            watched,
+            secrets: None,
+            sealing_key: None,
+            sealed,
+            recipients,
+            utxos,
+            pending,
+            coin_selection: Box::new(BranchAndBound),
+            output_type,
+            history,
+            height,
+        })
+    }
+
+    /// Writes the wallet to a file, see `serialize`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), WalletError> {
+        fs::write(path, self.serialize()?).map_err(WalletError::Io)
+    }
+
+    /// Reads a locked or watch-only wallet from a file, see `deserialize`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, WalletError> {
+        Self::deserialize(fs::read(path).map_err(WalletError::Io)?)
+    }
+
+    /// Mnemonic to back the wallet up, unless it is locked
+    pub fn mnemonic(&self) -> Option<&Mnemonic> {
+        self.secrets.as_ref().map(|secrets| &secrets.mnemonic)
+    }
+
+    /// Keychain of the wallet, unless it is watch-only
+    pub fn keychain(&self) -> Option<&Keychain> {
+        self.keychain.as_ref()
     }
 
     pub fn utxos(&self) -> &HashSet<Utxo> {
         &self.utxos
     }
+
+    /// Transactions of the wallet waiting to be mined
+    pub fn pending(&self) -> Vec<&Transaction> {
+        self.pending.keys().collect()
+    }
+
+    /// Balance of the coins the wallet will hold once its pending transactions are mined
+    ///
+    /// Utxos confirmed by fewer than `MATURITY` blocks are immature, as a reorg may still undo
+    /// them. The outputs of pending transactions paying the wallet, such as change, are pending.
+    pub fn balance(&self) -> Balance {
+        let (mut confirmed, mut immature) = (0, 0);
+        for utxo in self.unspent() {
+            let is_mature = self
+                .entry_of(utxo.txid())
+                .is_none_or(|entry| self.confirmations_of(entry) >= MATURITY);
+            if is_mature {
+                confirmed += utxo.amount() as u64;
+            } else {
+                immature += utxo.amount() as u64;
+            }
+        }
+        let pending = self
+            .pending
+            .keys()
+            .flat_map(|tx| tx.outputs())
+            .filter(|output| self.tracks(output.key_hash()))
+            .map(|output| output.amount() as u64)
+            .sum();
+        Balance::new(confirmed, immature, pending)
+    }
+
+    /// Transactions of the main chain spending or paying the wallet, the oldest first
+    pub fn history(&self) -> &Vec<HistoryEntry> {
+        &self.history
+    }
+
+    pub fn entry_of(&self, txid: &Hash) -> Option<&HistoryEntry> {
+        self.history.iter().find(|entry| entry.txid() == txid)
+    }
+
+    /// Number of blocks from the block holding a transaction of the history to the last one
+    /// processed, both included
+    pub fn confirmations_of(&self, entry: &HistoryEntry) -> usize {
+        (self.height + 1).saturating_sub(entry.height())
+    }
+
+    /// Height of the last block processed
+    pub fn height(&self) -> usize {
+        self.height
+    }
 }
 
 impl fmt::Display for Wallet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Wallet ({}) {{", self.utxos.len())?;
+        write!(f, "\n  {}\n", self.balance())?;
         for utxo in &self.utxos {
             write!(
                 f,
-                "\n  txid: {:x}  vout:{}\n  public_key: {}  amount: {}\n",
+                "\n  txid: {:x}  vout:{}\n  address: {}  amount: {}\n",
                 utxo.txid(),
                 utxo.vout(),
-                utxo.public_key(),
+                utxo.utxo_data().address(),
                 utxo.amount()
             )?;
         }
+        for entry in &self.history {
+            write!(
+                f,
+                "\n  {} {:+}  txid: {:x}\n  height: {}  confirmations: {}\n",
+                entry.direction(),
+                entry.amount(),
+                entry.txid(),
+                entry.height(),
+                self.confirmations_of(entry)
+            )?;
+        }
         write!(f, "}}\n")
     }
 }
+
+pub mod balance;
+pub mod coin_selection;
+pub mod file;
+pub mod hd;
+pub mod history;
+pub mod mnemonic;
@@ -0,0 +1,146 @@
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+use blockchain::address::{self, Address};
+use blockchain::error::address::AddressError;
+use blockchain::transaction::TransactionOutput;
+use blockchain::utxo::{self as utxo, OutputType};
+
+pub mod common;
+
+const CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+#[test]
+fn address_test_vector() {
+    let secret_key = SecretKey::from_slice(&[[0; 31].as_ref(), &[1]].concat()).unwrap();
+    let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+    // First 20 bytes of the SHA-256 of the compressed generator
+    let key_hash = [
+        0x0f, 0x71, 0x5b, 0xaf, 0x5d, 0x4c, 0x2e, 0xd3, 0x29, 0x78, 0x5c, 0xef, 0x29, 0xe5, 0x62,
+        0xf7, 0x34, 0x88, 0xc8, 0xa2,
+    ];
+    assert_eq!(utxo::key_hash(&public_key), key_hash);
+    let address = "sim1qpac4ht6afshdx2tctnhjnetz7u6g3j9zaxk5fe";
+    assert_eq!(Address::new(public_key).to_string(), address);
+    assert_eq!(address.parse::<Address>().unwrap().key_hash(), &key_hash);
+    let upper = address.to_uppercase().parse::<Address>().unwrap();
+    assert_eq!(upper.key_hash(), &key_hash);
+}
+
+/// Valid Bech32m strings of BIP350
+#[test]
+fn bech32m_test_vectors() {
+    for string in &[
+        "A1LQFN3A",
+        "a1lqfn3a",
+        "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+        "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+        "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+        "?1v759aa",
+    ] {
+        let (prefix, values) = address::decode(string).unwrap();
+        assert_eq!(address::encode(&prefix, &values), string.to_lowercase());
+    }
+    // Checksums of Bech32 rather than Bech32m
+    for string in &[
+        "A12UEL5L",
+        "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+    ] {
+        assert!(matches!(
+            address::decode(string),
+            Err(AddressError::WrongChecksum)
+        ));
+    }
+}
+
+/// Taproot address of BIP350 paying the x-coordinate of the generator, the key of secret 1
+#[test]
+fn taproot_test_vector() {
+    let secret_key = SecretKey::from_slice(&[[0; 31].as_ref(), &[1]].concat()).unwrap();
+    let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+    let mut values = vec![OutputType::Schnorr as u8];
+    values.extend(address::regroup(&public_key.serialize()[1..], 8, 5, true).unwrap());
+    let address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+    assert_eq!(address::encode("bc", &values), address);
+    assert_eq!(
+        address::decode(address).unwrap(),
+        ("bc".to_string(), values)
+    );
+}
+
+#[test]
+fn address_round_trip() {
+    let public_key = common::random_public_key();
+    let address = Address::new(public_key);
+    assert_eq!(address.to_string().parse::<Address>().unwrap(), address);
+    let output = TransactionOutput::new(1, address);
+    assert!(output.utxo_data().pays(&public_key));
+    assert!(!output.utxo_data().pays(&common::random_public_key()));
+    assert_eq!(output.address(), address);
+}
+
+#[test]
+fn address_rejects_programs_other_than_key_hashes() {
+    let public_key = common::random_public_key();
+    for program in &[&public_key.serialize()[..], &[0; 19], &[0; 21]] {
+        let mut values = vec![OutputType::Ecdsa as u8];
+        values.extend(address::regroup(program, 8, 5, true).unwrap());
+        assert!(matches!(
+            address::encode("sim", &values).parse::<Address>(),
+            Err(AddressError::InvalidProgram)
+        ));
+    }
+}
+
+#[test]
+fn address_keeps_output_type() {
+    let public_key = common::random_public_key();
+    let address = Address::with_type(public_key, OutputType::Schnorr);
+    assert!(address.to_string().starts_with("sim1p"));
+    let parsed = address.to_string().parse::<Address>().unwrap();
+    assert_eq!(parsed.output_type(), OutputType::Schnorr);
+    assert_ne!(parsed, Address::new(public_key));
+    let output = TransactionOutput::new(1, address);
+    assert_eq!(output.output_type(), OutputType::Schnorr);
+    assert_eq!(output.address(), address);
+}
+
+#[test]
+fn address_rejects_typos() {
+    let address = Address::new(common::random_public_key()).to_string();
+    let prefix_len = "sim1".len();
+    for (i, c) in address.char_indices().skip(prefix_len) {
+        for typo in CHARSET.chars().filter(|typo| *typo != c) {
+            let mut chars: Vec<char> = address.chars().collect();
+            chars[i] = typo;
+            let typo: String = chars.into_iter().collect();
+            assert!(matches!(
+                typo.parse::<Address>(),
+                Err(AddressError::WrongChecksum)
+            ));
+        }
+    }
+    for i in prefix_len..address.len() - 1 {
+        let mut chars: Vec<char> = address.chars().collect();
+        chars.swap(i, i + 1);
+        let swapped: String = chars.into_iter().collect();
+        assert!(swapped == address || swapped.parse::<Address>().is_err());
+    }
+    assert!(matches!(
+        address[..address.len() - 1].parse::<Address>(),
+        Err(AddressError::WrongChecksum)
+    ));
+    assert!(matches!(
+        (address[..address.len() - 1].to_string() + "b").parse::<Address>(),
+        Err(AddressError::InvalidCharacter)
+    ));
+    let (_, values) = address::decode(&address).unwrap();
+    assert!(matches!(
+        address::encode("bc", &values).parse::<Address>(),
+        Err(AddressError::WrongNetwork(_))
+    ));
+    let mixed = address[..5].to_uppercase() + &address[5..];
+    assert!(matches!(
+        mixed.parse::<Address>(),
+        Err(AddressError::MixedCase)
+    ));
+}
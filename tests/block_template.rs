@@ -0,0 +1,130 @@
+use log::info;
+use std::borrow::Cow;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use blockchain::block::blockheader::BlockHeader;
+use blockchain::block::Block;
+use blockchain::consensus::proof_of_authority::ProofOfAuthority;
+use blockchain::consensus::proof_of_work::ProofOfWork;
+use blockchain::constants::NODES;
+use blockchain::error::block::BlockError;
+use blockchain::miner::template::BlockTemplate;
+use blockchain::network::Network;
+use blockchain::node::message::Message;
+use blockchain::utxo_pool::UtxoPool;
+
+pub mod common;
+
+fn solve(template: &BlockTemplate) -> BlockHeader {
+    let mut header = template.header().clone();
+    while !header.has_proof_of_work() {
+        header.inc_nonce();
+    }
+    header
+}
+
+fn random_template() -> BlockTemplate {
+    let transactions = vec![
+        common::random_transaction(None, None),
+        common::random_transaction(None, None),
+    ];
+    BlockTemplate::new(Block::new(&Block::genesis(), transactions).unwrap())
+}
+
+#[test]
+fn block_template_ser_deser() {
+    let template = random_template();
+    let message = Message::BlockTemplate(Cow::Borrowed(&template));
+    assert!(Message::deserialize(message.serialize()).unwrap() == message);
+
+    let header = solve(&template);
+    let message = Message::SubmitBlock(header.clone());
+    assert!(Message::deserialize(message.serialize()).unwrap() == message);
+}
+
+#[test]
+fn block_template_solve() {
+    let template = random_template();
+    let genesis = Block::genesis();
+    let utxo_pool = UtxoPool::new();
+    let engine = ProofOfWork::new(1);
+    let header = solve(&template);
+    let block = template
+        .solve(&header, &genesis, &utxo_pool, &engine)
+        .unwrap();
+    assert_eq!(block.hash(), header.hash());
+    assert_eq!(block.transactions(), template.transactions());
+
+    let mut unsolved = header.clone();
+    while unsolved.has_proof_of_work() {
+        unsolved.inc_nonce();
+    }
+    match template.solve(&unsolved, &genesis, &utxo_pool, &engine) {
+        Err(BlockError::InsufficientProofOfWork) => {}
+        _ => panic!("Unsolved header accepted"),
+    }
+    match random_template().solve(&header, &genesis, &utxo_pool, &engine) {
+        Err(BlockError::UnknownTemplate) => {}
+        _ => panic!("Header of another template accepted"),
+    }
+
+    let (public_key, secret_key) = common::random_key();
+    let engine = ProofOfAuthority::new(vec![public_key], public_key, secret_key);
+    match template.solve(&header, &genesis, &utxo_pool, &engine) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Unsealed block accepted by proof of authority"),
+    }
+}
+
+#[test]
+fn external_miner() {
+    common::log_setup();
+
+    let mut network = Network::random(NODES, 0);
+    let (miner_sender, miner_listener) = mpsc::channel();
+    let node = network
+        .nodes_mut()
+        .iter_mut()
+        .filter_map(|n| n.as_mut())
+        .find(|n| n.id() == 0)
+        .unwrap();
+    node.connect_miner(miner_sender);
+    let sender = node.sender().clone();
+    info!("Network:\n{:?}", network);
+
+    network.run();
+    let mut submitted = Vec::new();
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(3) {
+        let bytes = Arc::new(Message::GetBlockTemplate.serialize());
+        sender.send((None, bytes)).unwrap();
+        if let Ok((_, bytes)) = miner_listener.recv_timeout(Duration::from_millis(100)) {
+            if let Ok(Message::BlockTemplate(template)) = Message::deserialize(&*bytes) {
+                let header = solve(&template);
+                submitted.push(header.hash());
+                let bytes = Arc::new(Message::SubmitBlock(header).serialize());
+                sender.send((None, bytes)).unwrap();
+            }
+        }
+    }
+
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+
+    let nodes = network.nodes_as_ref();
+    let node = nodes.iter().find(|n| n.id() == 0).unwrap();
+    assert!(node.miner().blocks().is_empty());
+    let accepted: Vec<_> = submitted
+        .into_iter()
+        .filter(|&hash| node.blockchain().contains(hash))
+        .collect();
+    assert!(!accepted.is_empty());
+    for node in &nodes {
+        for &hash in &accepted {
+            assert!(node.blockchain().contains(hash));
+        }
+    }
+}
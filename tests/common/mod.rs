@@ -4,8 +4,9 @@ use std::sync::Once;
 
 use rand::Rng;
 use rand_core::RngCore;
-use secp256k1::{PublicKey, Secp256k1, SecretKey, Signature};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
 
+use blockchain::block::Block;
 use blockchain::transaction::{Transaction, TransactionInput, TransactionOutput};
 use blockchain::utxo::{Utxo, UtxoData, UtxoId};
 use blockchain::utxo_pool::UtxoPool;
@@ -28,7 +29,7 @@ pub fn log_setup() {
     });
 }
 
-fn random_hash() -> Hash {
+pub fn random_hash() -> Hash {
     let mut hash = [0u8; 32];
     rand::thread_rng().fill_bytes(&mut hash);
     Hash::from(hash)
@@ -58,12 +59,9 @@ pub fn random_utxo_with(
 }
 
 pub fn random_utxo(id: Option<UtxoId>, data: Option<UtxoData>) -> Utxo {
-    random_utxo_with(
-        id.map(|id| *id.txid()),
-        id.map(|id| id.vout()),
-        data.map(|data| data.amount()),
-        data.map(|data| *data.public_key()),
-    )
+    let utxo_id = random_utxo_id(id.map(|id| *id.txid()), id.map(|id| id.vout()));
+    let utxo_data = data.unwrap_or_else(|| random_utxo_data(None, None));
+    Utxo::new(utxo_id, utxo_data)
 }
 
 pub fn random_public_key() -> PublicKey {
@@ -84,8 +82,7 @@ pub fn random_transaction_input(txid: Option<Hash>, vout: Option<usize>) -> Tran
     let utxo_id = random_utxo_id(txid, vout);
     let mut sig = [0u8; 64];
     rand::thread_rng().fill_bytes(&mut sig);
-    let sig = Signature::from_compact(&sig).unwrap();
-    TransactionInput::new(utxo_id, sig)
+    TransactionInput::new(utxo_id, random_public_key(), sig)
 }
 
 pub fn random_transaction_output(
@@ -119,6 +116,11 @@ pub fn random_transaction(
     }
 }
 
+pub fn random_block(parent: &Block) -> Block {
+    let transactions = vec![random_transaction(None, None)];
+    Block::new(parent, transactions).unwrap()
+}
+
 pub fn random_transaction_with(
     sender: Option<SecretKey>,
     recipients: Option<Vec<PublicKey>>,
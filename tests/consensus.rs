@@ -83,6 +83,10 @@ fn consensus_after_double_spend() {
         let wallet: HashSet<Utxo> = honest_nodes[i].wallet().utxos().iter().copied().collect();
         let utxo_pool: HashSet<Utxo> = honest_nodes[i].utxo_pool().into();
         assert!(wallet.is_subset(&utxo_pool));
+        let transaction_pool = honest_nodes[i].transaction_pool();
+        for transaction in transaction_pool.transactions() {
+            assert_eq!(transaction_pool.conflicts_of(transaction).count(), 1);
+        }
     }
 
     let sets = network::partition(&honest_nodes, |n1, n2| n1.blockchain() == n2.blockchain());
@@ -0,0 +1,116 @@
+use log::info;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::block::Block;
+use blockchain::consensus::proof_of_authority::ProofOfAuthority;
+use blockchain::consensus::proof_of_work::ProofOfWork;
+use blockchain::consensus::ConsensusEngine;
+use blockchain::constants::NODES;
+use blockchain::error::block::BlockError;
+use blockchain::network::{self, Network};
+use blockchain::node::message::Message;
+use blockchain::utxo_pool::UtxoPool;
+
+pub mod common;
+
+#[test]
+fn proof_of_work_seal() {
+    let utxo_pool = UtxoPool::new();
+    let mut engine = ProofOfWork::new(1);
+    let genesis = Block::genesis();
+    let mut block = common::random_block(&genesis);
+    while block.check_proof_of_work().is_ok() {
+        block.inc_nonce();
+    }
+    match engine.verify_seal(&genesis, &block, &utxo_pool) {
+        Err(BlockError::InsufficientProofOfWork) => {}
+        _ => panic!("Unsealed block accepted"),
+    }
+
+    engine.start(&genesis, &block, &utxo_pool);
+    let sealed = loop {
+        if let Some(block) = engine.seal() {
+            break block;
+        }
+    };
+    assert!(engine.verify_seal(&genesis, &sealed, &utxo_pool).is_ok());
+    assert!(engine.fork_choice(&block, &common::random_block(&sealed)));
+    assert!(!engine.fork_choice(&sealed, &block));
+}
+
+#[test]
+fn proof_of_authority_seal() {
+    let keys = vec![common::random_key(), common::random_key()];
+    let authorities: Vec<_> = keys.iter().map(|(pk, _)| *pk).collect();
+    let mut engines: Vec<_> = keys
+        .iter()
+        .map(|(pk, sk)| ProofOfAuthority::new(authorities.clone(), *pk, *sk))
+        .collect();
+
+    let utxo_pool = UtxoPool::new();
+    let genesis = Block::genesis();
+    let block = common::random_block(&genesis);
+    engines[0].start(&genesis, &block, &utxo_pool);
+    assert!(engines[0].seal().is_none());
+    engines[1].start(&genesis, &block, &utxo_pool);
+    let sealed = engines[1].seal().unwrap();
+    assert!(engines[1].seal().is_none());
+    assert_eq!(sealed.hash(), block.hash());
+    assert!(engines[0]
+        .verify_seal(&genesis, &sealed, &utxo_pool)
+        .is_ok());
+    match engines[0].verify_seal(&genesis, &block, &utxo_pool) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Unsealed block accepted"),
+    }
+
+    let child = common::random_block(&sealed);
+    engines[1].start(&sealed, &child, &utxo_pool);
+    let mut forged = child.clone();
+    forged.set_seal(sealed.seal().clone());
+    assert!(engines[1].seal().is_none());
+    match engines[1].verify_seal(&sealed, &forged, &utxo_pool) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Block sealed by the wrong authority accepted"),
+    }
+    engines[0].start(&sealed, &child, &utxo_pool);
+    let child = engines[0].seal().unwrap();
+    assert!(engines[1].verify_seal(&sealed, &child, &utxo_pool).is_ok());
+    assert_eq!(
+        Block::deserialize(child.serialize()).unwrap().seal(),
+        child.seal()
+    );
+}
+
+#[test]
+fn proof_of_authority_network() {
+    common::log_setup();
+
+    let mut network = Network::random(NODES, 0);
+    let authorities: Vec<usize> = (0..NODES).collect();
+    network.set_proof_of_authority(&authorities);
+    info!("Network:\n{:?}", network);
+
+    network.run();
+    thread::sleep(Duration::from_secs(3));
+
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+
+    let nodes = network.nodes_as_ref();
+    let sets = network::partition(&nodes, |n1, n2| n1.blockchain() == n2.blockchain());
+    assert!(sets.len() == 1);
+
+    let blockchain = nodes[0].blockchain();
+    info!("Proof of authority --- height: {}", blockchain.height());
+    assert_eq!(blockchain.chain().len(), blockchain.height() + 1);
+    for block in blockchain.main_chain() {
+        let authority = nodes
+            .iter()
+            .find(|n| n.id() == authorities[block.height() % NODES])
+            .unwrap();
+        assert!(authority.miner().blocks().contains(&block.hash()));
+    }
+}
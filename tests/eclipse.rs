@@ -0,0 +1,69 @@
+use log::info;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use blockchain::constants::NODES;
+use blockchain::network::eclipse::EclipseMetrics;
+use blockchain::network::Network;
+use blockchain::node::behaviour::Behaviour;
+use blockchain::node::message::Message;
+
+pub mod common;
+
+const SYBILS: usize = 3;
+
+#[test]
+fn eclipse_attack() {
+    common::log_setup();
+
+    let mut network = Network::eclipse(NODES, SYBILS);
+    // The leader of the Sybil identities out-mines the victim, the other identities do not mine
+    let mut hash_powers = vec![0.4 / (NODES - 1) as f64; NODES + SYBILS];
+    hash_powers[0] = 0.05;
+    hash_powers[NODES] = 1.0;
+    network.set_hash_powers(&hash_powers);
+    info!("Network:\n{:?}", network);
+
+    network.run();
+    thread::sleep(Duration::from_secs(3));
+
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    let end = Instant::now();
+    network.shut_down();
+
+    let nodes = network.nodes_as_ref();
+    let honest_nodes = network.honest_nodes_as_ref();
+    let victim = nodes.iter().find(|n| n.id() == 0).unwrap();
+    let attacker = nodes
+        .iter()
+        .find(|n| n.integrity() == Behaviour::Sybil)
+        .and_then(|n| n.attacker())
+        .unwrap();
+
+    assert!(victim
+        .neighbours()
+        .iter()
+        .all(|n| attacker.is_identity(n.id())));
+
+    let victim_blocks = victim.miner().blocks();
+    for block in victim.blockchain().chain().values() {
+        assert!(
+            block.is_genesis()
+                || victim_blocks.contains(&block.hash())
+                || attacker.owns_chain_of(block, victim.blockchain())
+        );
+    }
+
+    let metrics = EclipseMetrics::measure(victim, &honest_nodes, attacker, end);
+    info!(
+        "Eclipse --- victim on attacker chain: {:.3}  honest nodes on attacker chain: {:.3}  \
+         victim height: {}  honest height: {}",
+        metrics.victim_ratio(),
+        metrics.honest_ratio(),
+        metrics.victim_height,
+        metrics.honest_height
+    );
+    assert!(metrics.victim_ratio() > 0.5);
+    assert_eq!(metrics.honest_on_attacker_chain, Duration::default());
+}
@@ -0,0 +1,230 @@
+use log::info;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::blockchain::Blockchain;
+use blockchain::consensus::finality::FinalityGadget;
+use blockchain::consensus::vote::Vote;
+use blockchain::constants::NODES;
+use blockchain::error::blockchain::BlockchainError;
+use blockchain::error::finality::FinalityError;
+use blockchain::network::{self, Network};
+use blockchain::node::behaviour::Behaviour;
+use blockchain::node::message::Message;
+
+pub mod common;
+
+const INTERVAL: usize = 2;
+
+#[test]
+fn vote_serialization() {
+    let (_, secret_key) = common::random_key();
+    let vote = Vote::sign(INTERVAL, common::random_hash(), &secret_key);
+    match Message::deserialize(Message::Vote(vote.clone()).serialize()).unwrap() {
+        Message::Vote(deserialized) => assert_eq!(deserialized, vote),
+        _ => panic!("Vote deserialized to another message"),
+    }
+    let bytes = vote.serialize();
+    assert_eq!(Vote::deserialize(&bytes[..bytes.len() - 1]), None);
+    let mut bytes = bytes;
+    bytes[41] ^= 0xff;
+    assert_eq!(Vote::deserialize(bytes), None);
+}
+
+#[test]
+fn quorum_finalizes_checkpoint() {
+    let keys: Vec<_> = (0..4).map(|_| common::random_key()).collect();
+    let validators = keys.iter().map(|(pk, _)| *pk).collect();
+    let mut finality = FinalityGadget::new(INTERVAL, validators);
+    let hash = common::random_hash();
+    assert_eq!(finality.quorum(), 3);
+
+    for (_, sk) in &keys[..2] {
+        assert!(finality.add(&Vote::sign(INTERVAL, hash, sk)).unwrap());
+        assert!(!finality.add(&Vote::sign(INTERVAL, hash, sk)).unwrap());
+    }
+    assert!(finality.checkpoint(INTERVAL).is_none());
+    assert!(finality.can_finalize(INTERVAL, &hash));
+    assert!(!finality.can_finalize(INTERVAL, &common::random_hash()));
+    assert!(finality
+        .add(&Vote::sign(INTERVAL, hash, &keys[2].1))
+        .unwrap());
+    assert_eq!(finality.checkpoint(INTERVAL), Some(hash));
+    assert_eq!(finality.checkpoints(), vec![(INTERVAL, hash)]);
+    assert_eq!(
+        finality.last_vote_of(&keys[0].0).map(Vote::hash),
+        Some(&hash)
+    );
+    assert!(finality.last_vote_of(&keys[3].0).is_none());
+}
+
+#[test]
+fn invalid_votes_are_rejected() {
+    let (public_key, secret_key) = common::random_key();
+    let mut finality = FinalityGadget::new(INTERVAL, vec![public_key]);
+
+    match finality.add(&Vote::sign(
+        INTERVAL,
+        common::random_hash(),
+        &common::random_secret_key(),
+    )) {
+        Err(FinalityError::UnknownValidator) => {}
+        _ => panic!("Vote from an unknown validator accepted"),
+    }
+    match finality.add(&Vote::sign(
+        INTERVAL + 1,
+        common::random_hash(),
+        &secret_key,
+    )) {
+        Err(FinalityError::NotACheckpoint) => {}
+        _ => panic!("Vote for a height that is not a checkpoint accepted"),
+    }
+    let mut bytes = Vote::sign(INTERVAL, common::random_hash(), &secret_key).serialize();
+    bytes[9] ^= 1;
+    match finality.add(&Vote::deserialize(bytes).unwrap()) {
+        Err(FinalityError::InvalidSignature) => {}
+        _ => panic!("Forged vote accepted"),
+    }
+}
+
+#[test]
+fn equivocating_validators_do_not_count() {
+    let keys: Vec<_> = (0..4).map(|_| common::random_key()).collect();
+    let validators = keys.iter().map(|(pk, _)| *pk).collect();
+    let mut finality = FinalityGadget::new(INTERVAL, validators);
+    let (hash1, hash2) = (common::random_hash(), common::random_hash());
+
+    let (public_key, secret_key) = &keys[0];
+    assert!(finality
+        .add(&Vote::sign(INTERVAL, hash1, secret_key))
+        .unwrap());
+    assert!(finality
+        .add(&Vote::sign(INTERVAL, hash2, secret_key))
+        .unwrap());
+    assert!(!finality
+        .add(&Vote::sign(INTERVAL, hash2, secret_key))
+        .unwrap());
+    assert!(finality.is_equivocator(public_key));
+
+    for (_, sk) in &keys[1..3] {
+        finality.add(&Vote::sign(INTERVAL, hash1, sk)).unwrap();
+    }
+    assert!(finality.checkpoint(INTERVAL).is_none());
+    finality
+        .add(&Vote::sign(INTERVAL, hash1, &keys[3].1))
+        .unwrap();
+    assert_eq!(finality.checkpoint(INTERVAL), Some(hash1));
+}
+
+#[test]
+fn finalized_block_is_never_reverted() {
+    let mut blockchain = Blockchain::new(HashMap::new());
+    let a1 = common::random_block(blockchain.genesis());
+    let a2 = common::random_block(&a1);
+    blockchain.push(a1.clone()).unwrap();
+    blockchain.push(a2.clone()).unwrap();
+    blockchain.finalize(a2.hash()).unwrap();
+    blockchain.finalize(a1.hash()).unwrap();
+    assert_eq!(blockchain.finalized().hash(), a2.hash());
+
+    let b1 = common::random_block(blockchain.genesis());
+    let b2 = common::random_block(&b1);
+    let b3 = common::random_block(&b2);
+    for block in &[&b1, &b2, &b3] {
+        blockchain.push((*block).clone()).unwrap();
+    }
+    assert_eq!(blockchain.top_hash(), &a2.hash());
+    assert!(blockchain.descends_from(&a2, &a1));
+    assert!(!blockchain.descends_from(&b3, &a1));
+    assert_eq!(blockchain.ancestor_at(&b3, 1), Some(&b1));
+
+    match blockchain.set_top(b3.hash()) {
+        Err(BlockchainError::FinalizedBlock) => {}
+        _ => panic!("Top conflicting with a finalized block"),
+    }
+    match blockchain.insert(common::random_block(&b3), true) {
+        Err(BlockchainError::FinalizedBlock) => {}
+        _ => panic!("Top conflicting with a finalized block"),
+    }
+    match blockchain.finalize(b2.hash()) {
+        Err(BlockchainError::ConflictingFinality) => {}
+        _ => panic!("Conflicting blocks finalized"),
+    }
+    assert!(blockchain.check_reversible(&[b1, b2, b3]).is_ok());
+    assert!(blockchain.check_reversible(&[a1]).is_err());
+}
+
+fn check_finality(network: &Network) {
+    let nodes = network.honest_nodes_as_ref();
+    let sets = network::partition(&nodes, |n1, n2| n1.blockchain() == n2.blockchain());
+    assert!(sets.len() == 1);
+
+    let blockchain = nodes[0].blockchain();
+    let finalized = nodes
+        .iter()
+        .map(|n| n.blockchain().finalized())
+        .max_by_key(|b| b.height())
+        .unwrap();
+    info!(
+        "Finality --- height: {}, finalized height: {}",
+        blockchain.height(),
+        finalized.height()
+    );
+    assert!(blockchain.height() >= 3 * INTERVAL);
+    assert!(finalized.height() > 0);
+    for node in &nodes {
+        let blockchain = node.blockchain();
+        assert!(blockchain.descends_from(blockchain.top(), blockchain.finalized()));
+        assert!(blockchain.descends_from(finalized, blockchain.finalized()));
+    }
+}
+
+/// Runs the network at `NETWORK_HASHRATE` so that checkpoints get votes before being buried
+fn run(network: &mut Network) {
+    let nodes = network.nodes_as_ref().len();
+    network.set_hash_powers(&vec![1.0 / nodes as f64; nodes]);
+    info!("Network:\n{:?}", network);
+    network.run();
+    thread::sleep(Duration::from_secs(3));
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+}
+
+#[test]
+fn finality_with_a_third_of_validators_offline() {
+    common::log_setup();
+
+    let offline = NODES / 3;
+    let mut network = Network::random(NODES - offline, 0);
+    let mut validators: Vec<_> = network
+        .nodes_as_ref()
+        .iter()
+        .map(|n| *n.public_key())
+        .collect();
+    validators.extend((0..offline).map(|_| common::random_public_key()));
+    network.set_finality(INTERVAL, &validators);
+
+    run(&mut network);
+    check_finality(&network);
+}
+
+#[test]
+fn finality_with_a_third_of_validators_equivocating() {
+    common::log_setup();
+
+    let equivocators = NODES / 3;
+    let mut behaviours = vec![Behaviour::Honest; NODES - equivocators];
+    behaviours.extend(vec![Behaviour::Equivocator; equivocators]);
+    let mut network = Network::random_with(&behaviours);
+    let validators: Vec<_> = network
+        .nodes_as_ref()
+        .iter()
+        .map(|n| *n.public_key())
+        .collect();
+    network.set_finality(INTERVAL, &validators);
+
+    run(&mut network);
+    check_finality(&network);
+}
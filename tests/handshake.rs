@@ -0,0 +1,298 @@
+use log::info;
+use secp256k1::{PublicKey, SecretKey};
+use std::borrow::Cow;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use blockchain::block::Block;
+use blockchain::constants::{NODES, PROTOCOL_VERSION, SERVICE_NETWORK};
+use blockchain::error::message::MessageError;
+use blockchain::network::link::Link;
+use blockchain::network::{Neighbour, Network, Packet, Synchronizer};
+use blockchain::node::behaviour::Behaviour;
+use blockchain::node::message::Message;
+use blockchain::node::version::Version;
+use blockchain::node::Node;
+
+pub mod common;
+
+const PEER: usize = 1;
+
+/// Spawns a node whose only neighbour is a peer driven by the test and known by `peer_pk`
+fn spawn_node(
+    peer_pk: PublicKey,
+) -> (
+    thread::JoinHandle<Node>,
+    Sender<Packet>,
+    Receiver<Packet>,
+    PublicKey,
+) {
+    let (pk, sk) = common::random_key();
+    let (sender, listener) = mpsc::channel();
+    let (peer_sender, peer_listener) = mpsc::channel();
+    let neighbours = vec![Neighbour::new(PEER, peer_pk, peer_sender)];
+    let barrier = Arc::new(Barrier::new(1));
+    let state = Arc::new(Mutex::new(vec![true, false]));
+    let synchronizer = Synchronizer::new(barrier, state);
+    let mut node = Node::new(
+        0,
+        pk,
+        sk,
+        sender.clone(),
+        listener,
+        neighbours,
+        vec![pk, peer_pk],
+        synchronizer,
+        Behaviour::Honest,
+    );
+    let handle = thread::spawn(move || {
+        node.run();
+        node
+    });
+    (handle, sender, peer_listener, pk)
+}
+
+fn peer_version(ephemeral_key: PublicKey) -> Version {
+    Version::new(
+        PROTOCOL_VERSION,
+        SERVICE_NETWORK,
+        0,
+        rand::random(),
+        Block::genesis().hash(),
+        ephemeral_key,
+    )
+}
+
+/// Plays the peer side of the handshake with keys `(peer_pk, peer_sk)` and returns the link
+fn connect(
+    sender: &Sender<Packet>,
+    peer_listener: &Receiver<Packet>,
+    pk: &PublicKey,
+    (peer_pk, peer_sk): (PublicKey, SecretKey),
+) -> Link {
+    let (ephemeral_pk, ephemeral_sk) = common::random_key();
+    send(
+        sender,
+        Some(PEER),
+        &Message::Version(peer_version(ephemeral_pk)),
+    );
+    let (_, bytes) = peer_listener.recv().unwrap();
+    let version = Version::deserialize(&*bytes).unwrap();
+    let link = Link::new(
+        &peer_pk,
+        &peer_sk,
+        &ephemeral_sk,
+        pk,
+        version.ephemeral_key(),
+    );
+    let bytes = link.seal(&Message::Verack.serialize());
+    sender.send((Some(PEER), Arc::new(bytes))).unwrap();
+    link
+}
+
+fn send(sender: &Sender<Packet>, origin: Option<usize>, message: &Message) {
+    sender
+        .send((origin, Arc::new(message.serialize())))
+        .unwrap();
+}
+
+#[test]
+fn version_ser_deser() {
+    let version = Version::new(
+        PROTOCOL_VERSION,
+        SERVICE_NETWORK,
+        7,
+        rand::random(),
+        Block::genesis().hash(),
+        common::random_public_key(),
+    );
+    assert_eq!(Version::deserialize(version.serialize()), Some(version));
+    assert_eq!(Version::deserialize(&version.serialize()[1..]), None);
+    assert_eq!(Version::deserialize(b"v"), None);
+}
+
+#[test]
+fn handshake_with_compatible_peer() {
+    let (peer_pk, peer_sk) = common::random_key();
+    let (handle, sender, peer_listener, pk) = spawn_node(peer_pk);
+    let (ephemeral_pk, ephemeral_sk) = common::random_key();
+    let version = peer_version(ephemeral_pk);
+    send(&sender, Some(PEER), &Message::Version(version));
+    let (origin, bytes) = peer_listener.recv().unwrap();
+    let node_version = Version::deserialize(&*bytes).unwrap();
+    let mut link = Link::new(
+        &peer_pk,
+        &peer_sk,
+        &ephemeral_sk,
+        &pk,
+        node_version.ephemeral_key(),
+    );
+    let bytes = link.seal(&Message::Verack.serialize());
+    sender.send((Some(PEER), Arc::new(bytes))).unwrap();
+    send(&sender, None, &Message::ShutDown);
+    let node = handle.join().unwrap();
+
+    assert!(node.neighbours()[0].is_connected());
+    assert_eq!(node.neighbours()[0].version(), Some(&version));
+    assert_eq!(origin, Some(node.id()));
+    assert_eq!(node_version, node.version());
+    let (_, bytes) = peer_listener.recv().unwrap();
+    assert_ne!(Message::Verack.serialize(), *bytes);
+    let bytes = link.open(&bytes).unwrap();
+    assert!(Message::deserialize(&bytes).unwrap() == Message::Verack);
+}
+
+#[test]
+fn handshake_with_wrong_key() {
+    let (handle, sender, peer_listener, pk) = spawn_node(common::random_public_key());
+    connect(&sender, &peer_listener, &pk, common::random_key());
+    send(&sender, None, &Message::ShutDown);
+    let node = handle.join().unwrap();
+
+    assert!(node.neighbours()[0].is_refused());
+    assert!(!node.neighbours()[0].is_connected());
+}
+
+#[test]
+fn unsealed_packet_gets_neighbour_banned() {
+    let peer_key = common::random_key();
+    let (handle, sender, peer_listener, pk) = spawn_node(peer_key.0);
+    connect(&sender, &peer_listener, &pk, peer_key);
+    let transaction = common::random_transaction(None, None);
+    send(
+        &sender,
+        Some(PEER),
+        &Message::Transaction(Cow::Owned(transaction)),
+    );
+    send(&sender, None, &Message::ShutDown);
+    let node = handle.join().unwrap();
+
+    assert!(node.neighbours()[0].is_connected());
+    assert!(node.neighbours()[0].is_banned());
+}
+
+#[test]
+fn malformed_message_gets_neighbour_banned() {
+    let transaction = Message::Transaction(Cow::Owned(common::random_transaction(None, None)));
+    let transaction = transaction.serialize();
+    for bytes in &[&b"?"[..], b"t", b"b", &transaction[..transaction.len() - 1]] {
+        let peer_key = common::random_key();
+        let (handle, sender, peer_listener, pk) = spawn_node(peer_key.0);
+        let link = connect(&sender, &peer_listener, &pk, peer_key);
+        let bytes = link.seal(bytes);
+        sender.send((Some(PEER), Arc::new(bytes))).unwrap();
+        send(&sender, None, &Message::ShutDown);
+        let node = handle.join().unwrap();
+
+        assert!(node.neighbours()[0].is_connected());
+        assert!(node.neighbours()[0].is_banned());
+    }
+}
+
+#[test]
+fn message_deserialization_errors() {
+    assert_eq!(Message::deserialize(b"").err(), Some(MessageError::Empty));
+    assert_eq!(
+        Message::deserialize(b"?").err(),
+        Some(MessageError::UnknownType(b'?'))
+    );
+    assert_eq!(
+        Message::deserialize(b"v").err(),
+        Some(MessageError::InvalidVersion)
+    );
+    assert_eq!(
+        Message::deserialize(b"c").err(),
+        Some(MessageError::InvalidVote)
+    );
+    assert_eq!(
+        Message::deserialize(b"t").err(),
+        Some(MessageError::InvalidTransaction)
+    );
+    assert_eq!(
+        Message::deserialize(b"b").err(),
+        Some(MessageError::InvalidBlock)
+    );
+    assert_eq!(
+        Message::deserialize(b"m").err(),
+        Some(MessageError::InvalidBlock)
+    );
+    assert_eq!(
+        Message::deserialize(b"h").err(),
+        Some(MessageError::InvalidHeader)
+    );
+    assert_eq!(
+        Message::deserialize(b"e").err(),
+        Some(MessageError::InvalidEvidence)
+    );
+}
+
+#[test]
+fn handshake_with_incompatible_peer() {
+    let (handle, sender, _peer_listener, _) = spawn_node(common::random_public_key());
+    let version = Version::new(
+        PROTOCOL_VERSION + 1,
+        SERVICE_NETWORK,
+        0,
+        rand::random(),
+        Block::genesis().hash(),
+        common::random_public_key(),
+    );
+    send(&sender, Some(PEER), &Message::Version(version));
+    send(&sender, Some(PEER), &Message::Verack);
+    let block = Block::new(
+        &Block::genesis(),
+        vec![common::random_transaction(None, None)],
+    )
+    .unwrap();
+    send(&sender, Some(PEER), &Message::Block(Cow::Owned(block)));
+    sender
+        .send((Some(PEER), Arc::new(vec![0xff, 0, 1])))
+        .unwrap();
+    send(&sender, None, &Message::ShutDown);
+    let node = handle.join().unwrap();
+
+    assert!(node.neighbours()[0].is_refused());
+    assert!(!node.neighbours()[0].is_connected());
+    assert_eq!(node.blockchain().chain().len(), 1);
+}
+
+#[test]
+fn handshake_on_other_chain() {
+    let (handle, sender, _peer_listener, _) = spawn_node(common::random_public_key());
+    let version = Version::new(
+        PROTOCOL_VERSION,
+        SERVICE_NETWORK,
+        0,
+        rand::random(),
+        *common::random_utxo_id(None, None).txid(),
+        common::random_public_key(),
+    );
+    send(&sender, Some(PEER), &Message::Version(version));
+    send(&sender, None, &Message::ShutDown);
+    let node = handle.join().unwrap();
+
+    assert!(node.neighbours()[0].is_refused());
+}
+
+#[test]
+fn handshake_in_network() {
+    common::log_setup();
+
+    let mut network = Network::random(NODES, 0);
+    info!("Network:\n{:?}", network);
+
+    network.run();
+    thread::sleep(Duration::from_secs(1));
+
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+
+    for node in network.nodes_as_ref() {
+        for neighbour in node.neighbours() {
+            assert!(neighbour.is_connected());
+        }
+    }
+}
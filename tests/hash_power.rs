@@ -0,0 +1,67 @@
+use log::info;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::constants::NETWORK_HASHRATE;
+use blockchain::network::Network;
+use blockchain::node::behaviour::Behaviour;
+use blockchain::node::message::Message;
+
+pub mod common;
+
+/// Half of `NETWORK_HASHRATE` so that the nodes are held back by their throttle, not the CPU
+const HASH_POWERS: [f64; 3] = [0.1, 0.15, 0.25];
+/// Long enough for about 300 blocks, as lost races spread the block shares more than the hash shares
+const RUN_TIME: Duration = Duration::from_secs(120);
+const BLOCK_TOLERANCE: f64 = 0.15;
+const TOLERANCE: f64 = 0.05;
+
+#[test]
+fn network_hash_powers() {
+    let mut network = Network::random_with(&[Behaviour::Honest; HASH_POWERS.len()]);
+    network.set_hash_powers(&HASH_POWERS);
+    for node in network.nodes_as_ref() {
+        assert_eq!(node.hash_power(), Some(HASH_POWERS[node.id()]));
+        assert_eq!(
+            node.miner().hashrate_limit(),
+            Some(HASH_POWERS[node.id()] * NETWORK_HASHRATE)
+        );
+    }
+}
+
+#[test]
+fn hash_shares_follow_hash_powers() {
+    common::log_setup();
+
+    let mut network = Network::random_with(&[Behaviour::Honest; HASH_POWERS.len()]);
+    network.set_hash_powers(&HASH_POWERS);
+    network.run();
+    thread::sleep(RUN_TIME);
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+
+    let nodes = network.nodes_as_ref();
+    let main_chain = nodes[0].blockchain().main_chain();
+    let hashes: u64 = nodes.iter().map(|node| node.miner().hashes()).sum();
+    let total_hash_power: f64 = HASH_POWERS.iter().sum();
+    for node in nodes {
+        let mined = main_chain
+            .iter()
+            .filter(|block| node.miner().blocks().contains(&block.hash()))
+            .count();
+        let hash_power = HASH_POWERS[node.id()] / total_hash_power;
+        let hash_share = node.miner().hashes() as f64 / hashes as f64;
+        let block_share = mined as f64 / main_chain.len() as f64;
+        info!(
+            "Node #{} --- hash power: {:.2}  hash share: {:.3}  block share: {:.3}  ({}/{} blocks)",
+            node.id(),
+            hash_power,
+            hash_share,
+            block_share,
+            mined,
+            main_chain.len()
+        );
+        assert!((hash_share - hash_power).abs() <= TOLERANCE);
+        assert!((block_share - hash_power).abs() <= BLOCK_TOLERANCE);
+    }
+}
@@ -0,0 +1,74 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use blockchain::block::Block;
+use blockchain::constants::TXS_PER_BLOCK;
+use blockchain::miner::Miner;
+use blockchain::transaction_pool::TransactionPool;
+use blockchain::utxo_pool::UtxoPool;
+
+pub mod common;
+
+fn random_transaction_pool() -> TransactionPool {
+    let mut transaction_pool = TransactionPool::new();
+    for _ in 0..TXS_PER_BLOCK {
+        transaction_pool
+            .add(common::random_transaction(None, None), 0)
+            .unwrap();
+    }
+    transaction_pool
+}
+
+#[test]
+fn nonce_overflow_carries_to_extra_nonce() {
+    let mut block = Block::new(
+        &Block::genesis(),
+        vec![common::random_transaction(None, None)],
+    )
+    .unwrap();
+    block.set_nonce(u32::MAX);
+    let hash = block.hash();
+    block.inc_nonce();
+    assert_eq!(block.nonce(), 0);
+    assert_eq!(block.extra_nonce(), 1);
+    assert_ne!(block.hash(), hash);
+
+    let deserialized = Block::deserialize(block.serialize()).unwrap();
+    assert_eq!(deserialized.extra_nonce(), 1);
+    assert_eq!(deserialized.hash(), block.hash());
+}
+
+#[test]
+fn miner_finds_block() {
+    let genesis = Block::genesis();
+    let utxo_pool = UtxoPool::new();
+    let transaction_pool = random_transaction_pool();
+    let mut miner = Miner::with_threads(2);
+    let start = Instant::now();
+    let block = loop {
+        if let Some(block) = miner.mine(&genesis, &utxo_pool, &transaction_pool) {
+            break block;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10));
+    };
+
+    assert!(block.hash() < block.target().hash());
+    assert_eq!(block.hash_prev_block(), &genesis.hash());
+    assert_eq!(miner.blocks(), &vec![block.hash()]);
+    assert!(miner.hashes() > 0);
+    assert!(miner.hashrate() > 0.0);
+}
+
+#[test]
+fn miner_stops_on_new_tip() {
+    let genesis = Block::genesis();
+    let transaction_pool = random_transaction_pool();
+    let mut miner = Miner::with_threads(2);
+    miner.mine_from(&genesis, &UtxoPool::new(), &transaction_pool);
+    thread::sleep(Duration::from_millis(50));
+    miner.discard_block();
+    thread::sleep(Duration::from_millis(50));
+    let hashes = miner.hashes();
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(miner.hashes(), hashes);
+}
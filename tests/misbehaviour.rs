@@ -0,0 +1,90 @@
+use log::info;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::constants::{BAN_SCORE, NODES};
+use blockchain::error::block::BlockError;
+use blockchain::error::blockchain::BlockchainError;
+use blockchain::error::utxo_pool::UtxoPoolError;
+use blockchain::error::Error;
+use blockchain::network::{Neighbour, Network};
+use blockchain::node::behaviour::Behaviour;
+use blockchain::node::message::Message;
+
+pub mod common;
+
+#[test]
+fn neighbour_misbehave() {
+    let (sender, _listener) = mpsc::channel();
+    let mut neighbour = Neighbour::new(0, common::random_public_key(), sender);
+    assert!(!neighbour.misbehave(BAN_SCORE - 1));
+    assert!(!neighbour.is_banned());
+    assert!(neighbour.misbehave(1));
+    assert!(neighbour.is_banned());
+    assert!(!neighbour.misbehave(BAN_SCORE));
+}
+
+#[test]
+fn error_misbehaviour_score() {
+    let err = Error::from(UtxoPoolError::TransactionHasInvalidSignature(
+        secp256k1::Error::IncorrectSignature,
+    ));
+    assert!(err.misbehaviour_score() >= BAN_SCORE);
+    let err = Error::from(UtxoPoolError::BlockHasInvalidSignature(1, 0));
+    assert!(err.misbehaviour_score() >= BAN_SCORE);
+    let err = Error::from(UtxoPoolError::BlockHasUnknownUtxo);
+    assert!(err.misbehaviour_score() >= BAN_SCORE);
+    let err = Error::from(UtxoPoolError::TransactionHasUnknownUtxo);
+    assert_eq!(err.misbehaviour_score(), 0);
+    let err = Error::from(BlockError::InsufficientProofOfWork);
+    assert!(err.misbehaviour_score() >= BAN_SCORE);
+    let err = Error::from(BlockError::DoubleSpending);
+    assert!(err.misbehaviour_score() >= BAN_SCORE);
+    let err = Error::from(BlockError::UnknownTemplate);
+    assert_eq!(err.misbehaviour_score(), 0);
+    let err = Error::from(BlockchainError::OrphanBlock);
+    assert_eq!(err.misbehaviour_score(), 0);
+}
+
+/// Runs a network of honest nodes and one node of the given behaviour, and checks that its
+/// neighbours ban it and only it
+fn check_banned(behaviour: Behaviour) {
+    common::log_setup();
+
+    let mut behaviours = vec![Behaviour::Honest; NODES];
+    behaviours.push(behaviour);
+    let mut network = Network::random_with(&behaviours);
+    info!("Network:\n{:?}", network);
+
+    network.run();
+    thread::sleep(Duration::from_secs(3));
+
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+
+    let nodes = network.nodes_as_ref();
+    let culprit = nodes.iter().find(|n| n.integrity() == behaviour).unwrap();
+    let honest_nodes = network.honest_nodes_as_ref();
+    for node in &honest_nodes {
+        for neighbour in node.neighbours() {
+            if neighbour.id() == culprit.id() {
+                assert!(neighbour.is_banned());
+            } else {
+                assert!(!neighbour.is_banned());
+                assert_eq!(neighbour.misbehaviour(), 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn malicious_node_gets_banned() {
+    check_banned(Behaviour::Malicious);
+}
+
+#[test]
+fn counterfeiter_gets_banned() {
+    check_banned(Behaviour::Counterfeiter);
+}
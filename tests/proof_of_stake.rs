@@ -0,0 +1,256 @@
+use log::info;
+use secp256k1::PublicKey;
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::block::Block;
+use blockchain::consensus::evidence::Evidence;
+use blockchain::consensus::proof_of_stake::ProofOfStake;
+use blockchain::consensus::vrf;
+use blockchain::consensus::ConsensusEngine;
+use blockchain::constants::{NODES, VRF_PROOF_BYTES};
+use blockchain::error::block::BlockError;
+use blockchain::network::{self, Network};
+use blockchain::node::behaviour::Behaviour;
+use blockchain::node::message::Message;
+use blockchain::utxo_pool::UtxoPool;
+
+pub mod common;
+
+const SLOTS: usize = 400;
+const TOLERANCE: f64 = 0.1;
+
+/// Validators holding the given stakes in a utxo pool
+fn validators(stakes: &[u32]) -> (Vec<ProofOfStake>, Vec<PublicKey>, UtxoPool) {
+    let keys: Vec<_> = stakes.iter().map(|_| common::random_key()).collect();
+    let utxos: HashSet<_> = keys
+        .iter()
+        .zip(stakes)
+        .map(|((pk, _), &stake)| common::random_utxo_with(None, None, Some(stake), Some(*pk)))
+        .collect();
+    let engines = keys
+        .iter()
+        .map(|(pk, sk)| ProofOfStake::new(*pk, *sk))
+        .collect();
+    let public_keys = keys.iter().map(|(pk, _)| *pk).collect();
+    (engines, public_keys, common::random_utxo_pool(Some(utxos)))
+}
+
+#[test]
+fn vrf_proofs() {
+    let (public_key, secret_key) = common::random_key();
+    let proof = vrf::prove(b"parent", &secret_key);
+    assert_eq!(proof, vrf::prove(b"parent", &secret_key));
+    assert_eq!(
+        vrf::verify(b"parent", &proof, &public_key),
+        Some(vrf::output(&proof))
+    );
+    assert_ne!(
+        vrf::output(&proof),
+        vrf::output(&vrf::prove(b"other", &secret_key))
+    );
+    assert!(vrf::verify(b"other", &proof, &public_key).is_none());
+    assert!(vrf::verify(b"parent", &proof, &common::random_public_key()).is_none());
+    for i in &[0, 33, VRF_PROOF_BYTES - 1] {
+        let mut forged = proof;
+        forged[*i] ^= 1;
+        assert!(vrf::verify(b"parent", &forged, &public_key).is_none());
+    }
+}
+
+#[test]
+fn proof_of_stake_seal() {
+    let (mut engines, _, utxo_pool) = validators(&[10, 0]);
+    let genesis = Block::genesis();
+    let block = common::random_block(&genesis);
+
+    engines[0].start(&genesis, &block, &utxo_pool);
+    let sealed = engines[0].seal().unwrap();
+    assert!(engines[0].seal().is_none());
+    assert_eq!(sealed.hash_merkle_root(), block.hash_merkle_root());
+    assert_ne!(sealed.hash(), block.hash());
+    assert!(engines[1]
+        .verify_seal(&genesis, &sealed, &utxo_pool)
+        .is_ok());
+    assert_eq!(
+        Block::deserialize(sealed.serialize()).unwrap().seal(),
+        sealed.seal()
+    );
+    match engines[1].verify_seal(&genesis, &block, &utxo_pool) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Unsealed block accepted"),
+    }
+
+    engines[1].start(&genesis, &block, &utxo_pool);
+    assert!(engines[1].seal().is_none());
+
+    let mut forged = common::random_block(&genesis);
+    forged.set_nonce(sealed.nonce());
+    forged.set_seal(sealed.seal().clone());
+    match engines[1].verify_seal(&genesis, &forged, &utxo_pool) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Block with a stolen seal accepted"),
+    }
+    match engines[1].verify_seal(&sealed, &sealed, &utxo_pool) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Draw of another slot accepted"),
+    }
+    // The stake follows the public key, then the proof of the draw
+    for i in &[40, 41, 41 + VRF_PROOF_BYTES - 1] {
+        let mut forged = sealed.clone();
+        let mut seal = sealed.seal().clone();
+        seal[*i] ^= 1;
+        forged.set_seal(seal);
+        match engines[1].verify_seal(&genesis, &forged, &utxo_pool) {
+            Err(BlockError::InvalidSeal) => {}
+            _ => panic!("Block with a forged stake or draw accepted"),
+        }
+    }
+    assert!(engines[1].fork_choice(&sealed, &common::random_block(&sealed)));
+    assert!(!engines[1].fork_choice(&sealed, &common::random_block(&Block::genesis())));
+}
+
+#[test]
+fn stakes_are_read_at_the_parent() {
+    let (mut engines, public_keys, utxo_pool) = validators(&[0, 10]);
+    let genesis = Block::genesis();
+    let block = common::random_block(&genesis);
+    engines[0].start(&genesis, &block, &utxo_pool);
+    assert!(engines[0].seal().is_none());
+
+    let mut utxos = HashSet::new();
+    utxos.insert(common::random_utxo_with(
+        None,
+        None,
+        Some(5),
+        Some(public_keys[0]),
+    ));
+    let funded = common::random_utxo_pool(Some(utxos));
+    assert_eq!(engines[1].stake_of(&public_keys[0], &funded), 5);
+    engines[0].start(&genesis, &block, &funded);
+    let sealed = engines[0].seal().unwrap();
+    assert!(engines[1].verify_seal(&genesis, &sealed, &funded).is_ok());
+    match engines[1].verify_seal(&genesis, &sealed, &utxo_pool) {
+        Err(BlockError::InvalidSeal) => {}
+        _ => panic!("Block sealed without stake at its parent accepted"),
+    }
+}
+
+#[test]
+fn slots_are_won_in_proportion_to_stakes() {
+    let stakes = [1, 2, 5];
+    let total: u32 = stakes.iter().sum();
+    let (mut engines, _, utxo_pool) = validators(&stakes);
+    let transactions = vec![common::random_transaction(None, None)];
+
+    let mut wins = vec![0; stakes.len()];
+    let mut parent = Block::genesis();
+    for _ in 0..SLOTS {
+        let block = Block::new(&parent, transactions.clone()).unwrap();
+        let sealed: Vec<_> = engines
+            .iter_mut()
+            .map(|engine| {
+                engine.start(&parent, &block, &utxo_pool);
+                engine.seal().unwrap()
+            })
+            .collect();
+        let winner = (1..sealed.len()).fold(0, |winner, i| {
+            if engines[0].fork_choice(&sealed[winner], &sealed[i]) {
+                i
+            } else {
+                winner
+            }
+        });
+        wins[winner] += 1;
+        parent = sealed[winner].clone();
+    }
+
+    for (wins, &stake) in wins.iter().zip(stakes.iter()) {
+        let share = *wins as f64 / SLOTS as f64;
+        let expected = stake as f64 / total as f64;
+        info!("Stake share: {:.3}, slot share: {:.3}", expected, share);
+        assert!((share - expected).abs() < TOLERANCE);
+    }
+}
+
+#[test]
+fn equivocating_validator_gets_slashed() {
+    let (mut engines, public_keys, utxo_pool) = validators(&[10, 10, 10]);
+    let genesis = Block::genesis();
+    let block = common::random_block(&genesis);
+    let mut twin = block.clone();
+    twin.set_extra_nonce(block.extra_nonce().wrapping_add(1));
+
+    engines[0].start(&genesis, &block, &utxo_pool);
+    let block = engines[0].seal().unwrap();
+    engines[0].start(&genesis, &twin, &utxo_pool);
+    let twin = engines[0].seal().unwrap();
+    engines[1].start(&genesis, &twin, &utxo_pool);
+    let honest = engines[1].seal().unwrap();
+    assert_ne!(honest.hash(), twin.hash());
+    assert_eq!(engines[0].score(&block), engines[0].score(&twin));
+    assert_ne!(engines[0].score(&block), engines[0].score(&honest));
+
+    assert!(engines[2].check_equivocation(&block).is_none());
+    assert!(engines[2].check_equivocation(&honest).is_none());
+    assert!(engines[2].check_equivocation(&block).is_none());
+    let evidence = engines[2].check_equivocation(&twin).unwrap();
+    let bytes = Message::Evidence(evidence.clone()).serialize();
+    match Message::deserialize(bytes).unwrap() {
+        Message::Evidence(deserialized) => assert_eq!(deserialized, evidence),
+        _ => panic!("Evidence deserialized to another message"),
+    }
+
+    let public_key = public_keys[0];
+    assert!(!engines[1].is_slashed(&public_key));
+    assert!(engines[1].punish(&evidence).unwrap());
+    assert!(!engines[1].punish(&evidence).unwrap());
+    assert!(engines[1].is_slashed(&public_key));
+    assert_eq!(engines[1].stake_of(&public_key, &utxo_pool), 0);
+    assert!(engines[1].fork_choice(&block, &honest));
+    assert!(!engines[1].fork_choice(&honest, &twin));
+
+    for (block1, block2) in &[(&block, &block), (&block, &honest)] {
+        match engines[2].punish(&Evidence::new(block1, block2)) {
+            Err(BlockError::InvalidEvidence) => {}
+            _ => panic!("Invalid evidence accepted"),
+        }
+    }
+    assert!(!engines[2].is_slashed(&public_key));
+}
+
+#[test]
+fn proof_of_stake_network() {
+    common::log_setup();
+
+    let mut behaviours = vec![Behaviour::Honest; NODES];
+    behaviours.push(Behaviour::Equivocator);
+    let mut network = Network::random_with(&behaviours);
+    network.set_proof_of_stake();
+    info!("Network:\n{:?}", network);
+
+    network.run();
+    thread::sleep(Duration::from_secs(3));
+
+    info!("Network shutting down");
+    network.broadcast(Message::ShutDown);
+    network.shut_down();
+
+    let nodes = network.honest_nodes_as_ref();
+    let sets = network::partition(&nodes, |n1, n2| n1.blockchain() == n2.blockchain());
+    assert!(sets.len() == 1);
+
+    let equivocator = network
+        .nodes_as_ref()
+        .into_iter()
+        .find(|n| n.integrity() == Behaviour::Equivocator)
+        .unwrap();
+    let blockchain = nodes[0].blockchain();
+    info!("Proof of stake --- height: {}", blockchain.height());
+    if equivocator.miner().blocks().len() > 1 {
+        assert!(nodes
+            .iter()
+            .any(|n| n.miner().engine().is_slashed(equivocator.public_key())));
+    }
+}
@@ -0,0 +1,147 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use blockchain::block::Block;
+use blockchain::miner::selfish::SelfishMining;
+
+pub mod common;
+
+fn random_chain(parent: &Block, len: usize) -> Vec<Block> {
+    let mut chain: Vec<Block> = Vec::with_capacity(len);
+    for _ in 0..len {
+        let parent = chain.last().unwrap_or(parent);
+        let transactions = vec![common::random_transaction(None, None)];
+        chain.push(Block::new(parent, transactions).unwrap());
+    }
+    chain
+}
+
+#[test]
+fn selfish_mining_publishes_on_lead_of_one_after_tie() {
+    let genesis = Block::genesis();
+    let private = random_chain(&genesis, 2);
+    let mut selfish_mining = SelfishMining::new();
+
+    assert!(selfish_mining
+        .on_own_block(private[0].clone(), 0)
+        .is_empty());
+    assert_eq!(
+        selfish_mining.on_public_block(1, 1),
+        vec![private[0].clone()]
+    );
+    assert_eq!(
+        selfish_mining.on_own_block(private[1].clone(), 1),
+        vec![private[1].clone()]
+    );
+    assert!(selfish_mining.withheld().is_empty());
+    assert_eq!(selfish_mining.public_height(), 2);
+}
+
+#[test]
+fn selfish_mining_adopts_public_chain() {
+    let genesis = Block::genesis();
+    let mut selfish_mining = SelfishMining::new();
+
+    assert!(selfish_mining.on_public_block(1, 0).is_empty());
+    let private = random_chain(&genesis, 2);
+    assert!(selfish_mining
+        .on_own_block(private[1].clone(), 1)
+        .is_empty());
+    assert_eq!(
+        selfish_mining.on_public_block(2, 2),
+        vec![private[1].clone()]
+    );
+    assert!(selfish_mining.on_public_block(3, 2).is_empty());
+    assert!(selfish_mining.withheld().is_empty());
+}
+
+#[test]
+fn selfish_mining_releases_lead() {
+    let genesis = Block::genesis();
+    let private = random_chain(&genesis, 4);
+    let mut selfish_mining = SelfishMining::new();
+    for (height, block) in private.iter().enumerate() {
+        assert!(selfish_mining
+            .on_own_block(block.clone(), height)
+            .is_empty());
+    }
+
+    assert_eq!(
+        selfish_mining.on_public_block(1, 4),
+        vec![private[0].clone()]
+    );
+    assert_eq!(
+        selfish_mining.on_public_block(2, 4),
+        vec![private[1].clone()]
+    );
+    assert_eq!(
+        selfish_mining.on_public_block(3, 4),
+        vec![private[2].clone(), private[3].clone()]
+    );
+    assert!(selfish_mining.withheld().is_empty());
+    assert_eq!(selfish_mining.public_height(), 4);
+}
+
+/// Revenue share of a selfish miner with hash share `alpha`, when a share `gamma` of the honest
+/// miners mine on its branch during a tie (Eyal and Sirer, equation 8)
+fn predicted_revenue_share(alpha: f64, gamma: f64) -> f64 {
+    let numerator =
+        alpha * (1.0 - alpha).powi(2) * (4.0 * alpha + gamma * (1.0 - 2.0 * alpha)) - alpha.powi(3);
+    numerator / (1.0 - alpha * (1.0 + (2.0 - alpha) * alpha))
+}
+
+/// Share of the public chain mined by the selfish miner after `rounds` blocks are found, the
+/// selfish miner finding each one with probability `alpha`
+///
+/// Chains are tracked as the owner of each block, `true` for the selfish miner. The blocks handed
+/// to the strategy only carry the heights it reads.
+fn simulated_revenue_share(alpha: f64, gamma: f64, rounds: usize) -> f64 {
+    let mut rng = StdRng::seed_from_u64(0);
+    let transaction = common::random_transaction(None, None);
+    let mut blocks = vec![Block::genesis()];
+    for height in 1..=rounds {
+        let block = Block::new(&blocks[height - 1], vec![transaction.clone()]).unwrap();
+        blocks.push(block);
+    }
+    let mut selfish_mining = SelfishMining::new();
+    let mut private: Vec<bool> = Vec::new();
+    let mut public: Vec<bool> = Vec::new();
+    let mut tie = false;
+    for _ in 0..rounds {
+        let published = if rng.gen_bool(alpha) {
+            let height = private.len();
+            private.push(true);
+            selfish_mining.on_own_block(blocks[height + 1].clone(), height)
+        } else {
+            if tie && rng.gen_bool(gamma) {
+                public = private[..public.len()].to_vec();
+            }
+            public.push(false);
+            tie = false;
+            selfish_mining.on_public_block(public.len(), private.len())
+        };
+        if let Some(block) = published.last() {
+            let height = block.height();
+            if height > public.len() {
+                public = private[..height].to_vec();
+                tie = false;
+            } else if height == public.len() && public[..] != private[..height] {
+                tie = true;
+            }
+        }
+        if public.len() > private.len() {
+            private = public.clone();
+        }
+    }
+    public.iter().filter(|selfish| **selfish).count() as f64 / public.len() as f64
+}
+
+#[test]
+fn selfish_mining_revenue_above_threshold() {
+    let (alpha, gamma) = (0.4, 0.5);
+    let predicted = predicted_revenue_share(alpha, gamma);
+    let revenue_share = simulated_revenue_share(alpha, gamma, 100_000);
+    assert!(predicted > alpha);
+    assert!((revenue_share - predicted).abs() < 0.02);
+    assert!(revenue_share > alpha);
+}
@@ -1,7 +1,13 @@
 use rand::seq::SliceRandom;
 use rand::Rng;
+use secp256k1::{PublicKey, SecretKey};
 
+use blockchain::constants::UTXO_ID_BYTES;
+use blockchain::error::transaction::TransactionError;
+use blockchain::transaction::partial::PartialTransaction;
+use blockchain::transaction::schnorr;
 use blockchain::transaction::{Transaction, TransactionInput, TransactionOutput};
+use blockchain::utxo::{OutputType, Utxo, UtxoData};
 
 pub mod common;
 
@@ -9,22 +15,39 @@ pub mod common;
 fn transaction_input_ser_deser() {
     let transaction_input = common::random_transaction_input(None, None);
     let transaction_input2 = TransactionInput::deserialize(transaction_input.serialize());
-    assert_eq!(transaction_input, transaction_input2);
+    assert_eq!(Some(transaction_input), transaction_input2);
 }
 
 #[test]
 fn transaction_output_ser_deser() {
     let transaction_output = common::random_transaction_output(None, None);
-    let transaction_output2 = TransactionOutput::deserialize(transaction_output.serialize());
+    let transaction_output2 =
+        TransactionOutput::deserialize(transaction_output.serialize()).unwrap();
     assert_eq!(transaction_output, transaction_output2);
 }
 
 #[test]
 fn transaction_ser_deser() {
     let transaction = common::random_transaction(None, None);
-    let (transaction2, transaction2_bytes) = Transaction::deserialize(transaction.serialize());
+    let (transaction2, transaction2_bytes) =
+        Transaction::deserialize(transaction.serialize()).unwrap();
     assert_eq!(transaction2_bytes, transaction2.bytes());
     assert_eq!(transaction, transaction2);
+
+    let bytes = transaction.serialize();
+    for len in 0..bytes.len() {
+        assert!(Transaction::deserialize(&bytes[..len]).is_none());
+    }
+    let mut tampered = bytes.clone();
+    tampered[1..9].copy_from_slice(&usize::MAX.to_be_bytes());
+    assert!(Transaction::deserialize(&tampered).is_none());
+    let public_key_start = 1 + 3 * 8 + UTXO_ID_BYTES;
+    tampered = bytes.clone();
+    tampered[public_key_start] = 0xff;
+    assert!(Transaction::deserialize(&tampered).is_none());
+    tampered = bytes.clone();
+    *tampered.last_mut().unwrap() = 0xff;
+    assert!(Transaction::deserialize(&tampered).is_none());
 }
 
 #[test]
@@ -56,3 +79,160 @@ fn transaction_check_double_spending() {
     let tx = common::random_transaction_with(Some(sk), None, Some(vec![utxo, utxo]), None);
     assert!(tx.check_double_spending().is_err());
 }
+
+#[test]
+fn partial_transaction_combine_and_finalize() {
+    let (pk1, sk1) = common::random_key();
+    let (pk2, sk2) = common::random_key();
+    let inputs = vec![
+        common::random_utxo_with(None, None, None, Some(pk1)),
+        common::random_utxo_with(None, None, None, Some(pk2)),
+        common::random_utxo_with(None, None, None, Some(pk1)),
+    ];
+    let outputs = vec![common::random_transaction_output(None, None)];
+    let mut partial1 = PartialTransaction::new(inputs.clone(), outputs.clone());
+    let mut partial2 = PartialTransaction::deserialize(partial1.serialize()).unwrap();
+    assert_eq!(partial1.sign(&sk1), 2);
+    assert_eq!(partial2.sign(&sk2), 1);
+    assert_eq!(partial1.unsigned(), vec![&inputs[1]]);
+    assert!(matches!(
+        partial1.clone().finalize(),
+        Err(TransactionError::MissingSignatures)
+    ));
+
+    let other = PartialTransaction::new(inputs[..2].to_vec(), outputs.clone());
+    assert!(matches!(
+        partial1.combine(&other),
+        Err(TransactionError::MismatchedPartialTransactions)
+    ));
+    partial1.combine(&partial2).unwrap();
+    assert!(partial1.is_complete());
+    let partial3 = PartialTransaction::deserialize(partial1.serialize()).unwrap();
+    assert_eq!(partial3, partial1);
+
+    let secret_keys = [sk1, sk2, sk1];
+    let utxo_ids = inputs.iter().map(|utxo| *utxo.utxo_id()).collect();
+    let transaction = Transaction::sign_with(utxo_ids, outputs, &secret_keys);
+    assert_eq!(partial1.finalize().unwrap().inputs(), transaction.inputs());
+}
+
+#[test]
+fn partial_transaction_rejects_invalid_signatures() {
+    let (pk, sk) = common::random_key();
+    let inputs = vec![common::random_utxo_with(None, None, None, Some(pk))];
+    let outputs = vec![common::random_transaction_output(None, None)];
+    let mut partial = PartialTransaction::new(inputs.clone(), outputs);
+    partial.sign(&sk);
+    let bytes = partial.serialize();
+    for len in 0..bytes.len() {
+        assert!(PartialTransaction::deserialize(&bytes[..len]).is_err());
+    }
+
+    // Signed over other outputs
+    let other = Transaction::sign(
+        vec![*inputs[0].utxo_id()],
+        vec![common::random_transaction_output(None, None)],
+        &sk,
+    );
+    let mut unsigned = PartialTransaction::new(inputs, partial.outputs().clone());
+    assert!(matches!(
+        unsigned.add_signature(0, pk, *other.inputs()[0].sig()),
+        Err(TransactionError::InvalidSignature)
+    ));
+    assert!(unsigned
+        .add_signature(1, pk, *other.inputs()[0].sig())
+        .is_err());
+
+    // Signed by a key whose hash the utxo does not pay
+    let (other_pk, other_sk) = common::random_key();
+    let forged = Transaction::sign(
+        vec![*unsigned.inputs()[0].utxo_id()],
+        unsigned.outputs().clone(),
+        &other_sk,
+    );
+    assert!(matches!(
+        unsigned.add_signature(0, other_pk, *forged.inputs()[0].sig()),
+        Err(TransactionError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn schnorr_sign_verify() {
+    let (pk, sk) = common::random_key();
+    let (other_pk, _) = common::random_key();
+    let message = [7; 32];
+    let signature = schnorr::sign(&message, &sk);
+    assert!(schnorr::verify(&message, &signature, &pk));
+    assert!(!schnorr::verify(&[8; 32], &signature, &pk));
+    assert!(!schnorr::verify(&message, &signature, &other_pk));
+}
+
+#[test]
+fn schnorr_verify_batch() {
+    let mut rng = rand::thread_rng();
+    let messages: Vec<[u8; 32]> = (0..8).map(|_| rng.gen()).collect();
+    let keys: Vec<_> = (0..8).map(|_| common::random_key()).collect();
+    let signatures: Vec<_> = messages
+        .iter()
+        .zip(&keys)
+        .map(|(message, (_, sk))| schnorr::sign(message, sk))
+        .collect();
+    fn batch<'a>(
+        messages: &'a [[u8; 32]],
+        signatures: &'a [[u8; 64]],
+        keys: &'a [(PublicKey, SecretKey)],
+    ) -> Vec<(&'a [u8], &'a [u8; 64], &'a PublicKey)> {
+        messages
+            .iter()
+            .zip(signatures)
+            .zip(keys)
+            .map(|((message, signature), (pk, _))| (&message[..], signature, pk))
+            .collect()
+    }
+    assert!(schnorr::verify_batch(&[]));
+    assert!(schnorr::verify_batch(&batch(&messages, &signatures, &keys)));
+    assert!(schnorr::verify_batch(
+        &batch(&messages, &signatures, &keys)[..1]
+    ));
+
+    let mut tampered = signatures.clone();
+    tampered[rng.gen_range(0, 8)][40] ^= 1;
+    assert!(!schnorr::verify_batch(&batch(&messages, &tampered, &keys)));
+    // Swapped signatures are each valid, but not for these messages
+    let mut swapped = signatures;
+    swapped.swap(2, 5);
+    assert!(!schnorr::verify_batch(&batch(&messages, &swapped, &keys)));
+}
+
+#[test]
+fn partial_transaction_with_schnorr_inputs() {
+    let (pk1, sk1) = common::random_key();
+    let (pk2, sk2) = common::random_key();
+    let utxo = |pk, output_type| {
+        let utxo_id = common::random_utxo_id(None, None);
+        Utxo::new(utxo_id, UtxoData::with_type(1, pk, output_type))
+    };
+    let inputs = vec![utxo(pk1, OutputType::Schnorr), utxo(pk2, OutputType::Ecdsa)];
+    let outputs = vec![common::random_transaction_output(None, None)];
+    let mut partial1 = PartialTransaction::new(inputs.clone(), outputs.clone());
+    let mut partial2 = PartialTransaction::deserialize(partial1.serialize()).unwrap();
+    assert_eq!(partial1.sign(&sk1), 1);
+    assert_eq!(partial2.sign(&sk2), 1);
+    let partial2 = PartialTransaction::deserialize(partial2.serialize()).unwrap();
+    partial1.combine(&partial2).unwrap();
+    let transaction = partial1.finalize().unwrap();
+    let message = transaction.message();
+    for (input, utxo) in transaction.inputs().iter().zip(&inputs) {
+        assert!(blockchain::transaction::verify_message(
+            &message,
+            input.public_key(),
+            input.sig(),
+            utxo.utxo_data()
+        ));
+    }
+    assert!(schnorr::verify(
+        &message[..],
+        transaction.inputs()[0].sig(),
+        &pk1
+    ));
+}
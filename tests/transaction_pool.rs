@@ -1,4 +1,15 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::block::Block;
+use blockchain::error::transaction_pool::TransactionPoolError;
+use blockchain::error::utxo_pool::UtxoPoolError;
+use blockchain::transaction::Transaction;
+use blockchain::transaction_pool::orphan::OrphanPool;
 use blockchain::transaction_pool::TransactionPool;
+use blockchain::utxo::Utxo;
+use blockchain::wallet::Wallet;
 
 pub mod common;
 
@@ -6,8 +17,8 @@ pub mod common;
 fn transaction_pool_add() {
     let mut transaction_pool = TransactionPool::new();
     let transaction = common::random_transaction(None, None);
-    assert!(transaction_pool.add(transaction.clone()).is_ok());
-    assert!(transaction_pool.add(transaction.clone()).is_err());
+    assert!(transaction_pool.add(transaction.clone(), 0).is_ok());
+    assert!(transaction_pool.add(transaction.clone(), 0).is_err());
 }
 
 #[test]
@@ -16,20 +27,304 @@ fn transaction_pool_remove() {
     let transaction = common::random_transaction(None, None);
     assert!(transaction_pool.remove(&transaction).is_err());
 
-    transaction_pool.add(transaction.clone()).unwrap();
+    transaction_pool.add(transaction.clone(), 0).unwrap();
     assert!(transaction_pool.remove(&transaction).is_ok());
     assert!(transaction_pool.remove(&transaction).is_err());
 }
 
+#[test]
+fn transaction_pool_undo_all() {
+    let mut transaction_pool = TransactionPool::new();
+    let transactions = vec![
+        common::random_transaction(None, None),
+        common::random_transaction(None, None),
+    ];
+    let block = Block::new(&Block::genesis(), transactions.clone()).unwrap();
+    transaction_pool.add(transactions[0].clone(), 0).unwrap();
+    transaction_pool.undo_all(vec![block], &common::random_utxo_pool(None));
+    for transaction in &transactions {
+        assert!(transaction_pool.transactions().contains(transaction));
+    }
+    let bytes: usize = transactions.iter().map(|tx| tx.bytes()).sum();
+    assert_eq!(transaction_pool.bytes(), bytes);
+}
+
 #[test]
 fn transaction_pool_compatibility_of() {
     let mut transaction_pool = TransactionPool::new();
     let transaction = common::random_transaction(None, None);
     assert!(transaction_pool.compatibility_of(&transaction).is_ok());
 
-    transaction_pool.add(transaction.clone()).unwrap();
+    transaction_pool.add(transaction.clone(), 0).unwrap();
     assert!(transaction_pool.compatibility_of(&transaction).is_err());
 
     transaction_pool.remove(&transaction).unwrap();
     assert!(transaction_pool.compatibility_of(&transaction).is_ok());
 }
+
+fn fixed_size_transaction() -> Transaction {
+    let inputs = vec![common::random_transaction_input(None, None)];
+    let outputs = vec![common::random_transaction_output(None, None)];
+    common::random_transaction(Some(inputs), Some(outputs))
+}
+
+#[test]
+fn full_transaction_pool_evicts_lowest_fee_rate() {
+    let transactions: Vec<_> = (0..5).map(|_| fixed_size_transaction()).collect();
+    let bytes = transactions[0].bytes();
+    let mut transaction_pool = TransactionPool::with_limits(3 * bytes, Duration::from_secs(60));
+
+    for (fee, transaction) in transactions[..3].iter().enumerate() {
+        let evicted = transaction_pool.add(transaction.clone(), fee as u32 + 1);
+        assert!(evicted.unwrap().is_empty());
+    }
+    assert_eq!(transaction_pool.bytes(), 3 * bytes);
+
+    let evicted = transaction_pool.add(transactions[3].clone(), 4).unwrap();
+    assert_eq!(evicted, vec![transactions[0].clone()]);
+    assert_eq!(transaction_pool.size(), 3);
+    assert_eq!(transaction_pool.fee_of(&transactions[3]), Some(4));
+    assert!(transaction_pool.fee_of(&transactions[0]).is_none());
+
+    match transaction_pool.add(transactions[4].clone(), 2) {
+        Err(TransactionPoolError::Full) => {}
+        _ => panic!("Transaction paying the lowest fee rate added to a full pool"),
+    }
+    assert_eq!(transaction_pool.bytes(), 3 * bytes);
+}
+
+#[test]
+fn full_transaction_pool_evicts_descendants_along() {
+    let (_, parent, child) = chain();
+    let (unrelated, better) = (fixed_size_transaction(), fixed_size_transaction());
+    let max_bytes = parent.bytes() + child.bytes() + unrelated.bytes() + better.bytes();
+    let mut transaction_pool = TransactionPool::with_limits(max_bytes, Duration::from_secs(60));
+    transaction_pool.add(parent.clone(), 1).unwrap();
+    transaction_pool.add(child.clone(), 3).unwrap();
+    transaction_pool.add(unrelated.clone(), 2).unwrap();
+    transaction_pool.add(better.clone(), 9).unwrap();
+
+    let inputs = vec![
+        common::random_transaction_input(None, None),
+        common::random_transaction_input(None, None),
+    ];
+    let outputs = vec![common::random_transaction_output(None, None)];
+    let transaction = common::random_transaction(Some(inputs), Some(outputs));
+    let evicted = transaction_pool.add(transaction.clone(), 8).unwrap();
+    assert_eq!(evicted, vec![parent, child]);
+    assert!(transaction_pool.transactions().contains(&unrelated));
+    let bytes = unrelated.bytes() + better.bytes() + transaction.bytes();
+    assert_eq!(transaction_pool.bytes(), bytes);
+}
+
+#[test]
+fn transaction_pool_expire() {
+    let expiry = Duration::from_millis(50);
+    let mut transaction_pool = TransactionPool::with_limits(1 << 16, expiry);
+    let transaction1 = common::random_transaction(None, None);
+    let transaction2 = common::random_transaction(None, None);
+
+    transaction_pool.add(transaction1.clone(), 0).unwrap();
+    assert!(transaction_pool.expire().is_empty());
+    thread::sleep(2 * expiry);
+    transaction_pool.add(transaction2.clone(), 0).unwrap();
+    assert_eq!(transaction_pool.expire(), vec![transaction1]);
+    assert_eq!(transaction_pool.size(), 1);
+    assert_eq!(transaction_pool.bytes(), transaction2.bytes());
+}
+
+#[test]
+fn unlocked_wallet_inputs_are_spendable_again() {
+    let (public_key, secret_key) = common::random_key();
+    let utxo = common::random_utxo_with(None, None, Some(10), Some(public_key));
+    let mut utxos = HashSet::new();
+    utxos.insert(utxo);
+    let mut wallet = Wallet::new(public_key, secret_key, vec![public_key], utxos);
+    let transaction = common::random_transaction_with(
+        Some(secret_key),
+        Some(vec![common::random_public_key()]),
+        Some(vec![utxo]),
+        None,
+    );
+
+    assert!(wallet.add_pending(&transaction));
+    assert!(wallet.spendable().is_empty());
+    assert!(wallet.release(&transaction));
+    assert!(!wallet.release(&transaction));
+    assert_eq!(wallet.spendable(), vec![&utxo]);
+}
+
+/// Parent transaction spending a random utxo of its sender and child spending the output of
+/// the parent
+fn chain() -> (Utxo, Transaction, Transaction) {
+    let (public_key, secret_key) = common::random_key();
+    let utxo = common::random_utxo_with(None, None, Some(10), Some(public_key));
+    let parent = common::random_transaction_with(
+        Some(secret_key),
+        Some(vec![public_key]),
+        Some(vec![utxo]),
+        None,
+    );
+    let output = common::random_utxo_with(Some(*parent.id()), Some(0), Some(10), Some(public_key));
+    let child = common::random_transaction_with(
+        Some(secret_key),
+        Some(vec![common::random_public_key()]),
+        Some(vec![output]),
+        Some(vec![9]),
+    );
+    (utxo, parent, child)
+}
+
+#[test]
+fn chained_transactions_are_selected_parents_first() {
+    let mut transaction_pool = TransactionPool::new();
+    let (utxo, parent, child) = chain();
+    transaction_pool.add(child.clone(), 1).unwrap();
+    transaction_pool.add(parent.clone(), 0).unwrap();
+    assert_eq!(transaction_pool.ancestors_of(&child), vec![parent.clone()]);
+    assert_eq!(
+        transaction_pool.select(),
+        Some(vec![parent.clone(), child.clone()])
+    );
+
+    let mut utxos = HashSet::new();
+    utxos.insert(utxo);
+    let utxo_pool = common::random_utxo_pool(Some(utxos));
+    assert!(utxo_pool.check_utxos_exist_for(&child).is_err());
+    let utxo_view = transaction_pool.utxo_view_of(&utxo_pool);
+    assert!(utxo_view.check_utxos_exist_for(&child).is_ok());
+    assert!(utxo_view.authenticate(&child).is_ok());
+    assert_eq!(utxo_view.fee_of(&child), Some(1));
+    transaction_pool.remove(&parent).unwrap();
+    let utxo_view = transaction_pool.utxo_view_of(&utxo_pool);
+    assert!(utxo_view.check_utxos_exist_for(&child).is_err());
+    assert!(utxo_view.check_utxos_exist_for(&parent).is_ok());
+
+    let block = Block::new(&Block::genesis(), vec![parent.clone(), child.clone()]).unwrap();
+    assert!(utxo_pool.check_utxos_exist(&block).is_ok());
+    assert!(utxo_pool.check_signatures_of(&block).is_ok());
+    let block = Block::new(&Block::genesis(), vec![child, parent]).unwrap();
+    assert_eq!(
+        utxo_pool.check_utxos_exist(&block),
+        Err(UtxoPoolError::BlockHasUnknownUtxo)
+    );
+}
+
+#[test]
+fn double_spent_parent_takes_its_descendants_along() {
+    let mut transaction_pool = TransactionPool::new();
+    let (utxo, parent, child) = chain();
+    transaction_pool.add(parent, 0).unwrap();
+    transaction_pool.add(child, 1).unwrap();
+    let unrelated = common::random_transaction(None, None);
+    transaction_pool.add(unrelated, 0).unwrap();
+
+    let double_spend = common::random_transaction_with(None, None, Some(vec![utxo]), None);
+    let block = Block::new(
+        &Block::genesis(),
+        vec![double_spend, common::random_transaction(None, None)],
+    )
+    .unwrap();
+    transaction_pool.process(&block);
+    assert_eq!(transaction_pool.size(), 1);
+}
+
+#[test]
+fn orphans_wait_for_their_parents() {
+    let mut orphans = OrphanPool::new(Duration::from_secs(60));
+    let (_, parent, child) = chain();
+    assert!(orphans.add(child.clone()));
+    assert!(!orphans.add(child.clone()));
+    assert!(orphans.contains(&child));
+    assert!(orphans.take_children_of(&child).is_empty());
+    assert_eq!(orphans.take_children_of(&parent), vec![child]);
+    assert_eq!(orphans.size(), 0);
+}
+
+#[test]
+fn double_spend_race_is_won_by_the_first_seen_transaction() {
+    let (public_key, secret_key) = common::random_key();
+    let utxo = common::random_utxo_with(None, None, Some(10), Some(public_key));
+    let mut utxos = HashSet::new();
+    utxos.insert(utxo);
+    let recipients = vec![common::random_public_key(), common::random_public_key()];
+    let mut wallet = Wallet::new(public_key, secret_key, recipients.clone(), utxos);
+    let (tx1, tx2) = wallet.double_spend().unwrap();
+
+    let mut transaction_pool = TransactionPool::new();
+    transaction_pool.add(tx1.clone(), 0).unwrap();
+    match transaction_pool.add(tx2.clone(), 0) {
+        Err(TransactionPoolError::InsufficientFee(txid)) => assert_eq!(&txid, tx1.id()),
+        _ => panic!("Double spend replaced the first seen transaction for free"),
+    }
+    assert_eq!(
+        transaction_pool.conflicts_of(&tx2).collect::<Vec<_>>(),
+        vec![&tx1]
+    );
+    assert!(transaction_pool.transactions().contains(&tx1));
+}
+
+#[test]
+fn replace_by_fee_evicts_replaced_transaction_and_descendants() {
+    let (public_key, secret_key) = common::random_key();
+    let utxo = common::random_utxo_with(None, None, Some(10), Some(public_key));
+    let recipient = common::random_public_key();
+    let pay = |amount| {
+        common::random_transaction_with(
+            Some(secret_key),
+            Some(vec![recipient]),
+            Some(vec![utxo]),
+            Some(vec![amount]),
+        )
+    };
+    let (original, bump1, bump2) = (pay(10), pay(9), pay(8));
+    let output = common::random_utxo_with(Some(*original.id()), Some(0), Some(10), Some(recipient));
+    let child = common::random_transaction_with(None, None, Some(vec![output]), Some(vec![9]));
+    let spending_replaced =
+        common::random_transaction_with(None, None, Some(vec![utxo, output]), Some(vec![10]));
+
+    let mut transaction_pool = TransactionPool::new();
+    transaction_pool.add(original.clone(), 0).unwrap();
+    transaction_pool.add(child.clone(), 1).unwrap();
+    match transaction_pool.add(bump1, 1) {
+        Err(TransactionPoolError::InsufficientFee(_)) => {}
+        _ => panic!("Replacement not paying more than the replaced transactions accepted"),
+    }
+    match transaction_pool.add(spending_replaced, 10) {
+        Err(TransactionPoolError::UnknownUtxo(txid)) => assert_eq!(&txid, original.id()),
+        _ => panic!("Replacement spending an output of the replaced transaction accepted"),
+    }
+    assert_eq!(transaction_pool.size(), 2);
+
+    let evicted = transaction_pool.add(bump2.clone(), 2).unwrap();
+    assert_eq!(evicted, vec![original, child]);
+    assert_eq!(transaction_pool.size(), 1);
+    assert_eq!(transaction_pool.bytes(), bump2.bytes());
+}
+
+#[test]
+fn child_pays_for_parent() {
+    let (_, parent, child) = chain();
+    let unrelated: Vec<_> = (0..2).map(|_| fixed_size_transaction()).collect();
+    let mut transaction_pool = TransactionPool::new();
+    transaction_pool.add(parent.clone(), 0).unwrap();
+    transaction_pool.add(child.clone(), 10).unwrap();
+    for transaction in &unrelated {
+        transaction_pool.add(transaction.clone(), 1).unwrap();
+    }
+
+    let package = transaction_pool.package_of(&child, &[]);
+    assert_eq!(package, vec![parent.clone(), child.clone()]);
+    assert_eq!(transaction_pool.package_fee(&package), 10);
+    assert_eq!(
+        transaction_pool.package_of(&child, &[parent.clone()]),
+        vec![child.clone()]
+    );
+    assert_eq!(transaction_pool.select(), Some(package));
+
+    let rich = fixed_size_transaction();
+    transaction_pool.add(rich.clone(), 100).unwrap();
+    let selected = transaction_pool.select().unwrap();
+    assert_eq!(selected[0], rich);
+    assert!(unrelated.contains(&selected[1]));
+}
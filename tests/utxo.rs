@@ -12,6 +12,6 @@ fn utxo_id_ser_deser() {
 #[test]
 fn utxo_data_ser_deser() {
     let utxo_data = common::random_utxo_data(None, None);
-    let utxo_data2 = UtxoData::deserialize(utxo_data.serialize());
+    let utxo_data2 = UtxoData::deserialize(utxo_data.serialize()).unwrap();
     assert_eq!(utxo_data, utxo_data2);
 }
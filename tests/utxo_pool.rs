@@ -3,9 +3,12 @@ use rand::seq::SliceRandom;
 use rand::Rng;
 use std::collections::HashSet;
 
+use blockchain::block::Block;
 use blockchain::blockchain::Blockchain;
 use blockchain::constants::UTXO_HASH_INIT;
-use blockchain::utxo::Utxo;
+use blockchain::error::utxo_pool::UtxoPoolError;
+use blockchain::transaction::{Transaction, TransactionInput, TransactionOutput};
+use blockchain::utxo::{OutputType, Utxo, UtxoData, UtxoId};
 use blockchain::utxo_pool::UtxoPool;
 use blockchain::Hash;
 
@@ -111,3 +114,151 @@ fn utxo_pool_process_undo_tx() {
     utxo_pool.undo_t(&tx, &blockchain, blockchain.top());
     assert_eq!(utxo_pool, utxo_pool_cl);
 }
+
+#[test]
+fn utxo_pool_indexes_utxos_by_public_key() {
+    let mut rng = rand::thread_rng();
+    let (pk, _) = common::random_key();
+    let pk_utxos_len = rng.gen_range(1, common::UTXOS_PER_KEY_MAX);
+    let pk_utxos: HashSet<_> = (0..pk_utxos_len)
+        .map(|i| {
+            common::random_utxo_with(Some(Hash::from(UTXO_HASH_INIT)), Some(i), None, Some(pk))
+        })
+        .collect();
+    let other_utxos: HashSet<_> = (pk_utxos_len..pk_utxos_len + common::UTXOS_PER_KEY_MAX)
+        .map(|i| common::random_utxo_with(Some(Hash::from(UTXO_HASH_INIT)), Some(i), None, None))
+        .collect();
+    let utxos: HashSet<_> = pk_utxos.union(&other_utxos).copied().collect();
+    let mut utxo_pool = UtxoPool::from(utxos.clone());
+    let blockchain = Blockchain::new(utxos.iter().map(|u| (*u.id(), *u.data())).collect());
+    let balance = |utxos: &HashSet<Utxo>| utxos.iter().map(|u| u.amount() as u64).sum::<u64>();
+    assert_eq!(utxo_pool.owned_by(&pk), pk_utxos);
+    assert_eq!(utxo_pool.balance_of(&pk), balance(&pk_utxos));
+
+    let spent = pk_utxos.iter().copied().choose(&mut rng).unwrap();
+    let tx = common::random_transaction_with(None, None, Some(vec![spent]), None);
+    utxo_pool.process_t(&tx);
+    let mut unspent = pk_utxos.clone();
+    unspent.remove(&spent);
+    assert_eq!(utxo_pool.owned_by(&pk), unspent);
+    assert_eq!(utxo_pool.balance_of(&pk), balance(&unspent));
+
+    utxo_pool.undo_t(&tx, &blockchain, blockchain.top());
+    assert_eq!(utxo_pool.owned_by(&pk), pk_utxos);
+    assert!(utxo_pool.owned_by(&common::random_public_key()).is_empty());
+}
+
+#[test]
+fn utxo_pool_check_signatures_of_mixed_output_types() {
+    let keys: Vec<_> = (0..4).map(|_| common::random_key()).collect();
+    let utxos: Vec<_> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, (pk, _))| {
+            let output_type = if i == 0 {
+                OutputType::Ecdsa
+            } else {
+                OutputType::Schnorr
+            };
+            let utxo_id = common::random_utxo_id(None, None);
+            Utxo::new(utxo_id, UtxoData::with_type(1, *pk, output_type))
+        })
+        .collect();
+    let utxo_pool = common::random_utxo_pool(Some(utxos.iter().copied().collect()));
+    let secret_keys: Vec<_> = keys.iter().map(|(_, sk)| *sk).collect();
+    let outputs = || vec![common::random_transaction_output(None, None)];
+    let transactions = vec![
+        Transaction::sign_utxos(&utxos[..2], outputs(), &secret_keys[..2]),
+        Transaction::sign_utxos(&utxos[2..], outputs(), &secret_keys[2..]),
+    ];
+    let block = Block::new(&Block::genesis(), transactions.clone()).unwrap();
+    assert!(utxo_pool.check_signatures_of(&block).is_ok());
+    for transaction in &transactions {
+        assert!(utxo_pool.authenticate(transaction).is_ok());
+    }
+
+    let tampered = tamper(&transactions[1], 1);
+    assert!(utxo_pool.authenticate(&tampered).is_err());
+    let block = Block::new(&Block::genesis(), vec![transactions[0].clone(), tampered]).unwrap();
+    assert_eq!(
+        utxo_pool.check_signatures_of(&block),
+        Err(UtxoPoolError::BlockHasInvalidSignature(1, 1))
+    );
+}
+
+/// Copy of a transaction with the signature of an input altered
+fn tamper(transaction: &Transaction, input: usize) -> Transaction {
+    let mut inputs = transaction.inputs().clone();
+    let mut sig = *inputs[input].sig();
+    sig[63] ^= 1;
+    inputs[input] =
+        TransactionInput::new(*inputs[input].utxo_id(), *inputs[input].public_key(), sig);
+    Transaction::new(inputs, transaction.outputs().clone())
+}
+
+#[test]
+fn utxo_pool_check_signatures_of_reports_first_invalid_input() {
+    let mut rng = rand::thread_rng();
+    let (transactions_len, inputs_len) = (32, 4);
+    let (pk, sk) = common::random_key();
+    let mut utxos = HashSet::new();
+    let mut transactions = Vec::new();
+    for t in 0..transactions_len - 1 {
+        let keys: Vec<_> = (0..inputs_len).map(|_| common::random_key()).collect();
+        let spent: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, (pk, _))| {
+                let output_type = if (t + i) % 3 == 0 {
+                    OutputType::Ecdsa
+                } else {
+                    OutputType::Schnorr
+                };
+                let utxo_id = common::random_utxo_id(None, None);
+                Utxo::new(utxo_id, UtxoData::with_type(1, *pk, output_type))
+            })
+            .collect();
+        utxos.extend(spent.iter().copied());
+        let output = TransactionOutput::with_type(1, pk, OutputType::Schnorr);
+        let secret_keys: Vec<_> = keys.iter().map(|(_, sk)| *sk).collect();
+        transactions.push(Transaction::sign_utxos(&spent, vec![output], &secret_keys));
+    }
+    // The last transaction spends an output created earlier in the block
+    let utxo_id = UtxoId::new(*transactions[0].id(), 0);
+    let spent = [Utxo::new(
+        utxo_id,
+        UtxoData::with_type(1, pk, OutputType::Schnorr),
+    )];
+    let output = common::random_transaction_output(None, None);
+    transactions.push(Transaction::sign_utxos(&spent, vec![output], &[sk]));
+    let utxo_pool = UtxoPool::from(utxos);
+    let block = Block::new(&Block::genesis(), transactions.clone()).unwrap();
+    assert!(utxo_pool.check_signatures_of(&block).is_ok());
+
+    let chained = transactions_len - 1;
+    let mut tampered = transactions.clone();
+    tampered[chained] = tamper(&transactions[chained], 0);
+    let block = Block::new(&Block::genesis(), tampered).unwrap();
+    assert_eq!(
+        utxo_pool.check_signatures_of(&block),
+        Err(UtxoPoolError::BlockHasInvalidSignature(chained, 0))
+    );
+
+    let positions: Vec<_> = transactions
+        .iter()
+        .enumerate()
+        .flat_map(|(t, transaction)| (0..transaction.inputs().len()).map(move |i| (t, i)))
+        .choose_multiple(&mut rng, 3);
+    let mut tampered = transactions;
+    for &(t, i) in &positions {
+        tampered[t] = tamper(&tampered[t], i);
+    }
+    let (t, i) = positions.into_iter().min().unwrap();
+    let block = Block::new(&Block::genesis(), tampered).unwrap();
+    for _ in 0..4 {
+        assert_eq!(
+            utxo_pool.check_signatures_of(&block),
+            Err(UtxoPoolError::BlockHasInvalidSignature(t, i))
+        );
+    }
+}
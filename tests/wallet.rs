@@ -0,0 +1,801 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+use blockchain::address::Address;
+use blockchain::block::Block;
+use blockchain::blockchain::Blockchain;
+use blockchain::constants::{GAP_LIMIT, MATURITY, UTXO_HASH_INIT};
+use blockchain::error::wallet::WalletError;
+use blockchain::transaction::partial::PartialTransaction;
+use blockchain::transaction::{Transaction, TransactionOutput};
+use blockchain::utxo::{self, OutputType, Utxo, UtxoData, UtxoId};
+use blockchain::utxo_pool::UtxoPool;
+use blockchain::wallet::balance::Balance;
+use blockchain::wallet::coin_selection::{BranchAndBound, CoinSelection, Knapsack, LargestFirst};
+use blockchain::wallet::hd::{self, Chain, ExtendedKey, HARDENED};
+use blockchain::wallet::history::Direction;
+use blockchain::wallet::mnemonic::Mnemonic;
+use blockchain::wallet::Wallet;
+use blockchain::Hash;
+
+pub mod common;
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Blockchain whose initial utxo pays `amount` to a random key, along with that utxo and key
+fn funded_blockchain(amount: u32) -> (Blockchain, Utxo, SecretKey) {
+    let (public_key, secret_key) = common::random_key();
+    let utxo_id = UtxoId::new(Hash::from(UTXO_HASH_INIT), 0);
+    let utxo = Utxo::new(utxo_id, UtxoData::new(amount, public_key));
+    let mut initial_utxos = HashMap::new();
+    initial_utxos.insert(*utxo.utxo_id(), *utxo.utxo_data());
+    (Blockchain::new(initial_utxos), utxo, secret_key)
+}
+
+/// Pushes a block paying `amounts` to `recipients` out of the utxo
+fn pay(
+    blockchain: &mut Blockchain,
+    utxo: &Utxo,
+    secret_key: &SecretKey,
+    recipients: &[PublicKey],
+) -> Transaction {
+    let amount = utxo.amount() / recipients.len() as u32;
+    let outputs = recipients
+        .iter()
+        .map(|recipient| TransactionOutput::new(amount, *recipient))
+        .collect();
+    let transaction = Transaction::sign(vec![*utxo.utxo_id()], outputs, secret_key);
+    let block = Block::new(blockchain.top(), vec![transaction.clone()]).unwrap();
+    blockchain.push(block).unwrap();
+    transaction
+}
+
+#[test]
+fn hmac_sha512_test_vector() {
+    let mac = hd::hmac_sha512(b"Jefe", b"what do ya want for nothing?");
+    assert_eq!(
+        mac.to_vec(),
+        from_hex(
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+             9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        )
+    );
+}
+
+#[test]
+fn extended_key_test_vector() {
+    let secp = Secp256k1::new();
+    let master = ExtendedKey::master(&from_hex("000102030405060708090a0b0c0d0e0f"));
+    assert_eq!(
+        master.secret_key()[..],
+        from_hex("e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35")[..]
+    );
+    assert_eq!(
+        master.chain_code()[..],
+        from_hex("873dff81c02f525623fd1fe5167eac3a55a049de3d314bb42ee227ffed37d508")[..]
+    );
+    assert_eq!(
+        master.derive(HARDENED, &secp).secret_key()[..],
+        from_hex("edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea")[..]
+    );
+    assert_eq!(
+        master.derive_path(&[HARDENED, 1], &secp).secret_key()[..],
+        from_hex("3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368")[..]
+    );
+}
+
+#[test]
+fn mnemonic_round_trip() {
+    let mnemonic = Mnemonic::generate();
+    assert_eq!(mnemonic.words().len(), 12);
+    assert_eq!(Mnemonic::parse(&mnemonic.to_string()).unwrap(), mnemonic);
+
+    let long = Mnemonic::from_entropy(&[0xab; 32]).unwrap();
+    assert_eq!(long.words().len(), 24);
+    assert_eq!(Mnemonic::parse(&long.to_string()).unwrap(), long);
+
+    assert!(Mnemonic::from_entropy(&[0; 15]).is_err());
+    assert!(Mnemonic::from_entropy(&[0; 18]).is_err());
+}
+
+#[test]
+fn mnemonic_seed_test_vector() {
+    let mnemonic = Mnemonic::from_entropy(&[0; 16]).unwrap();
+    assert_eq!(
+        mnemonic.to_seed("TREZOR").to_vec(),
+        from_hex(
+            "608d490a22aaaec9e2cf358152367a983f956ee0f85bf5cceab4da01ae52b83b\
+             412270d58de20c08f09b2e1a7a7c0d518137fe0a1f914a28ec382e48a9c1cbf1"
+        )
+    );
+}
+
+#[test]
+fn mnemonic_rejects_typos() {
+    let mnemonic = Mnemonic::from_entropy(&[0; 16]).unwrap();
+    let mut words = mnemonic.words();
+
+    assert!(Mnemonic::parse(&words[1..].join(" ")).is_err());
+
+    words[0] = String::from("xxxx");
+    assert!(Mnemonic::parse(&words.join(" ")).is_err());
+
+    let mut words = mnemonic.words();
+    words[0] = if words[0] == "baba" { "babe" } else { "baba" }.to_string();
+    assert!(Mnemonic::parse(&words.join(" ")).is_err());
+}
+
+#[test]
+fn wallets_restored_from_a_mnemonic_derive_the_same_keys() {
+    let mnemonic = Mnemonic::generate();
+    let mut wallet1 = Wallet::from_mnemonic(mnemonic.clone(), "passphrase", Vec::new());
+    let mut wallet2 = Wallet::from_mnemonic(mnemonic.clone(), "passphrase", Vec::new());
+    let mut wallet3 = Wallet::from_mnemonic(mnemonic, "", Vec::new());
+
+    let receive_key = wallet1.receive_key().unwrap();
+    let change_key = wallet1.change_key().unwrap();
+    assert_eq!(wallet2.receive_key().unwrap(), receive_key);
+    assert_eq!(wallet2.change_key().unwrap(), change_key);
+    assert_ne!(wallet3.receive_key().unwrap(), receive_key);
+    assert!(wallet2
+        .secret_key_of(&utxo::key_hash(&receive_key))
+        .is_some());
+    assert!(wallet3
+        .secret_key_of(&utxo::key_hash(&receive_key))
+        .is_none());
+}
+
+#[test]
+fn fresh_keys_are_never_handed_out_twice() {
+    let mut wallet = Wallet::from_mnemonic(Mnemonic::generate(), "", Vec::new());
+    let keys: HashSet<_> = (0..2 * GAP_LIMIT)
+        .flat_map(|_| vec![wallet.receive_key().unwrap(), wallet.change_key().unwrap()])
+        .collect();
+    assert_eq!(keys.len(), 4 * GAP_LIMIT);
+    assert!(keys.iter().all(|key| wallet.owns(&utxo::key_hash(key))));
+    assert_eq!(
+        wallet.keychain().unwrap().issued(Chain::Receive),
+        2 * GAP_LIMIT as u32
+    );
+    assert_eq!(
+        wallet.keychain().unwrap().derived(Chain::Receive),
+        3 * GAP_LIMIT as u32
+    );
+}
+
+#[test]
+fn scan_finds_payments_within_the_gap_limit() {
+    let mnemonic = Mnemonic::generate();
+    let mut wallet = Wallet::from_mnemonic(mnemonic.clone(), "", Vec::new());
+    let keys: Vec<_> = (0..3 * GAP_LIMIT)
+        .map(|_| wallet.receive_key().unwrap())
+        .collect();
+
+    // Each payment moves the gap limit further, the last one falls past it
+    let (mut blockchain, utxo, secret_key) = funded_blockchain(300);
+    let recipients = [
+        keys[GAP_LIMIT - 1],
+        keys[2 * GAP_LIMIT - 2],
+        keys[3 * GAP_LIMIT - 1],
+    ];
+    pay(&mut blockchain, &utxo, &secret_key, &recipients);
+
+    let mut restored = Wallet::from_mnemonic(mnemonic, "", Vec::new());
+    restored.scan(&blockchain);
+    let utxos: HashSet<_> = restored.utxos().iter().map(|u| *u.key_hash()).collect();
+    assert_eq!(utxos, recipients[..2].iter().map(utxo::key_hash).collect());
+    assert_eq!(
+        restored
+            .keychain()
+            .unwrap()
+            .path_of(&utxo::key_hash(&keys[2 * GAP_LIMIT - 2])),
+        Some((Chain::Receive, 2 * GAP_LIMIT as u32 - 2))
+    );
+}
+
+#[test]
+fn process_and_undo_recognise_derived_keys() {
+    let (public_key, secret_key) = common::random_key();
+    let (mut blockchain, utxo, sender) = funded_blockchain(100);
+    let mut wallet = Wallet::new(public_key, secret_key, Vec::new(), HashSet::new());
+    let recipients = [
+        wallet.receive_key().unwrap(),
+        wallet.change_key().unwrap(),
+        public_key,
+    ];
+    let transaction = pay(&mut blockchain, &utxo, &sender, &recipients);
+
+    let block = blockchain.top().clone();
+    wallet.process(&block);
+    let expected: HashSet<_> = (0..recipients.len())
+        .map(|vout| {
+            let utxo_id = UtxoId::new(*transaction.id(), vout);
+            Utxo::new(utxo_id, UtxoData::new(33, recipients[vout]))
+        })
+        .collect();
+    assert_eq!(wallet.utxos(), &expected);
+
+    wallet.undo(&block, &blockchain);
+    assert!(wallet.utxos().is_empty());
+}
+
+/// Wallet holding the utxos of a payment to one of its derived keys, along with the blockchain
+fn funded_wallet() -> (Wallet, Blockchain) {
+    let (public_key, secret_key) = common::random_key();
+    let (mut blockchain, utxo, sender) = funded_blockchain(100);
+    let mut wallet = Wallet::new(public_key, secret_key, vec![public_key], HashSet::new());
+    let recipients = [wallet.receive_key().unwrap(), public_key];
+    pay(&mut blockchain, &utxo, &sender, &recipients);
+    wallet.process(blockchain.top());
+    (wallet, blockchain)
+}
+
+#[test]
+fn encrypted_wallet_file_round_trip() {
+    let (mut wallet, _) = funded_wallet();
+    let path = env::temp_dir().join(format!("wallet-{}", rand::random::<u64>()));
+    assert!(matches!(wallet.save(&path), Err(WalletError::NotEncrypted)));
+
+    wallet.encrypt("correct horse").unwrap();
+    wallet.save(&path).unwrap();
+    let mut loaded = Wallet::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(loaded.is_locked());
+    assert!(loaded.mnemonic().is_none());
+    assert_eq!(loaded.utxos(), wallet.utxos());
+
+    let utxos: Vec<Utxo> = loaded.utxos().iter().copied().collect();
+    let output = TransactionOutput::new(1, common::random_public_key());
+    assert!(matches!(
+        loaded.sign(&utxos, vec![output]),
+        Err(WalletError::Locked)
+    ));
+    assert!(loaded.initiate().is_none());
+    assert!(loaded.double_spend().is_none());
+
+    assert!(matches!(
+        loaded.unlock_keys("battery staple"),
+        Err(WalletError::WrongPassphrase)
+    ));
+    loaded.unlock_keys("correct horse").unwrap();
+    assert_eq!(loaded.mnemonic(), wallet.mnemonic());
+    assert!(loaded.sign(&utxos, vec![output]).is_ok());
+    for utxo in &utxos {
+        assert_eq!(
+            loaded.secret_key_of(utxo.key_hash()),
+            wallet.secret_key_of(utxo.key_hash())
+        );
+    }
+}
+
+#[test]
+fn locked_wallet_keeps_tracking_its_keys() {
+    let (mut wallet, _) = funded_wallet();
+    assert!(matches!(wallet.lock_keys(), Err(WalletError::NotEncrypted)));
+    assert!(!wallet.is_locked());
+
+    wallet.encrypt("passphrase").unwrap();
+    wallet.lock_keys().unwrap();
+    assert!(wallet.is_locked());
+    let (public_key, secret_key) = common::random_key();
+    assert!(matches!(
+        wallet.import(public_key, secret_key),
+        Err(WalletError::Locked)
+    ));
+
+    // Keys handed out while locked are derived from the extended public keys
+    let receive_key = wallet.receive_key().unwrap();
+    assert!(wallet
+        .secret_key_of(&utxo::key_hash(&receive_key))
+        .is_none());
+    let transaction = Transaction::sign(
+        vec![common::random_utxo_id(None, None)],
+        vec![TransactionOutput::new(10, receive_key)],
+        &common::random_secret_key(),
+    );
+    wallet.process_t(&transaction);
+    assert!(wallet
+        .utxos()
+        .iter()
+        .any(|u| u.utxo_data().pays(&receive_key)));
+
+    wallet.unlock_keys("passphrase").unwrap();
+    assert!(wallet
+        .secret_key_of(&utxo::key_hash(&receive_key))
+        .is_some());
+}
+
+#[test]
+fn tampered_wallet_file_is_rejected() {
+    let (mut wallet, _) = funded_wallet();
+    wallet.encrypt("passphrase").unwrap();
+    let bytes = wallet.serialize().unwrap();
+
+    assert!(Wallet::deserialize(&bytes).is_ok());
+    for len in (0..bytes.len()).step_by(13).chain(Some(bytes.len() - 1)) {
+        assert!(matches!(
+            Wallet::deserialize(&bytes[..len]),
+            Err(WalletError::InvalidWalletFile)
+        ));
+    }
+
+    // The cost follows the presence flag and salt, and is checked before any key derivation
+    let cost = 1 + 1 + 16;
+    for (offset, value) in &[(0, u32::MAX), (0, 1), (4, u32::MAX), (4, 1)] {
+        let mut tampered = bytes.clone();
+        tampered[cost + offset..cost + offset + 4].copy_from_slice(&value.to_be_bytes());
+        assert!(matches!(
+            Wallet::deserialize(&tampered),
+            Err(WalletError::InvalidWalletFile)
+        ));
+    }
+
+    // The ciphertext starts after the presence flag, salt, cost, nonce and length
+    let mut tampered = bytes.clone();
+    tampered[1 + 1 + 16 + 4 + 4 + 12 + 8] ^= 1;
+    let mut wallet = Wallet::deserialize(&tampered).unwrap();
+    assert!(matches!(
+        wallet.unlock_keys("passphrase"),
+        Err(WalletError::WrongPassphrase)
+    ));
+}
+
+fn utxos_of(amounts: &[u32]) -> Vec<Utxo> {
+    amounts
+        .iter()
+        .map(|amount| common::random_utxo_with(None, None, Some(*amount), None))
+        .collect()
+}
+
+fn amounts_of(utxos: Option<Vec<Utxo>>) -> Vec<u32> {
+    let mut amounts: Vec<_> = utxos.unwrap().iter().map(|u| u.amount()).collect();
+    amounts.sort();
+    amounts
+}
+
+#[test]
+fn largest_first_selection() {
+    let utxos = utxos_of(&[1, 2, 5, 8]);
+    assert_eq!(amounts_of(LargestFirst.select(&utxos, 0, 9, 0)), vec![5, 8]);
+    assert_eq!(amounts_of(LargestFirst.select(&utxos, 0, 8, 0)), vec![8]);
+    assert!(LargestFirst.select(&utxos, 0, 17, 0).is_none());
+}
+
+#[test]
+fn branch_and_bound_selection() {
+    let utxos = utxos_of(&[1, 2, 5, 8]);
+    assert_eq!(
+        amounts_of(BranchAndBound.select(&utxos, 0, 7, 0)),
+        vec![2, 5]
+    );
+    assert_eq!(
+        amounts_of(BranchAndBound.select(&utxos, 0, 12, 1)),
+        vec![5, 8]
+    );
+    assert!(BranchAndBound.select(&utxos, 0, 17, 0).is_none());
+
+    // Effective values are 8, 1 and 1
+    let utxos = utxos_of(&[3, 3, 10]);
+    assert_eq!(
+        amounts_of(BranchAndBound.select(&utxos, 2, 2, 0)),
+        vec![3, 3]
+    );
+
+    // Without an exact match, falls back on knapsack
+    let utxos = utxos_of(&[4, 8]);
+    assert_eq!(amounts_of(BranchAndBound.select(&utxos, 0, 5, 0)), vec![8]);
+}
+
+#[test]
+fn knapsack_selection() {
+    let utxos = utxos_of(&[1, 2, 5, 8, 20]);
+    let amounts = amounts_of(Knapsack.select(&utxos, 0, 13, 0));
+    assert_eq!(amounts.iter().sum::<u32>(), 13);
+    assert_eq!(amounts_of(Knapsack.select(&utxos, 0, 20, 0)), vec![20]);
+    assert_eq!(amounts_of(Knapsack.select(&utxos, 0, 17, 0)), vec![20]);
+    assert!(Knapsack.select(&utxos, 0, 37, 0).is_none());
+}
+
+/// Spends all the utxos, whatever they are worth
+struct SelectAll;
+
+impl CoinSelection for SelectAll {
+    fn select(&self, utxos: &[Utxo], _: u32, _: u64, _: u64) -> Option<Vec<Utxo>> {
+        Some(utxos.to_vec())
+    }
+}
+
+#[test]
+fn create_payment_checks_the_coin_selection() {
+    let (public_key, secret_key) = common::random_key();
+    let recipient = common::random_public_key();
+    let mut utxos = HashSet::new();
+    utxos.insert(common::random_utxo_with(
+        None,
+        None,
+        Some(5),
+        Some(public_key),
+    ));
+    let mut wallet = Wallet::new(public_key, secret_key, vec![public_key], utxos);
+    wallet.set_coin_selection(Box::new(SelectAll));
+    assert!(matches!(
+        wallet.create_payment(recipient, 0, 100),
+        Err(WalletError::InsufficientFunds)
+    ));
+
+    let mut utxos = HashSet::new();
+    for _ in 0..2 {
+        utxos.insert(common::random_utxo_with(
+            None,
+            None,
+            Some(u32::MAX),
+            Some(public_key),
+        ));
+    }
+    let mut wallet = Wallet::new(public_key, secret_key, vec![public_key], utxos);
+    wallet.set_coin_selection(Box::new(SelectAll));
+    assert!(matches!(
+        wallet.create_payment(recipient, 1, 100),
+        Err(WalletError::ChangeTooLarge)
+    ));
+    assert!(wallet.create_payment(recipient, u32::MAX, 100).is_ok());
+}
+
+#[test]
+fn create_payment_pays_the_fee_and_returns_change() {
+    let (mut wallet, _) = funded_wallet();
+    let recipient = common::random_public_key();
+    let fee_rate = 100;
+    for coin_selection in vec![
+        Box::new(LargestFirst) as Box<dyn CoinSelection>,
+        Box::new(BranchAndBound),
+        Box::new(Knapsack),
+    ] {
+        wallet.set_coin_selection(coin_selection);
+        let transaction = wallet.create_payment(recipient, 20, fee_rate).unwrap();
+        assert_eq!(transaction.outputs()[0].amount(), 20);
+        assert!(transaction.outputs()[0].utxo_data().pays(&recipient));
+        assert_eq!(transaction.outputs().len(), 2);
+        assert!(wallet.owns(transaction.outputs()[1].key_hash()));
+
+        let inputs: u32 = transaction
+            .inputs()
+            .iter()
+            .map(|input| {
+                let utxo = wallet.utxos().iter().find(|u| u.id() == input.utxo_id());
+                utxo.unwrap().amount()
+            })
+            .sum();
+        let outputs: u32 = transaction.outputs().iter().map(|o| o.amount()).sum();
+        let fee = inputs - outputs;
+        assert!(fee as usize * 1000 >= transaction.bytes() * fee_rate as usize);
+        assert!(wallet.release(&transaction));
+    }
+
+    // The utxos are worth 50 each, spending one costs 11 at this fee rate
+    assert!(matches!(
+        wallet.create_payment(recipient, 90, fee_rate),
+        Err(WalletError::InsufficientFunds)
+    ));
+    wallet.encrypt("passphrase").unwrap();
+    wallet.lock_keys().unwrap();
+    assert!(matches!(
+        wallet.create_payment(recipient, 20, fee_rate),
+        Err(WalletError::Locked)
+    ));
+}
+
+#[test]
+fn history_follows_the_main_chain() {
+    let mut wallet = Wallet::from_mnemonic(Mnemonic::generate(), "", Vec::new());
+    let (mut blockchain, utxo, sender) = funded_blockchain(100);
+    let recipient = common::random_public_key();
+    let received = pay(
+        &mut blockchain,
+        &utxo,
+        &sender,
+        &[wallet.receive_key().unwrap(), recipient],
+    );
+    wallet.process(blockchain.top());
+
+    let sent = wallet.create_payment(recipient, 20, 0).unwrap();
+    let block = Block::new(blockchain.top(), vec![sent.clone()]).unwrap();
+    blockchain.push(block).unwrap();
+    wallet.process(blockchain.top());
+
+    let receive_key = wallet.receive_key().unwrap();
+    let internal = wallet.create_payment(receive_key, 10, 0).unwrap();
+    let block = Block::new(blockchain.top(), vec![internal.clone()]).unwrap();
+    blockchain.push(block).unwrap();
+    wallet.process(blockchain.top());
+
+    let summary: Vec<_> = wallet
+        .history()
+        .iter()
+        .map(|entry| (*entry.txid(), entry.direction(), entry.amount()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (*received.id(), Direction::Received, 50),
+            (*sent.id(), Direction::Sent, -20),
+            (*internal.id(), Direction::Internal, 0),
+        ]
+    );
+    let entry = wallet.entry_of(received.id()).unwrap();
+    assert_eq!(entry.height(), 1);
+    assert_eq!(wallet.confirmations_of(entry), 3);
+    assert!(wallet.to_string().contains("received +50"));
+
+    // A reorg takes the transactions of the undone blocks out of the history
+    let top = blockchain.top().clone();
+    wallet.undo(&top, &blockchain);
+    let parent = blockchain.parent_of(&top).unwrap().clone();
+    wallet.undo(&parent, &blockchain);
+    assert_eq!(wallet.history().len(), 1);
+    assert_eq!(wallet.height(), 1);
+    assert_eq!(wallet.confirmations_of(&wallet.history()[0]), 1);
+    assert!(wallet.entry_of(sent.id()).is_none());
+
+    wallet.encrypt("passphrase").unwrap();
+    let loaded = Wallet::deserialize(wallet.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.history(), wallet.history());
+    assert_eq!(loaded.height(), 1);
+}
+
+#[test]
+fn pending_transactions_lock_their_inputs() {
+    let (mut wallet, _) = funded_wallet();
+    let recipient = common::random_public_key();
+    let transaction1 = wallet.create_payment(recipient, 20, 0).unwrap();
+    assert_eq!(wallet.pending(), vec![&transaction1]);
+    assert!(!wallet.add_pending(&transaction1));
+    let transaction2 = wallet.create_payment(recipient, 20, 0).unwrap();
+    assert!(!transaction1.shares_utxo_with(&transaction2));
+    assert!(matches!(
+        wallet.create_payment(recipient, 20, 0),
+        Err(WalletError::InsufficientFunds)
+    ));
+    assert_eq!(wallet.pending().len(), 2);
+
+    // Waits for the transactions to be stale
+    assert!(wallet.rebroadcast(Duration::from_secs(60)).is_empty());
+    thread::sleep(Duration::from_millis(10));
+    let stale: HashSet<_> = wallet
+        .rebroadcast(Duration::from_millis(5))
+        .into_iter()
+        .collect();
+    assert_eq!(
+        stale,
+        vec![transaction1.clone(), transaction2]
+            .into_iter()
+            .collect()
+    );
+    assert!(wallet.rebroadcast(Duration::from_millis(5)).is_empty());
+
+    assert!(wallet.abandon(&transaction1));
+    assert!(!wallet.abandon(&transaction1));
+    assert!(wallet.create_payment(recipient, 20, 0).is_ok());
+}
+
+#[test]
+fn balance_by_state() {
+    let (mut wallet, mut blockchain) = funded_wallet();
+    assert_eq!(wallet.balance(), Balance::new(0, 100, 0));
+
+    let recipient = common::random_public_key();
+    let transaction = wallet.create_payment(recipient, 20, 0).unwrap();
+    assert_eq!(wallet.balance(), Balance::new(0, 50, 30));
+    assert_eq!(wallet.balance().total(), 80);
+
+    for _ in 1..MATURITY {
+        let transactions = vec![common::random_transaction(None, None)];
+        let block = Block::new(blockchain.top(), transactions).unwrap();
+        blockchain.push(block).unwrap();
+        wallet.process(blockchain.top());
+    }
+    assert_eq!(wallet.balance(), Balance::new(50, 0, 30));
+
+    let block = Block::new(blockchain.top(), vec![transaction]).unwrap();
+    blockchain.push(block).unwrap();
+    wallet.process(blockchain.top());
+    assert!(wallet.pending().is_empty());
+    assert_eq!(wallet.balance(), Balance::new(50, 30, 0));
+}
+
+#[test]
+fn watch_only_wallet_tracks_without_spending() {
+    let (mut blockchain, utxo, sender) = funded_blockchain(100);
+    let (watched, _) = common::random_key();
+    let received = pay(
+        &mut blockchain,
+        &utxo,
+        &sender,
+        &[watched, common::random_public_key()],
+    );
+    let mut wallet = Wallet::watch_only(vec![watched], Vec::new());
+    wallet.scan(&blockchain);
+    assert!(wallet.is_watch_only());
+    assert_eq!(wallet.utxos().len(), 1);
+    assert_eq!(wallet.balance().total(), 50);
+    assert_eq!(wallet.entry_of(received.id()).unwrap().amount(), 50);
+    assert!(wallet.spendable().is_empty());
+    assert!(matches!(wallet.receive_key(), Err(WalletError::WatchOnly)));
+    assert!(matches!(
+        wallet.create_payment(common::random_public_key(), 10, 0),
+        Err(WalletError::Locked)
+    ));
+
+    // Watch-only wallets have no secrets to seal, so they are saved as is
+    let loaded = Wallet::deserialize(wallet.serialize().unwrap()).unwrap();
+    assert!(loaded.is_watch_only());
+    assert_eq!(loaded.utxos(), wallet.utxos());
+    assert!(loaded.tracks(&utxo::key_hash(&watched)));
+
+    let top = blockchain.top().clone();
+    wallet.undo(&top, &blockchain);
+    assert!(wallet.utxos().is_empty());
+    assert!(wallet.history().is_empty());
+}
+
+#[test]
+fn watched_keys_are_not_spendable() {
+    let (mut wallet, mut blockchain) = funded_wallet();
+    let (watched, secret_key) = common::random_key();
+    assert!(wallet.watch(watched));
+    assert!(!wallet.watch(watched));
+    assert!(!wallet.owns(&utxo::key_hash(&watched)));
+    let utxo = *wallet.spendable()[0];
+    let transaction = wallet
+        .sign(
+            &[utxo],
+            vec![TransactionOutput::new(utxo.amount(), watched)],
+        )
+        .unwrap();
+    let block = Block::new(blockchain.top(), vec![transaction]).unwrap();
+    blockchain.push(block).unwrap();
+    wallet.process(blockchain.top());
+    assert_eq!(wallet.balance().total(), 100);
+    assert_eq!(wallet.spendable().len(), 1);
+    assert_eq!(
+        wallet.history().last().unwrap().direction(),
+        Direction::Internal
+    );
+
+    // Importing the secret key of a watched key makes its utxos spendable
+    wallet.import(watched, secret_key).unwrap();
+    assert_eq!(wallet.spendable().len(), 2);
+}
+
+#[test]
+fn offline_wallet_signs_for_a_watch_only_one() {
+    let (offline, blockchain) = funded_wallet();
+    let addresses = offline
+        .utxos()
+        .iter()
+        .map(|utxo| utxo.utxo_data().address())
+        .collect();
+    let mut online = Wallet::watch_only(addresses, Vec::new());
+    online.scan(&blockchain);
+    assert_eq!(online.utxos(), offline.utxos());
+
+    let recipient = common::random_public_key();
+    let partial = online.create_partial(recipient, 60, 10).unwrap();
+    let bytes = partial.serialize();
+    let mut partial = PartialTransaction::deserialize(bytes).unwrap();
+    assert_eq!(online.sign_partial(&mut partial.clone()).unwrap(), 0);
+    assert_eq!(offline.sign_partial(&mut partial).unwrap(), 2);
+
+    // Change goes back to a watched key
+    let transaction = partial.finalize().unwrap();
+    assert!(transaction
+        .outputs()
+        .iter()
+        .all(|output| output.utxo_data().pays(&recipient) || online.tracks(output.key_hash())));
+    let mut blockchain = blockchain;
+    let block = Block::new(blockchain.top(), vec![transaction]).unwrap();
+    blockchain.push(block).unwrap();
+}
+
+#[test]
+fn wallets_sign_a_joint_transaction() {
+    let (mut blockchain, utxo, sender) = funded_blockchain(100);
+    let mut wallet1 = Wallet::from_mnemonic(Mnemonic::generate(), "", Vec::new());
+    let mut wallet2 = Wallet::from_mnemonic(Mnemonic::generate(), "", Vec::new());
+    let recipients = [
+        wallet1.receive_key().unwrap(),
+        wallet2.receive_key().unwrap(),
+    ];
+    pay(&mut blockchain, &utxo, &sender, &recipients);
+    wallet1.process(blockchain.top());
+    wallet2.process(blockchain.top());
+
+    let inputs: Vec<Utxo> = wallet1
+        .utxos()
+        .iter()
+        .chain(wallet2.utxos())
+        .copied()
+        .collect();
+    let outputs = vec![TransactionOutput::new(100, common::random_public_key())];
+    let mut partial1 = PartialTransaction::new(inputs, outputs);
+    let mut partial2 = PartialTransaction::deserialize(partial1.serialize()).unwrap();
+    assert_eq!(wallet1.sign_partial(&mut partial1).unwrap(), 1);
+    assert_eq!(wallet2.sign_partial(&mut partial2).unwrap(), 1);
+    partial1.combine(&partial2).unwrap();
+    let transaction = partial1.finalize().unwrap();
+    let block = Block::new(blockchain.top(), vec![transaction]).unwrap();
+    blockchain.push(block).unwrap();
+    wallet1.process(blockchain.top());
+    assert!(wallet1.utxos().is_empty());
+
+    // A wallet refuses to sign for one of its keys a utxo it does not know
+    let utxo2 = *wallet2.utxos().iter().next().unwrap();
+    let inflated = Utxo::new(*utxo2.utxo_id(), UtxoData::new(100, recipients[1]));
+    let mut partial = PartialTransaction::new(vec![inflated], Vec::new());
+    assert!(matches!(
+        wallet2.sign_partial(&mut partial),
+        Err(WalletError::UnknownUtxo)
+    ));
+    let forged = Utxo::new(*utxo.utxo_id(), UtxoData::new(1, recipients[1]));
+    let mut partial = PartialTransaction::new(vec![forged], Vec::new());
+    assert!(matches!(
+        wallet2.sign_partial(&mut partial),
+        Err(WalletError::UnknownUtxo)
+    ));
+}
+
+#[test]
+fn pay_to_an_address() {
+    let (mut wallet, _) = funded_wallet();
+    let mut payee = Wallet::from_mnemonic(Mnemonic::generate(), "", Vec::new());
+    let address = payee.receive_address().unwrap().to_string();
+    let address: Address = address.parse().unwrap();
+    let transaction = wallet.create_payment(address, 20, 0).unwrap();
+    assert_eq!(transaction.outputs()[0].address(), address);
+    assert!(payee.owns(transaction.outputs()[0].key_hash()));
+    assert!(transaction.to_string().contains(&address.to_string()));
+}
+
+#[test]
+fn spend_schnorr_outputs() {
+    let (mut blockchain, utxo, sender) = funded_blockchain(100);
+    let (public_key, secret_key) = common::random_key();
+    let mut wallet = Wallet::new(public_key, secret_key, Vec::new(), HashSet::new());
+    wallet.set_output_type(OutputType::Schnorr);
+    let address = wallet.receive_address().unwrap();
+    assert_eq!(address.output_type(), OutputType::Schnorr);
+    let outputs = vec![TransactionOutput::new(100, address)];
+    let transaction = Transaction::sign(vec![*utxo.utxo_id()], outputs, &sender);
+    let block = Block::new(blockchain.top(), vec![transaction]).unwrap();
+    blockchain.push(block).unwrap();
+    wallet.process(blockchain.top());
+    let received: Vec<Utxo> = wallet.utxos().iter().copied().collect();
+    assert_eq!(received[0].output_type(), OutputType::Schnorr);
+
+    let payment = wallet
+        .create_payment(common::random_public_key(), 60, 0)
+        .unwrap();
+    assert!(payment
+        .outputs()
+        .iter()
+        .any(|output| output.output_type() == OutputType::Schnorr));
+    let utxo_pool = UtxoPool::from(received.into_iter().collect::<HashSet<_>>());
+    assert!(utxo_pool.authenticate(&payment).is_ok());
+    let block = Block::new(blockchain.top(), vec![payment]).unwrap();
+    assert!(utxo_pool.check_signatures_of(&block).is_ok());
+
+    // The output type is saved along with the wallet
+    wallet.encrypt("passphrase").unwrap();
+    let loaded = Wallet::deserialize(wallet.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.output_type(), OutputType::Schnorr);
+}